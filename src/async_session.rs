@@ -0,0 +1,618 @@
+//! An async API, mirroring `DriverSession` and `Element`.
+//!
+//! Requires the `async` cargo feature. Both APIs send the same commands:
+//! `DriverSession` blocks on them, while these types return their futures.
+//! By default commands are sent with the driver's
+//! `Driver::async_transport`, an `AsyncHttpTransport` with the driver's
+//! connection settings, whose futures must be polled within a tokio
+//! runtime.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use webdriver_client::{AsyncDriverSession, By, Error};
+//! # use webdriver_client::firefox::GeckoDriver;
+//! # use webdriver_client::messages::NewSessionCmd;
+//! # async fn example() -> Result<(), Error> {
+//! let driver = GeckoDriver::spawn()?;
+//! let sess = AsyncDriverSession::create_session(Box::new(driver),
+//!                                               &NewSessionCmd::default()).await?;
+//! sess.go("https://www.rust-lang.org").await?;
+//! let link = sess.find_element(&By::css("a")).await?;
+//! println!("{}", link.text().await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::*;
+
+/// An async WebDriver session.
+///
+/// Like `DriverSession`, this is a cheap, reference-counted handle. When
+/// the last handle is dropped inside a tokio runtime, deleting the remote
+/// session is spawned onto that runtime; call `delete()` to wait for it.
+#[derive(Clone)]
+pub struct AsyncDriverSession {
+    inner: Arc<SessionInner>,
+}
+
+impl AsyncDriverSession {
+    /// Create a new session with the driver.
+    pub async fn create_session(driver: Box<dyn Driver>, params: &NewSessionCmd)
+        -> Result<AsyncDriverSession, Error>
+    {
+        let transport = driver.async_transport()?;
+        Self::create_session_with_transport(driver, params, transport).await
+    }
+
+    /// Create a new session with the driver, sending commands through
+    /// `transport` instead of the driver's `async_transport()`.
+    pub async fn create_session_with_transport(driver: Box<dyn Driver>, params: &NewSessionCmd,
+                                               transport: Box<dyn AsyncTransport>)
+        -> Result<AsyncDriverSession, Error>
+    {
+        let inner = SessionInner::create(driver, params, transport, false).await?;
+        Ok(AsyncDriverSession { inner: Arc::new(inner) })
+    }
+
+    /// Use an existing session
+    pub async fn attach(url: &str, session_id: &str) -> Result<AsyncDriverSession, Error> {
+        info!("Connecting to session at {} with id {}", url, session_id);
+        let transport = AsyncHttpTransport::new(url)?;
+        let inner = SessionInner::attach(Box::new(transport), session_id,
+                                         !util::is_loopback_url(url), false).await?;
+        Ok(AsyncDriverSession { inner: Arc::new(inner) })
+    }
+
+    /// Use an existing session, sending commands through `transport`. The
+    /// session is taken to be local, see `is_remote`.
+    pub async fn attach_with_transport(transport: Box<dyn AsyncTransport>, session_id: &str)
+        -> Result<AsyncDriverSession, Error>
+    {
+        let inner = SessionInner::attach(transport, session_id, false, false).await?;
+        Ok(AsyncDriverSession { inner: Arc::new(inner) })
+    }
+
+    pub fn browser_name(&self) -> Option<&str> {
+        self.inner.browser_name()
+    }
+
+    /// Register a listener that is told about every command this session
+    /// sends from now on, see `DriverSession::add_listener`.
+    pub fn add_listener<L: EventListener + 'static>(&self, listener: L) {
+        self.inner.add_listener(Arc::new(listener));
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.inner.session_id
+    }
+
+    /// Whether the driver runs on another machine. See `Driver::is_remote`.
+    pub fn is_remote(&self) -> bool {
+        self.inner.remote
+    }
+
+    /// Whether to remove the session when the last handle to it is
+    /// dropped, the default is true
+    pub fn drop_session(&mut self, drop: bool) {
+        self.inner.drop_session.store(drop, Ordering::SeqCst);
+    }
+
+    /// Delete the remote session now, instead of when the last handle is
+    /// dropped.
+    pub async fn delete(self) -> Result<(), Error> {
+        self.inner.delete().await
+    }
+
+    /// Navigate to the given URL
+    pub async fn go(&self, url: &str) -> Result<(), Error> {
+        self.inner.go(url).await
+    }
+
+    pub async fn get_current_url(&self) -> Result<String, Error> {
+        self.inner.get_current_url().await
+    }
+
+    pub async fn back(&self) -> Result<(), Error> {
+        self.inner.back().await
+    }
+
+    pub async fn forward(&self) -> Result<(), Error> {
+        self.inner.forward().await
+    }
+
+    pub async fn refresh(&self) -> Result<(), Error> {
+        self.inner.refresh().await
+    }
+
+    pub async fn get_page_source(&self) -> Result<String, Error> {
+        self.inner.get_page_source().await
+    }
+
+    pub async fn get_title(&self) -> Result<String, Error> {
+        self.inner.get_title().await
+    }
+
+    /// Get all cookies
+    pub async fn get_cookies(&self) -> Result<Vec<Cookie>, Error> {
+        self.inner.get_cookies().await
+    }
+
+    pub async fn get_window_handle(&self) -> Result<String, Error> {
+        self.inner.get_window_handle().await
+    }
+
+    pub async fn switch_window(&mut self, handle: &str) -> Result<(), Error> {
+        self.inner.switch_window(handle).await
+    }
+
+    pub async fn close_window(&mut self) -> Result<(), Error> {
+        self.inner.close_window().await
+    }
+
+    pub async fn get_window_handles(&self) -> Result<Vec<String>, Error> {
+        self.inner.get_window_handles().await
+    }
+
+    /// Dismiss an active dialog, if present.
+    ///
+    /// WebDriver spec: https://www.w3.org/TR/webdriver/#dismiss-alert
+    pub async fn dismiss_alert(&self) -> Result<(), Error> {
+        self.inner.dismiss_alert().await
+    }
+
+    /// Accept an active dialog, if present.
+    ///
+    /// WebDriver spec: https://www.w3.org/TR/webdriver/#accept-alert
+    pub async fn accept_alert(&self) -> Result<(), Error> {
+        self.inner.accept_alert().await
+    }
+
+    /// Get the message of an active dialog, if present.
+    ///
+    /// WebDriver spec: https://www.w3.org/TR/webdriver/#get-alert-text
+    pub async fn get_alert_text(&self) -> Result<String, Error> {
+        self.inner.get_alert_text().await
+    }
+
+    /// Set the text field of a user prompt.
+    ///
+    /// WebDriver spec: https://www.w3.org/TR/webdriver/#send-alert-text
+    pub async fn send_alert_text(&self, text: &str) -> Result<(), Error> {
+        self.inner.send_alert_text(text).await
+    }
+
+    /// Find the first element matching `by`.
+    pub async fn find_element(&self, by: &By) -> Result<AsyncElement, Error> {
+        self.find_element_below("", by).await
+    }
+
+    /// Find all elements matching `by`.
+    pub async fn find_elements(&self, by: &By) -> Result<Vec<AsyncElement>, Error> {
+        self.find_elements_below("", by).await
+    }
+
+    async fn find_element_below(&self, base: &str, by: &By) -> Result<AsyncElement, Error> {
+        let mut found = self.inner.find(base, by, false).await?;
+        Ok(AsyncElement::new(self, found.remove(0)))
+    }
+
+    async fn find_elements_below(&self, base: &str, by: &By) -> Result<Vec<AsyncElement>, Error> {
+        let found = self.inner.find(base, by, true).await?;
+        Ok(found.into_iter().map(|reference| AsyncElement::new(self, reference)).collect())
+    }
+
+    pub async fn execute(&self, script: ExecuteCmd) -> Result<JsonValue, Error> {
+        self.inner.execute(&script).await
+    }
+
+    pub async fn execute_async(&self, script: ExecuteCmd) -> Result<JsonValue, Error> {
+        self.inner.execute_async(&script).await
+    }
+
+    /// Run `script` with `args` and deserialize its result. See
+    /// `DriverSession::execute_typed`.
+    pub async fn execute_typed<A: ScriptArgs, R: DeserializeOwned>(&self, script: &str, args: A)
+    -> Result<R, Error>
+    {
+        let value = self.execute(ExecuteCmd { script: script.to_owned(),
+                                              args: args.into_script_args()? }).await?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Like `execute_typed`, for a script that reports its result by
+    /// calling the callback passed as its last argument.
+    pub async fn execute_async_typed<A: ScriptArgs, R: DeserializeOwned>(&self, script: &str,
+                                                                         args: A)
+    -> Result<R, Error>
+    {
+        let value = self.execute_async(ExecuteCmd { script: script.to_owned(),
+                                                    args: args.into_script_args()? }).await?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Evaluate `expression`, a JavaScript expression returning a
+    /// promise, and deserialize the value it resolves to. See
+    /// `DriverSession::evaluate_promise`.
+    pub async fn evaluate_promise<A: ScriptArgs, R: DeserializeOwned>(&self, expression: &str,
+                                                                      args: A)
+    -> Result<R, Error>
+    {
+        let outcome = self.execute_async_typed(&script::promise_script(expression), args).await?;
+        script::settle(outcome)
+    }
+
+    /// Like `execute`, with element, shadow root, window and frame
+    /// references in the result decoded into handles.
+    pub async fn execute_value(&self, script: ExecuteCmd) -> Result<AsyncScriptValue, Error> {
+        AsyncScriptValue::from_json(self, self.execute(script).await?)
+    }
+
+    /// Like `execute_async`, with element, shadow root, window and frame
+    /// references in the result decoded into handles.
+    pub async fn execute_async_value(&self, script: ExecuteCmd)
+        -> Result<AsyncScriptValue, Error>
+    {
+        AsyncScriptValue::from_json(self, self.execute_async(script).await?)
+    }
+
+    /// Valid values are element references as returned by AsyncElement::reference() or null to switch
+    /// to the top level frame
+    pub async fn switch_to_frame(&self, handle: JsonValue) -> Result<(), Error> {
+        self.inner.switch_to_frame(handle).await
+    }
+
+    pub async fn switch_to_parent_frame(&self) -> Result<(), Error> {
+        self.inner.switch_to_parent_frame().await
+    }
+
+    /// Take a screenshot of the current frame.
+    ///
+    /// WebDriver specification: https://www.w3.org/TR/webdriver/#take-screenshot
+    pub async fn screenshot(&self) -> Result<Screenshot, Error> {
+        self.inner.screenshot().await
+    }
+
+    /// Wait until `document.readyState` is `complete`, for example after
+    /// `go()` or a click that navigates.
+    ///
+    /// Uses the default timeout and poll interval of `DriverSession::wait()`.
+    pub async fn wait_for_document_ready(&self) -> Result<(), Error> {
+        self.wait_for_script("document to be ready".to_owned(),
+                             conditions::DOCUMENT_READY_SCRIPT, vec![]).await
+    }
+
+    /// Wait until the document has loaded and no `fetch` or
+    /// `XMLHttpRequest` has been in flight for `quiet_period`.
+    ///
+    /// See `conditions::network_idle` for how requests are tracked. Uses
+    /// the default timeout and poll interval of `DriverSession::wait()`.
+    pub async fn wait_for_network_idle(&self, quiet_period: Duration) -> Result<(), Error> {
+        let quiet_ms = quiet_period.as_secs() * 1000 + u64::from(quiet_period.subsec_millis());
+        self.wait_for_script(format!("network to be idle for {:?}", quiet_period),
+                             conditions::NETWORK_IDLE_SCRIPT, vec![json!(quiet_ms)]).await
+    }
+
+    /// Poll `script` until it returns `true`.
+    async fn wait_for_script(&self, message: String, script: &str, args: Vec<JsonValue>)
+        -> Result<(), Error>
+    {
+        let check = || async {
+            let cmd = ExecuteCmd { script: script.to_owned(), args: args.clone() };
+            Ok(if self.execute(cmd).await? == JsonValue::Bool(true) { Some(()) } else { None })
+        };
+        match wait::poll_async(wait::DEFAULT_TIMEOUT, wait::DEFAULT_POLL_INTERVAL, check).await? {
+            Some(()) => Ok(()),
+            None => Err(Error::WaitTimeout(message)),
+        }
+    }
+}
+
+/// An HTML element within an `AsyncDriverSession`.
+#[derive(Clone)]
+pub struct AsyncElement {
+    session: AsyncDriverSession,
+    reference: String,
+}
+
+impl AsyncElement {
+    pub fn new(s: &AsyncDriverSession, reference: String) -> Self {
+        AsyncElement { session: s.clone(), reference }
+    }
+
+    /// The session this element belongs to.
+    pub fn session(&self) -> &AsyncDriverSession {
+        &self.session
+    }
+
+    pub async fn attribute(&self, name: &str) -> Result<String, Error> {
+        self.session.inner.element_attribute(&self.reference, name).await
+    }
+
+    /// Return this element's property value.
+    ///
+    /// WebDriver spec: https://www.w3.org/TR/webdriver/#get-element-property
+    pub async fn property(&self, name: &str) -> Result<String, Error> {
+        self.session.inner.element_property(&self.reference, name).await
+    }
+
+    /// Click this element.
+    ///
+    /// WebDriver spec: https://www.w3.org/TR/webdriver/#element-click
+    pub async fn click(&self) -> Result<(), Error> {
+        self.session.inner.element_click(&self.reference).await
+    }
+
+    /// Clear the text of this element.
+    ///
+    /// WebDriver spec: https://www.w3.org/TR/webdriver/#element-clear
+    pub async fn clear(&self) -> Result<(), Error> {
+        self.session.inner.element_clear(&self.reference).await
+    }
+
+    /// Send key presses to this element.
+    ///
+    /// WebDriver spec: https://www.w3.org/TR/webdriver/#element-send-keys
+    pub async fn send_keys(&self, s: &str) -> Result<(), Error> {
+        self.session.inner.element_send_keys(&self.reference, s).await
+    }
+
+    pub async fn css_value(&self, name: &str) -> Result<String, Error> {
+        self.session.inner.element_css_value(&self.reference, name).await
+    }
+
+    pub async fn text(&self) -> Result<String, Error> {
+        self.session.inner.element_text(&self.reference).await
+    }
+
+    /// Whether this element is visible on the page.
+    pub async fn is_displayed(&self) -> Result<bool, Error> {
+        self.session.inner.element_is_displayed(&self.reference).await
+    }
+
+    /// Whether this element is enabled, i.e. not a disabled form control.
+    ///
+    /// WebDriver spec: https://www.w3.org/TR/webdriver/#is-element-enabled
+    pub async fn is_enabled(&self) -> Result<bool, Error> {
+        self.session.inner.element_is_enabled(&self.reference).await
+    }
+
+    /// Whether this element, an option, checkbox or radio button, is
+    /// selected.
+    ///
+    /// WebDriver spec: https://www.w3.org/TR/webdriver/#is-element-selected
+    pub async fn is_selected(&self) -> Result<bool, Error> {
+        self.session.inner.element_is_selected(&self.reference).await
+    }
+
+    /// Returns the tag name for this element
+    pub async fn name(&self) -> Result<String, Error> {
+        self.session.inner.element_name(&self.reference).await
+    }
+
+    /// Find the first element below this one matching `by`.
+    pub async fn find_element(&self, by: &By) -> Result<AsyncElement, Error> {
+        self.session.find_element_below(&format!("/element/{}", self.reference), by).await
+    }
+
+    /// Find all elements below this one matching `by`.
+    pub async fn find_elements(&self, by: &By) -> Result<Vec<AsyncElement>, Error> {
+        self.session.find_elements_below(&format!("/element/{}", self.reference), by).await
+    }
+
+    /// Returns a reference that can be passed on to the API
+    pub fn reference(&self) -> Result<JsonValue, Error> {
+        serde_json::to_value(ElementReference::from_str(&self.reference))
+            .map_err(Error::from)
+    }
+
+    /// The raw reference id that identifies this element, this can be used
+    /// with AsyncElement::new()
+    pub fn raw_reference(&self) -> &str { &self.reference }
+
+    /// Gets the `innerHTML` javascript attribute for this element, see
+    /// `Element::inner_html`.
+    pub async fn inner_html(&self) -> Result<JsonValue, Error> {
+        self.session.inner.element_script(&self.reference, "return arguments[0].innerHTML;").await
+    }
+
+    pub async fn outer_html(&self) -> Result<JsonValue, Error> {
+        self.session.inner.element_script(&self.reference, "return arguments[0].outerHTML;").await
+    }
+
+    /// Take a screenshot of this element
+    ///
+    /// WebDriver specification: https://www.w3.org/TR/webdriver/#take-element-screenshot
+    pub async fn screenshot(&self) -> Result<Screenshot, Error> {
+        self.session.inner.element_screenshot(&self.reference).await
+    }
+}
+
+impl fmt::Debug for AsyncElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WebDriver Element with remote reference {}", self.reference)
+    }
+}
+
+/// A shadow root within an `AsyncDriverSession`, as returned by
+/// `AsyncDriverSession::execute_value`.
+#[derive(Clone)]
+pub struct AsyncShadowRoot {
+    session: AsyncDriverSession,
+    reference: String,
+}
+
+impl AsyncShadowRoot {
+    pub fn new(s: &AsyncDriverSession, reference: String) -> Self {
+        AsyncShadowRoot { session: s.clone(), reference }
+    }
+
+    /// The session this shadow root belongs to.
+    pub fn session(&self) -> &AsyncDriverSession {
+        &self.session
+    }
+
+    /// WebDriver spec: https://www.w3.org/TR/webdriver/#find-element-from-shadow-root
+    pub async fn find_element(&self, by: &By) -> Result<AsyncElement, Error> {
+        self.session.find_element_below(&format!("/shadow/{}", self.reference), by).await
+    }
+
+    /// WebDriver spec: https://www.w3.org/TR/webdriver/#find-elements-from-shadow-root
+    pub async fn find_elements(&self, by: &By) -> Result<Vec<AsyncElement>, Error> {
+        self.session.find_elements_below(&format!("/shadow/{}", self.reference), by).await
+    }
+
+    /// The raw reference id that identifies this shadow root.
+    pub fn raw_reference(&self) -> &str { &self.reference }
+}
+
+impl fmt::Debug for AsyncShadowRoot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WebDriver ShadowRoot with remote reference {}", self.reference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AsyncDriverSession;
+    use crate::{By, Driver, Element, HttpDriverBuilder, JsonValue};
+    use crate::messages::{ExecuteCmd, NewSessionCmd};
+    use crate::testing::{element, FakeServer};
+    use crate::transport::Method;
+
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn server() -> FakeServer {
+        let server = FakeServer::new();
+        server.respond(Method::Post, "/url", JsonValue::Null);
+        server.respond(Method::Get, "/title", json!("Fake"));
+        server.respond(Method::Post, "/element", element("e1"));
+        server.respond(Method::Post, "/element/e1/value", JsonValue::Null);
+        server.respond(Method::Get, "/element/e1/selected", json!(true));
+        server
+    }
+
+    #[test]
+    fn sends_the_same_commands_as_blocking_api() {
+        let blocking = server();
+        let sess = blocking.session();
+        sess.go("https://example.com").unwrap();
+        assert_eq!(sess.get_title().unwrap(), "Fake");
+        let input: Element = sess.find_element(&By::css("input")).unwrap();
+        input.send_keys("hi").unwrap();
+        assert!(input.is_selected().unwrap());
+        drop((sess, input));
+
+        let async_server = server();
+        let transport = Box::new(async_server.clone());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async move {
+            // Spawned to check that the futures are `Send`.
+            tokio::spawn(async move {
+                let driver = HttpDriverBuilder::default().url("http://fake").build().unwrap();
+                let sess = AsyncDriverSession::create_session_with_transport(
+                    Box::new(driver), &NewSessionCmd::default(), transport).await.unwrap();
+                sess.go("https://example.com").await.unwrap();
+                assert_eq!(sess.get_title().await.unwrap(), "Fake");
+                let input = sess.find_element(&By::css("input")).await.unwrap();
+                input.send_keys("hi").await.unwrap();
+                assert!(input.is_selected().await.unwrap());
+                sess.delete().await.unwrap();
+            }).await.unwrap();
+        });
+
+        assert_eq!(async_server.calls(), blocking.calls());
+    }
+
+    fn script_server() -> FakeServer {
+        let server = FakeServer::new();
+        server.on(Method::Post, "/execute/sync", |call| {
+            match call.body["args"].as_array().map(Vec::len) {
+                Some(0) => json!(true),
+                _ => json!([element("e1"), call.body["args"][0]]),
+            }
+        });
+        server.respond(Method::Post, "/execute/async", json!({ "resolved": true, "value": 3 }));
+        server
+    }
+
+    #[test]
+    fn scripts_and_waits_match_blocking_api() {
+        let blocking = script_server();
+        let sess = blocking.session();
+        let ready: bool = sess.execute_typed("return true;", ()).unwrap();
+        assert!(ready);
+        let value = sess.execute_value(ExecuteCmd { script: "return x;".to_owned(),
+                                                    args: vec![json!(1)] }).unwrap();
+        let input = value.as_array().unwrap()[0].as_element().unwrap().clone();
+        sess.execute_typed::<_, JsonValue>("return x;", (&input,)).unwrap();
+        assert_eq!(sess.evaluate_promise::<_, u32>("Promise.resolve(3)", ()).unwrap(), 3);
+        sess.wait_for_document_ready().unwrap();
+        drop((sess, value, input));
+
+        let async_server = script_server();
+        let transport = Box::new(async_server.clone());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async move {
+            tokio::spawn(async move {
+                let driver = HttpDriverBuilder::default().url("http://fake").build().unwrap();
+                let sess = AsyncDriverSession::create_session_with_transport(
+                    Box::new(driver), &NewSessionCmd::default(), transport).await.unwrap();
+                let ready: bool = sess.execute_typed("return true;", ()).await.unwrap();
+                assert!(ready);
+                let value = sess.execute_value(ExecuteCmd { script: "return x;".to_owned(),
+                                                            args: vec![json!(1)] }).await.unwrap();
+                let input = value.as_array().unwrap()[0].as_element().unwrap().clone();
+                assert_eq!(input.raw_reference(), "e1");
+                sess.execute_typed::<_, JsonValue>("return x;", (&input,)).await.unwrap();
+                assert_eq!(sess.evaluate_promise::<_, u32>("Promise.resolve(3)", ())
+                               .await.unwrap(), 3);
+                sess.wait_for_document_ready().await.unwrap();
+                sess.delete().await.unwrap();
+            }).await.unwrap();
+        });
+
+        assert_eq!(async_server.calls(), blocking.calls());
+    }
+
+    /// A driver that records when it is dropped, as a local driver's
+    /// process would be stopped.
+    struct LoggingDriver {
+        log: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl Driver for LoggingDriver {
+        fn url(&self) -> &str {
+            "http://fake"
+        }
+    }
+
+    impl Drop for LoggingDriver {
+        fn drop(&mut self) {
+            self.log.lock().unwrap().push("driver dropped");
+        }
+    }
+
+    #[test]
+    fn driver_outlives_the_delete() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let server = FakeServer::new();
+        let delete_log = log.clone();
+        server.on(Method::Delete, "", move |_| {
+            delete_log.lock().unwrap().push("delete");
+            JsonValue::Null
+        });
+        let driver = Box::new(LoggingDriver { log: log.clone() });
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async move {
+            let sess = AsyncDriverSession::create_session_with_transport(
+                driver, &NewSessionCmd::default(), Box::new(server)).await.unwrap();
+            drop(sess);
+            while log.lock().unwrap().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+            assert_eq!(*log.lock().unwrap(), ["delete", "driver dropped"]);
+        });
+    }
+}
@@ -1,6 +1,6 @@
 extern crate webdriver_client;
 use webdriver_client::*;
-use webdriver_client::messages::{LocationStrategy, ExecuteCmd};
+use webdriver_client::messages::ExecuteCmd;
 use webdriver_client::firefox::GeckoDriver;
 use webdriver_client::chrome::ChromeDriver;
 
@@ -21,17 +21,17 @@ fn execute_function(name: &str, args: &str, sess: &DriverSession) -> Result<(),
         "source" => println!("{}", sess.get_page_source()?),
         "url" => println!("{}", sess.get_current_url()?),
         "innerhtml" => {
-            for (idx, elem) in sess.find_elements(args, LocationStrategy::Css)?.iter().enumerate() {
+            for (idx, elem) in sess.find_elements(&By::css(args))?.iter().enumerate() {
                 println!("#{} {}", idx, elem.inner_html()?);
             }
         }
         "outerhtml" => {
-            for (idx, elem) in sess.find_elements(args, LocationStrategy::Css)?.iter().enumerate() {
+            for (idx, elem) in sess.find_elements(&By::css(args))?.iter().enumerate() {
                 println!("#{} {}", idx, elem.outer_html()?);
             }
         }
         "frames" => {
-            for (idx, elem) in sess.find_elements("iframe", LocationStrategy::Css)?.iter().enumerate() {
+            for (idx, elem) in sess.find_elements(&By::css("iframe"))?.iter().enumerate() {
                 println!("#{} {}", idx, elem.raw_reference());
             }
         }
@@ -0,0 +1,298 @@
+//! Record WebDriver traffic to a file and replay it without a browser.
+//!
+//! `RecordingTransport` wraps another transport and writes each request
+//! and response to a JSON cassette file. `ReplayTransport` answers
+//! requests from a cassette, so code using a `DriverSession` can be tested
+//! offline and deterministically.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use webdriver_client::{Driver, DriverSession, HttpDriverBuilder};
+//! # use webdriver_client::cassette::{Matching, RecordingTransport, ReplayTransport};
+//! # use webdriver_client::messages::NewSessionCmd;
+//! // Once, against a real browser:
+//! let driver = HttpDriverBuilder::default().url("http://localhost:4444").build().unwrap();
+//! let transport = RecordingTransport::new(driver.transport().unwrap(), "login.json").unwrap();
+//! let sess = DriverSession::create_session_with_transport(
+//!     Box::new(driver), &NewSessionCmd::default(), Box::new(transport)).unwrap();
+//!
+//! // In tests:
+//! let driver = HttpDriverBuilder::default().url("http://localhost:4444").build().unwrap();
+//! let transport = ReplayTransport::from_file("login.json", Matching::Strict).unwrap();
+//! let sess = DriverSession::create_session_with_transport(
+//!     Box::new(driver), &NewSessionCmd::default(), Box::new(transport)).unwrap();
+//! ```
+
+use super::{Error, JsonValue};
+use crate::transport::{Method, Response, Transport};
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A recorded sequence of requests and responses.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+/// One request and the response it received.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// `"GET"`, `"POST"` or `"DELETE"`.
+    pub method: String,
+    pub path: String,
+    /// The JSON body, `{"raw": text}` if it was not JSON, or `null` if
+    /// there was none.
+    pub body: JsonValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    /// The JSON body, or `{"raw": text}` if it was not JSON.
+    pub body: JsonValue,
+}
+
+/// Bodies are stored as JSON values to keep cassettes readable. A body
+/// that is not valid JSON is stored as its text, tagged as
+/// `{"raw": text}`. So that the tag is unambiguous, a JSON body that looks
+/// like a tag is stored as raw text too.
+fn body_to_json(body: &str) -> JsonValue {
+    match serde_json::from_str(body) {
+        Ok(value) if !is_raw_tag(&value) => value,
+        _ => json!({ "raw": body }),
+    }
+}
+
+/// The body text that `body_to_json` stored as `value`.
+fn json_to_body(value: &JsonValue) -> String {
+    match (is_raw_tag(value), &value["raw"]) {
+        (true, JsonValue::String(text)) => text.clone(),
+        _ => value.to_string(),
+    }
+}
+
+fn is_raw_tag(value: &JsonValue) -> bool {
+    value.as_object().is_some_and(|map| map.len() == 1 && map.contains_key("raw"))
+}
+
+impl Cassette {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+/// Sends requests through another transport and records them, with their
+/// responses, to a cassette file.
+///
+/// The file is rewritten after every request, so it is complete even if
+/// the test panics. Requests that fail without a response, for example
+/// because the server could not be reached, are not recorded.
+pub struct RecordingTransport {
+    inner: Box<dyn Transport>,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl RecordingTransport {
+    /// Record requests sent through `inner` to the file at `path`, which is
+    /// created or truncated immediately.
+    pub fn new<P: Into<PathBuf>>(inner: Box<dyn Transport>, path: P) -> Result<Self, Error> {
+        let path = path.into();
+        let cassette = Cassette::default();
+        cassette.save(&path)?;
+        Ok(RecordingTransport {
+            inner,
+            path,
+            cassette: Mutex::new(cassette),
+        })
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn send(&self, method: Method, path: &str, body: Option<&str>) -> Result<Response, Error> {
+        let res = self.inner.send(method, path, body)?;
+        let mut cassette = self.cassette.lock().unwrap();
+        cassette.interactions.push(Interaction {
+            request: RecordedRequest {
+                method: method.as_str().to_owned(),
+                path: path.to_owned(),
+                body: body.map(body_to_json).unwrap_or(JsonValue::Null),
+            },
+            response: RecordedResponse {
+                status: res.status,
+                body: body_to_json(&res.body),
+            },
+        });
+        cassette.save(&self.path)?;
+        Ok(res)
+    }
+}
+
+/// How `ReplayTransport` picks the recorded response for a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Matching {
+    /// Requests must arrive in the recorded order, and match the method,
+    /// path and body of the next recorded request exactly.
+    Strict,
+    /// Each request is answered by the first unused recording with the same
+    /// method and path, in any order. Bodies are not compared.
+    Lenient,
+}
+
+/// Answers requests from a cassette instead of a WebDriver server.
+///
+/// A request with no matching recording fails with
+/// `Error::ReplayMismatch`. Each recording is used at most once.
+pub struct ReplayTransport {
+    matching: Matching,
+    /// Recordings not yet used.
+    remaining: Mutex<Vec<Interaction>>,
+}
+
+impl ReplayTransport {
+    pub fn new(cassette: Cassette, matching: Matching) -> Self {
+        ReplayTransport {
+            matching,
+            remaining: Mutex::new(cassette.interactions),
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P, matching: Matching) -> Result<Self, Error> {
+        Ok(Self::new(Cassette::load(path)?, matching))
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, method: Method, path: &str, body: Option<&str>) -> Result<Response, Error> {
+        let body = body.map(body_to_json).unwrap_or(JsonValue::Null);
+        let same_route = |req: &RecordedRequest| req.method == method.as_str() && req.path == path;
+
+        let mut remaining = self.remaining.lock().unwrap();
+        let index = match self.matching {
+            Matching::Strict => remaining.first()
+                .filter(|i| same_route(&i.request) && i.request.body == body)
+                .map(|_| 0),
+            Matching::Lenient => remaining.iter().position(|i| same_route(&i.request)),
+        };
+        let interaction = match index {
+            Some(index) => remaining.remove(index),
+            None => {
+                let expected = match remaining.first() {
+                    Some(next) => format!("next recording is {} {}",
+                                          next.request.method, next.request.path),
+                    None => "no recordings left".to_owned(),
+                };
+                return Err(Error::ReplayMismatch(format!(
+                    "no recording for {} {} with body {}; {}",
+                    method.as_str(), path, body, expected)));
+            }
+        };
+        Ok(Response {
+            status: interaction.response.status,
+            body: json_to_body(&interaction.response.body),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{body_to_json, json_to_body, Cassette, Matching, RecordingTransport,
+                ReplayTransport};
+    use super::super::{DriverSession, Error, JsonValue};
+    use crate::testing::{self, FakeServer};
+    use crate::transport::{Method, Transport};
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    fn session(transport: Box<dyn Transport>) -> DriverSession {
+        testing::session_with("http://fake", transport)
+    }
+
+    fn record(name: &str) -> Cassette {
+        let path = env::temp_dir()
+            .join(format!("webdriver_client-{}-{}.json", name, process::id()));
+        let server = FakeServer::new();
+        server.respond(Method::Post, "/url", JsonValue::Null);
+        server.respond(Method::Get, "/title", json!("Recorded"));
+        let transport = RecordingTransport::new(Box::new(server), &path).unwrap();
+        let sess = session(Box::new(transport));
+        sess.go("http://example.com/").unwrap();
+        assert_eq!(sess.get_title().unwrap(), "Recorded");
+        drop(sess);
+
+        let cassette = Cassette::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        cassette
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let cassette = record("replay");
+        let routes: Vec<_> = cassette.interactions.iter()
+            .map(|i| format!("{} {}", i.request.method, i.request.path))
+            .collect();
+        assert_eq!(routes, vec!["POST /session", "POST /session/1234/url",
+                                "GET /session/1234/title", "DELETE /session/1234"]);
+        assert_eq!(cassette.interactions[1].request.body, json!({ "url": "http://example.com/" }));
+
+        let sess = session(Box::new(ReplayTransport::new(cassette, Matching::Strict)));
+        sess.go("http://example.com/").unwrap();
+        assert_eq!(sess.get_title().unwrap(), "Recorded");
+    }
+
+    #[test]
+    fn strict_replay_checks_body_and_order() {
+        let cassette = record("strict");
+        let sess = session(Box::new(ReplayTransport::new(cassette.clone(), Matching::Strict)));
+        match sess.go("http://other.com/") {
+            Err(Error::ReplayMismatch(_)) => {}
+            other => panic!("Want ReplayMismatch, got {:?}", other),
+        }
+
+        let sess = session(Box::new(ReplayTransport::new(cassette, Matching::Strict)));
+        match sess.get_title() {
+            Err(Error::ReplayMismatch(msg)) =>
+                assert!(msg.contains("next recording is POST /session/1234/url"), "{}", msg),
+            other => panic!("Want ReplayMismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn lenient_replay_ignores_body_and_order() {
+        let cassette = record("lenient");
+        let sess = session(Box::new(ReplayTransport::new(cassette, Matching::Lenient)));
+        assert_eq!(sess.get_title().unwrap(), "Recorded");
+        sess.go("http://other.com/").unwrap();
+        // Each recording is used once.
+        assert!(sess.get_title().is_err());
+    }
+
+    #[test]
+    fn bodies_round_trip() {
+        assert_eq!(body_to_json(r#"{"value":null}"#), json!({ "value": null }));
+        assert_eq!(body_to_json(r#""x""#), json!("x"));
+        assert_eq!(body_to_json("x"), json!({ "raw": "x" }));
+        assert_eq!(body_to_json(r#"{"raw":1}"#), json!({ "raw": r#"{"raw":1}"# }));
+        for body in &[r#"{"value":null}"#, r#""x""#, "x", "", r#"{"raw":1}"#] {
+            assert_eq!(json_to_body(&body_to_json(body)), *body);
+        }
+    }
+}
@@ -13,6 +13,7 @@ pub struct ChromeDriverBuilder {
     driver_binary: OsString,
     port: Option<u16>,
     kill_on_drop: bool,
+    http_config: HttpConfig,
 }
 
 impl ChromeDriverBuilder {
@@ -21,6 +22,7 @@ impl ChromeDriverBuilder {
             driver_binary: "chromedriver".into(),
             port: None,
             kill_on_drop: true,
+            http_config: HttpConfig::default(),
         }
     }
     pub fn driver_path<S: Into<OsString>>(mut self, path: S) -> Self {
@@ -35,6 +37,11 @@ impl ChromeDriverBuilder {
         self.kill_on_drop = kill;
         self
     }
+    /// Connection settings, such as timeouts, for sessions on this driver.
+    pub fn http_config(mut self, config: HttpConfig) -> Self {
+        self.http_config = config;
+        self
+    }
     pub fn spawn(self) -> Result<ChromeDriver, Error> {
         let port = util::check_tcp_port(self.port)?;
 
@@ -51,6 +58,7 @@ impl ChromeDriverBuilder {
             child: child,
             url: format!("http://localhost:{}", port),
             kill_on_drop: self.kill_on_drop,
+            http_config: self.http_config,
         })
     }
 }
@@ -61,6 +69,7 @@ pub struct ChromeDriver {
     child: Child,
     url: String,
     kill_on_drop: bool,
+    http_config: HttpConfig,
 }
 
 impl ChromeDriver {
@@ -84,4 +93,13 @@ impl Driver for ChromeDriver {
     fn url(&self) -> &str {
         &self.url
     }
+
+    fn transport(&self) -> Result<Box<dyn Transport>, Error> {
+        Ok(Box::new(HttpTransport::with_config(&self.url, &self.http_config)?))
+    }
+
+    #[cfg(feature = "async")]
+    fn async_transport(&self) -> Result<Box<dyn AsyncTransport>, Error> {
+        Ok(Box::new(AsyncHttpTransport::with_config(&self.url, &self.http_config)?))
+    }
 }
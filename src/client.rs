@@ -0,0 +1,471 @@
+//! The session state and commands shared by the blocking and async APIs.
+//!
+//! Every command is an `async` method of `SessionInner` that sends its
+//! request through an `AsyncTransport`. `AsyncDriverSession` awaits these
+//! methods, while `DriverSession` runs them to completion with `block_on`.
+//! A blocking session's transport is a `Transport`, whose futures are
+//! ready as soon as they are created, so `block_on` needs no runtime.
+
+use super::{By, Driver, Error, JsonValue, Screenshot};
+use crate::events::{self, Command, EventListener};
+use crate::messages::*;
+use crate::transport::{AsyncTransport, Method};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::Instant;
+
+/// Wakes a thread parked in `block_on`.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Run `future` to completion on the current thread.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// Decode the JSON body of a WebDriver response. The body of an
+/// unsuccessful response is decoded as a `WebDriverError`.
+fn decode_body<D: DeserializeOwned + Debug>(success: bool, data: &str) -> Result<D, Error> {
+    if !success {
+        let err: Value<WebDriverError> = serde_json::from_str(data)?;
+        trace!("deserialize error result: {:#?}", err);
+        return Err(Error::WebDriverError(err.value));
+    }
+    let response = serde_json::from_str(data);
+    trace!("deserialize result: {:#?}", response);
+    Ok(response?)
+}
+
+/// Wrapper around an `AsyncTransport`, that handles Json encoding and
+/// decoding and tells listeners about each command.
+pub(crate) struct HttpClient {
+    transport: Box<dyn AsyncTransport>,
+    listeners: RwLock<Vec<Arc<dyn EventListener>>>,
+}
+
+impl HttpClient {
+    pub fn new(transport: Box<dyn AsyncTransport>) -> Self {
+        HttpClient {
+            transport,
+            listeners: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub fn add_listener(&self, listener: Arc<dyn EventListener>) {
+        self.listeners.write().unwrap().push(listener);
+    }
+
+    async fn send<D: DeserializeOwned + Debug>(&self, method: Method, path: &str, body: Option<&str>)
+        -> Result<D, Error>
+    {
+        match body {
+            Some(body) => {
+                debug!("{} {}\n\
+                        body: {}", method.as_str(), path, body);
+            }
+            None => {
+                debug!("{} {}", method.as_str(), path);
+            }
+        }
+        // Cloned so listeners may add listeners without deadlocking.
+        let listeners = self.listeners.read().unwrap().clone();
+        let command = Command::new(method, path, body);
+        events::notify(|| listeners.iter().for_each(|l| l.before_command(&command)));
+
+        let start = Instant::now();
+        let result = self.transport.send_async(method, path, body).await.and_then(|res| {
+            debug!("result status: {}\n\
+                    body: '{}'", res.status, res.body);
+            let value = decode_body(res.is_success(), &res.body)?;
+            Ok((value, res))
+        });
+        let duration = start.elapsed();
+
+        match result {
+            Ok((value, res)) => {
+                events::notify(|| {
+                    listeners.iter().for_each(|l| l.after_command(&command, &res, duration))
+                });
+                Ok(value)
+            }
+            Err(err) => {
+                events::notify(|| {
+                    listeners.iter().for_each(|l| l.on_error(&command, &err, duration))
+                });
+                Err(err)
+            }
+        }
+    }
+
+    pub async fn get<D: DeserializeOwned + Debug>(&self, path: &str) -> Result<D, Error> {
+        self.send(Method::Get, path, None).await
+    }
+
+    pub async fn delete<D: DeserializeOwned + Debug>(&self, path: &str) -> Result<D, Error> {
+        self.send(Method::Delete, path, None).await
+    }
+
+    pub async fn post<D: DeserializeOwned + Debug, E: Serialize>(&self, path: &str, body: &E)
+        -> Result<D, Error>
+    {
+        let body_str = serde_json::to_string(body)?;
+        self.send(Method::Post, path, Some(&body_str)).await
+    }
+}
+
+/// The state shared by all handles to a session.
+pub(crate) struct SessionInner {
+    /// driver is kept so it is dropped when the session is dropped.
+    _driver: Option<Box<dyn Driver>>,
+    client: Arc<HttpClient>,
+    pub session_id: String,
+    pub drop_session: AtomicBool,
+    pub capabilities: BTreeMap<String, JsonValue>,
+    /// Whether the driver is on another machine, see `Driver::is_remote`.
+    pub remote: bool,
+    /// Whether this is a `DriverSession`, so the session can be deleted
+    /// on drop with `block_on`.
+    blocking: bool,
+}
+
+impl SessionInner {
+    /// Create a new session with the driver.
+    pub async fn create(driver: Box<dyn Driver>, params: &NewSessionCmd,
+                        transport: Box<dyn AsyncTransport>, blocking: bool)
+        -> Result<SessionInner, Error>
+    {
+        let client = HttpClient::new(transport);
+        info!("Creating session at {}", driver.url());
+        let resp: Value<Session> = client.post("/session", params).await?;
+        let sess = resp.value;
+        info!("Session {} created", sess.sessionId);
+        let remote = driver.is_remote();
+        Ok(SessionInner {
+            _driver: Some(driver),
+            client: Arc::new(client),
+            session_id: sess.sessionId,
+            drop_session: AtomicBool::new(true),
+            capabilities: sess.capabilities,
+            remote,
+            blocking,
+        })
+    }
+
+    /// Use an existing session.
+    pub async fn attach(transport: Box<dyn AsyncTransport>, session_id: &str, remote: bool,
+                        blocking: bool)
+        -> Result<SessionInner, Error>
+    {
+        let inner = SessionInner {
+            _driver: None,
+            client: Arc::new(HttpClient::new(transport)),
+            session_id: session_id.to_owned(),
+            // This starts as false to avoid triggering the deletion call in Drop
+            // if an error occurs
+            drop_session: AtomicBool::new(false),
+            capabilities: Default::default(),
+            remote,
+            blocking,
+        };
+
+        // FIXME /status would be preferable here to test the connection, but
+        // it does not seem to work for the current geckodriver
+
+        // We can fetch any value for the session to verify it exists.
+        // The page URL will work.
+        let _ = inner.get_current_url().await?;
+
+        info!("Connected to existing session {}", inner.session_id);
+        // The session exists, enable session deletion on Drop
+        inner.drop_session.store(true, Ordering::SeqCst);
+        Ok(inner)
+    }
+
+    pub fn add_listener(&self, listener: Arc<dyn EventListener>) {
+        self.client.add_listener(listener);
+    }
+
+    pub fn browser_name(&self) -> Option<&str> {
+        match self.capabilities.get("browserName") {
+            Some(JsonValue::String(val)) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// The path of `command` within this session, e.g. `/url`.
+    fn path(&self, command: &str) -> String {
+        format!("/session/{}{}", self.session_id, command)
+    }
+
+    async fn get_value<T: DeserializeOwned + Debug>(&self, command: &str) -> Result<T, Error> {
+        let v: Value<T> = self.client.get(&self.path(command)).await?;
+        Ok(v.value)
+    }
+
+    async fn post_empty<E: Serialize>(&self, command: &str, body: &E) -> Result<(), Error> {
+        let _: Empty = self.client.post(&self.path(command), body).await?;
+        Ok(())
+    }
+
+    /// Delete the remote session now, instead of on drop.
+    #[cfg(feature = "async")]
+    pub async fn delete(&self) -> Result<(), Error> {
+        self.drop_session.store(false, Ordering::SeqCst);
+        let _: Empty = self.client.delete(&self.path("")).await?;
+        Ok(())
+    }
+
+    /// Send a base64 encoded zip file to a Selenium server, returning the
+    /// path it saved the file to.
+    pub async fn upload_zip(&self, zip: &str) -> Result<String, Error> {
+        let v: Value<String> = self.client.post(&self.path("/se/file"), &json!({ "file": zip })).await?;
+        Ok(v.value)
+    }
+
+    pub async fn go(&self, url: &str) -> Result<(), Error> {
+        self.post_empty("/url", &GoCmd { url: url.to_string() }).await
+    }
+
+    pub async fn get_current_url(&self) -> Result<String, Error> {
+        self.get_value("/url").await
+    }
+
+    pub async fn back(&self) -> Result<(), Error> {
+        self.post_empty("/back", &Empty {}).await
+    }
+
+    pub async fn forward(&self) -> Result<(), Error> {
+        self.post_empty("/forward", &Empty {}).await
+    }
+
+    pub async fn refresh(&self) -> Result<(), Error> {
+        self.post_empty("/refresh", &Empty {}).await
+    }
+
+    pub async fn get_page_source(&self) -> Result<String, Error> {
+        self.get_value("/source").await
+    }
+
+    pub async fn get_title(&self) -> Result<String, Error> {
+        self.get_value("/title").await
+    }
+
+    pub async fn get_cookies(&self) -> Result<Vec<Cookie>, Error> {
+        self.get_value("/cookie").await
+    }
+
+    pub async fn get_window_handle(&self) -> Result<String, Error> {
+        self.get_value("/window").await
+    }
+
+    pub async fn switch_window(&self, handle: &str) -> Result<(), Error> {
+        self.post_empty("/window", &SwitchWindowCmd::from(handle)).await
+    }
+
+    pub async fn close_window(&self) -> Result<(), Error> {
+        let _: Empty = self.client.delete(&self.path("/window")).await?;
+        Ok(())
+    }
+
+    pub async fn get_window_handles(&self) -> Result<Vec<String>, Error> {
+        self.get_value("/window/handles").await
+    }
+
+    pub async fn dismiss_alert(&self) -> Result<(), Error> {
+        self.post_empty("/alert/dismiss", &Empty {}).await
+    }
+
+    pub async fn accept_alert(&self) -> Result<(), Error> {
+        self.post_empty("/alert/accept", &Empty {}).await
+    }
+
+    pub async fn get_alert_text(&self) -> Result<String, Error> {
+        self.get_value("/alert/text").await
+    }
+
+    pub async fn send_alert_text(&self, text: &str) -> Result<(), Error> {
+        self.post_empty("/alert/text", &SendAlertTextCmd { text: text.to_owned() }).await
+    }
+
+    pub async fn execute(&self, script: &ExecuteCmd) -> Result<JsonValue, Error> {
+        let v: Value<JsonValue> = self.client.post(&self.path("/execute/sync"), script).await?;
+        Ok(v.value)
+    }
+
+    pub async fn execute_async(&self, script: &ExecuteCmd) -> Result<JsonValue, Error> {
+        let v: Value<JsonValue> = self.client.post(&self.path("/execute/async"), script).await?;
+        Ok(v.value)
+    }
+
+    pub async fn switch_to_frame(&self, handle: JsonValue) -> Result<(), Error> {
+        self.post_empty("/frame", &SwitchFrameCmd::from(handle)).await
+    }
+
+    pub async fn switch_to_parent_frame(&self) -> Result<(), Error> {
+        self.post_empty("/frame/parent", &Empty {}).await
+    }
+
+    pub async fn screenshot(&self) -> Result<Screenshot, Error> {
+        Screenshot::from_string(self.get_value("/screenshot").await?)
+    }
+
+    /// Find the references of the elements matching `by` below `base`:
+    /// `""` for the whole document, or `/element/{id}` or `/shadow/{id}`.
+    /// Returns a single reference unless `all` is set.
+    pub async fn find(&self, base: &str, by: &By, all: bool) -> Result<Vec<String>, Error> {
+        self.locate(self.path(base), by, all).await.map_err(|err| by.annotate(err))
+    }
+
+    /// Resolve each of the steps of `by` within the element found by the
+    /// one before.
+    async fn locate(&self, mut base: String, by: &By, all: bool) -> Result<Vec<String>, Error> {
+        let steps = by.steps();
+        let (last, parents) = steps.split_last().expect("A locator has at least one step");
+        for step in parents {
+            let parent = self.locate_step(&base, step, false).await?.remove(0);
+            base = self.path(&format!("/element/{}", parent));
+        }
+        self.locate_step(&base, last, all).await
+    }
+
+    async fn locate_step(&self, base: &str, by: &By, all: bool) -> Result<Vec<String>, Error> {
+        let references = match by.index() {
+            None if !all => {
+                let v: Value<ElementReference> = self.client.post(&format!("{}/element", base), &by.cmd()).await?;
+                vec![v.value]
+            }
+            index => {
+                let v: Value<Vec<ElementReference>> = self.client.post(&format!("{}/elements", base), &by.cmd()).await?;
+                match index {
+                    Some(i) => vec![by.pick(v.value, i)?],
+                    None => v.value,
+                }
+            }
+        };
+        Ok(references.into_iter().map(|er| er.reference).collect())
+    }
+
+    /// The path of `command` for the element `reference`.
+    fn element_path(&self, reference: &str, command: &str) -> String {
+        self.path(&format!("/element/{}/{}", reference, command))
+    }
+
+    async fn element_value<T: DeserializeOwned + Debug>(&self, reference: &str, command: &str)
+        -> Result<T, Error>
+    {
+        let v: Value<T> = self.client.get(&self.element_path(reference, command)).await?;
+        Ok(v.value)
+    }
+
+    async fn element_post<E: Serialize>(&self, reference: &str, command: &str, body: &E)
+        -> Result<(), Error>
+    {
+        let _: Value<JsonValue> = self.client.post(&self.element_path(reference, command), body).await?;
+        Ok(())
+    }
+
+    pub async fn element_attribute(&self, reference: &str, name: &str) -> Result<String, Error> {
+        self.element_value(reference, &format!("attribute/{}", name)).await
+    }
+
+    pub async fn element_property(&self, reference: &str, name: &str) -> Result<String, Error> {
+        self.element_value(reference, &format!("property/{}", name)).await
+    }
+
+    pub async fn element_click(&self, reference: &str) -> Result<(), Error> {
+        self.element_post(reference, "click", &Empty {}).await
+    }
+
+    pub async fn element_clear(&self, reference: &str) -> Result<(), Error> {
+        self.element_post(reference, "clear", &Empty {}).await
+    }
+
+    pub async fn element_send_keys(&self, reference: &str, text: &str) -> Result<(), Error> {
+        self.element_post(reference, "value", &json!({ "text": text })).await
+    }
+
+    pub async fn element_css_value(&self, reference: &str, name: &str) -> Result<String, Error> {
+        self.element_value(reference, &format!("css/{}", name)).await
+    }
+
+    pub async fn element_text(&self, reference: &str) -> Result<String, Error> {
+        self.element_value(reference, "text").await
+    }
+
+    pub async fn element_is_displayed(&self, reference: &str) -> Result<bool, Error> {
+        self.element_value(reference, "displayed").await
+    }
+
+    pub async fn element_is_enabled(&self, reference: &str) -> Result<bool, Error> {
+        self.element_value(reference, "enabled").await
+    }
+
+    pub async fn element_is_selected(&self, reference: &str) -> Result<bool, Error> {
+        self.element_value(reference, "selected").await
+    }
+
+    pub async fn element_name(&self, reference: &str) -> Result<String, Error> {
+        self.element_value(reference, "name").await
+    }
+
+    pub async fn element_screenshot(&self, reference: &str) -> Result<Screenshot, Error> {
+        Screenshot::from_string(self.element_value(reference, "screenshot").await?)
+    }
+
+    /// Run `script` with the element `reference` as its only argument.
+    pub async fn element_script(&self, reference: &str, script: &str) -> Result<JsonValue, Error> {
+        let args = vec![serde_json::to_value(ElementReference::from_str(reference))?];
+        self.execute(&ExecuteCmd { script: script.to_owned(), args }).await
+    }
+}
+
+impl Drop for SessionInner {
+    fn drop(&mut self) {
+        if !self.drop_session.load(Ordering::SeqCst) {
+            return;
+        }
+        let path = self.path("");
+        if self.blocking {
+            let _: Result<Empty, _> = block_on(self.client.delete(&path));
+            return;
+        }
+        #[cfg(feature = "async")]
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                // The driver is dropped after the delete, as dropping a local
+                // driver stops its process.
+                let client = self.client.clone();
+                let driver = self._driver.take();
+                handle.spawn(async move {
+                    let _: Result<Empty, _> = client.delete(&path).await;
+                    drop(driver);
+                });
+            }
+            Err(_) => {
+                warn!("Session {} dropped outside of a tokio runtime: stopping its driver \
+                       without deleting the session", self.session_id);
+            }
+        }
+    }
+}
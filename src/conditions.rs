@@ -0,0 +1,284 @@
+//! Ready-made conditions for `Wait::until`.
+//!
+//! Each function returns a closure that checks the condition once. Errors
+//! that just mean "not yet", such as `no such element` while waiting for an
+//! element to appear, are treated as the condition not holding; other errors
+//! end the wait.
+
+use super::{By, DriverSession, Element, Error, JsonValue};
+use crate::messages::ExecuteCmd;
+
+use std::time::Duration;
+
+const NO_SUCH_ELEMENT: &str = "no such element";
+const STALE_ELEMENT: &str = "stale element reference";
+const NO_SUCH_ALERT: &str = "no such alert";
+const NO_SUCH_FRAME: &str = "no such frame";
+
+pub(crate) const DOCUMENT_READY_SCRIPT: &str = "return document.readyState === 'complete';";
+
+/// Installs counters around `fetch` and `XMLHttpRequest` the first time it
+/// runs in a document, then reports whether the document is loaded and no
+/// request has been in flight for `arguments[0]` milliseconds.
+pub(crate) const NETWORK_IDLE_SCRIPT: &str = r#"
+var quietMs = arguments[0];
+if (!window.__webdriverClientNetwork) {
+  var state = { inflight: 0, last: Date.now() };
+  window.__webdriverClientNetwork = state;
+  var started = function() { state.inflight++; state.last = Date.now(); };
+  var finished = function() {
+    state.inflight = Math.max(0, state.inflight - 1);
+    state.last = Date.now();
+  };
+  if (window.fetch) {
+    var origFetch = window.fetch;
+    window.fetch = function() {
+      started();
+      try {
+        return origFetch.apply(this, arguments).then(
+          function(res) { finished(); return res; },
+          function(err) { finished(); throw err; });
+      } catch (err) {
+        finished();
+        throw err;
+      }
+    };
+  }
+  if (window.XMLHttpRequest) {
+    var origSend = window.XMLHttpRequest.prototype.send;
+    window.XMLHttpRequest.prototype.send = function() {
+      started();
+      this.addEventListener('loadend', finished);
+      try {
+        return origSend.apply(this, arguments);
+      } catch (err) {
+        this.removeEventListener('loadend', finished);
+        finished();
+        throw err;
+      }
+    };
+  }
+}
+var net = window.__webdriverClientNetwork;
+return document.readyState === 'complete'
+    && net.inflight === 0
+    && Date.now() - net.last >= quietMs;
+"#;
+
+fn execute_bool(sess: &DriverSession, script: &str, args: Vec<JsonValue>) -> Result<bool, Error> {
+    let res = sess.execute(ExecuteCmd { script: script.to_owned(), args })?;
+    Ok(res == JsonValue::Bool(true))
+}
+
+/// Turn the listed WebDriver errors into `Ok(None)`.
+fn not_yet<T>(res: Result<Option<T>, Error>, codes: &[&str]) -> Result<Option<T>, Error> {
+    match res {
+        Err(ref err) if codes.iter().any(|code| err.is_webdriver_error(code)) => Ok(None),
+        other => other,
+    }
+}
+
+fn find(sess: &DriverSession, by: &By) -> Result<Option<Element>, Error> {
+    not_yet(sess.find_element(by).map(Some), &[NO_SUCH_ELEMENT])
+}
+
+fn find_visible(sess: &DriverSession, by: &By)
+    -> Result<Option<Element>, Error>
+{
+    match find(sess, by)? {
+        Some(elem) => Ok(if elem.is_displayed()? { Some(elem) } else { None }),
+        None => Ok(None),
+    }
+}
+
+fn find_clickable(sess: &DriverSession, by: &By)
+    -> Result<Option<Element>, Error>
+{
+    match find_visible(sess, by)? {
+        Some(elem) => Ok(if elem.is_enabled()? { Some(elem) } else { None }),
+        None => Ok(None),
+    }
+}
+
+fn find_hidden(sess: &DriverSession, by: &By)
+    -> Result<Option<()>, Error>
+{
+    match find(sess, by)? {
+        Some(elem) => Ok(if elem.is_displayed()? { None } else { Some(()) }),
+        None => Ok(Some(())),
+    }
+}
+
+fn find_with_text(sess: &DriverSession, by: &By, text: &str)
+    -> Result<Option<Element>, Error>
+{
+    match find(sess, by)? {
+        Some(elem) => Ok(if elem.text()?.contains(text) { Some(elem) } else { None }),
+        None => Ok(None),
+    }
+}
+
+fn switch_to_found_frame(sess: &DriverSession, by: &By)
+    -> Result<Option<()>, Error>
+{
+    match find(sess, by)? {
+        Some(frame) => {
+            sess.switch_to_frame(frame.reference()?)?;
+            Ok(Some(()))
+        }
+        None => Ok(None),
+    }
+}
+
+/// An element matching `by` is present in the DOM.
+pub fn element_present(by: &By)
+    -> impl Fn(&DriverSession) -> Result<Option<Element>, Error>
+{
+    let by = by.clone();
+    move |sess| find(sess, &by)
+}
+
+/// An element matching `by` is present and displayed.
+pub fn element_visible(by: &By)
+    -> impl Fn(&DriverSession) -> Result<Option<Element>, Error>
+{
+    let by = by.clone();
+    move |sess| not_yet(find_visible(sess, &by), &[STALE_ELEMENT])
+}
+
+/// An element matching `by` is displayed and enabled.
+pub fn element_clickable(by: &By)
+    -> impl Fn(&DriverSession) -> Result<Option<Element>, Error>
+{
+    let by = by.clone();
+    move |sess| not_yet(find_clickable(sess, &by), &[STALE_ELEMENT])
+}
+
+/// No element matching `by` is displayed: it is either absent
+/// from the DOM or hidden.
+pub fn element_invisible(by: &By)
+    -> impl Fn(&DriverSession) -> Result<Option<()>, Error>
+{
+    let by = by.clone();
+    move |sess| {
+        match find_hidden(sess, &by) {
+            // The element was removed between finding it and checking it.
+            Err(ref err) if err.is_webdriver_error(STALE_ELEMENT) => Ok(Some(())),
+            other => other,
+        }
+    }
+}
+
+/// The text of an element matching `by` contains `text`.
+pub fn text_present(by: &By, text: &str)
+    -> impl Fn(&DriverSession) -> Result<Option<Element>, Error>
+{
+    let by = by.clone();
+    let text = text.to_owned();
+    move |sess| not_yet(find_with_text(sess, &by, &text), &[STALE_ELEMENT])
+}
+
+/// The page title is exactly `title`.
+pub fn title_is(title: &str) -> impl Fn(&DriverSession) -> Result<Option<()>, Error> {
+    let title = title.to_owned();
+    move |sess| Ok(if sess.get_title()? == title { Some(()) } else { None })
+}
+
+/// The page title contains `fragment`. The full title is returned.
+pub fn title_contains(fragment: &str)
+    -> impl Fn(&DriverSession) -> Result<Option<String>, Error>
+{
+    let fragment = fragment.to_owned();
+    move |sess| {
+        let title = sess.get_title()?;
+        Ok(if title.contains(&fragment) { Some(title) } else { None })
+    }
+}
+
+/// The current URL is exactly `url`.
+pub fn url_is(url: &str) -> impl Fn(&DriverSession) -> Result<Option<()>, Error> {
+    let url = url.to_owned();
+    move |sess| Ok(if sess.get_current_url()? == url { Some(()) } else { None })
+}
+
+/// The current URL contains `fragment`. The full URL is returned.
+pub fn url_contains(fragment: &str)
+    -> impl Fn(&DriverSession) -> Result<Option<String>, Error>
+{
+    let fragment = fragment.to_owned();
+    url_matches(move |url| url.contains(&fragment))
+}
+
+/// `predicate` returns true for the current URL. The URL is returned.
+pub fn url_matches<F>(predicate: F)
+    -> impl Fn(&DriverSession) -> Result<Option<String>, Error>
+    where F: Fn(&str) -> bool
+{
+    move |sess| {
+        let url = sess.get_current_url()?;
+        Ok(if predicate(&url) { Some(url) } else { None })
+    }
+}
+
+/// A user prompt is open. Its text is returned.
+pub fn alert_present() -> impl Fn(&DriverSession) -> Result<Option<String>, Error> {
+    |sess| not_yet(sess.get_alert_text().map(Some), &[NO_SUCH_ALERT])
+}
+
+/// Exactly `count` windows are open.
+pub fn number_of_windows_is(count: usize)
+    -> impl Fn(&DriverSession) -> Result<Option<()>, Error>
+{
+    move |sess| Ok(if sess.get_window_handles()?.len() == count { Some(()) } else { None })
+}
+
+/// A frame element matching `by` is present. Once it is, the
+/// session is switched to that frame.
+pub fn frame_available_and_switch(by: &By)
+    -> impl Fn(&DriverSession) -> Result<Option<()>, Error>
+{
+    let by = by.clone();
+    move |sess| {
+        not_yet(switch_to_found_frame(sess, &by),
+                &[STALE_ELEMENT, NO_SUCH_FRAME])
+    }
+}
+
+/// `element` is no longer attached to the DOM, for example after
+/// navigating away or re-rendering.
+pub fn staleness_of(element: &Element)
+    -> impl Fn(&DriverSession) -> Result<Option<()>, Error>
+{
+    let element = element.clone();
+    move |_| {
+        match element.name() {
+            Ok(_) => Ok(None),
+            Err(ref err) if err.is_webdriver_error(STALE_ELEMENT) => Ok(Some(())),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// `document.readyState` is `complete`.
+pub fn document_ready() -> impl Fn(&DriverSession) -> Result<Option<()>, Error> {
+    |sess| Ok(if execute_bool(sess, DOCUMENT_READY_SCRIPT, vec![])? { Some(()) } else { None })
+}
+
+/// The document has loaded and no `fetch` or `XMLHttpRequest` has been in
+/// flight for `quiet_period`.
+///
+/// Requests are counted by instrumentation injected on the first check in
+/// each document, so requests started before then are not seen. The quiet
+/// period is measured from the injection at the earliest.
+pub fn network_idle(quiet_period: Duration)
+    -> impl Fn(&DriverSession) -> Result<Option<()>, Error>
+{
+    let quiet_ms = quiet_period.as_secs() * 1000 + u64::from(quiet_period.subsec_millis());
+    move |sess| {
+        Ok(if execute_bool(sess, NETWORK_IDLE_SCRIPT, vec![json!(quiet_ms)])? {
+            Some(())
+        } else {
+            None
+        })
+    }
+}
@@ -0,0 +1,210 @@
+//! Hooks that observe every command a `DriverSession` sends.
+//!
+//! Register an `EventListener` with `DriverSession::add_listener` to log
+//! commands, collect timings or react to failures.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate webdriver_client;
+//! # use webdriver_client::{DriverSession, Error};
+//! # use webdriver_client::events::{Command, EventListener};
+//! # use webdriver_client::transport::Response;
+//! # use std::time::Duration;
+//! struct Timings;
+//!
+//! impl EventListener for Timings {
+//!     fn after_command(&self, command: &Command, _: &Response, duration: Duration) {
+//!         println!("{} took {:?}", command.name, duration);
+//!     }
+//! }
+//!
+//! # fn example(sess: &DriverSession) {
+//! sess.add_listener(Timings);
+//! # }
+//! # fn main() {}
+//! ```
+//!
+//! A listener that needs to send commands of its own, for example to take
+//! a screenshot when a command fails, should hold a `WeakDriverSession`
+//! from `DriverSession::downgrade`. A strong handle would keep the session
+//! alive forever, as the session owns its listeners. Commands sent from
+//! within a listener callback are not reported to listeners.
+
+use super::Error;
+use crate::transport::{Method, Response};
+use crate::util;
+
+use std::cell::Cell;
+use std::time::Duration;
+
+/// A command about to be sent, or just sent, to the WebDriver server.
+#[derive(Debug, Clone, Copy)]
+pub struct Command<'a> {
+    /// The command name from the WebDriver specification, for example
+    /// `"Element Click"`, or `"Unknown Command"` if it is not recognised.
+    pub name: &'static str,
+    pub method: Method,
+    /// The path of the request, for example `/session/1234/title`.
+    pub path: &'a str,
+    /// The JSON encoded body, for POST requests.
+    pub body: Option<&'a str>,
+}
+
+impl<'a> Command<'a> {
+    pub fn new(method: Method, path: &'a str, body: Option<&'a str>) -> Self {
+        Command {
+            name: command_name(method, path),
+            method,
+            path,
+            body,
+        }
+    }
+}
+
+/// Observes the commands sent by a `DriverSession`.
+///
+/// All methods do nothing by default.
+pub trait EventListener: Send + Sync {
+    /// Called before `command` is sent.
+    fn before_command(&self, _command: &Command) {}
+
+    /// Called when `command` succeeded, with the server's response and the
+    /// time taken.
+    fn after_command(&self, _command: &Command, _response: &Response, _duration: Duration) {}
+
+    /// Called when `command` failed, either because the server returned
+    /// an error or because it could not be reached, with the time taken.
+    fn on_error(&self, _command: &Command, _error: &Error, _duration: Duration) {}
+}
+
+thread_local! {
+    static IN_LISTENER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` unless this thread is already inside a listener callback, so
+/// commands sent by listeners do not trigger listeners again.
+pub(crate) fn notify<F: FnOnce()>(f: F) {
+    if IN_LISTENER.with(|flag| flag.replace(true)) {
+        return;
+    }
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            IN_LISTENER.with(|flag| flag.set(false));
+        }
+    }
+    let _reset = Reset;
+    f();
+}
+
+/// Endpoints from the WebDriver specification. `{}` matches any single
+/// path segment.
+const COMMANDS: &[(Method, &str, &str)] = &[
+    (Method::Post, "/session", "New Session"),
+    (Method::Delete, "/session/{}", "Delete Session"),
+    (Method::Get, "/status", "Status"),
+    (Method::Get, "/session/{}/timeouts", "Get Timeouts"),
+    (Method::Post, "/session/{}/timeouts", "Set Timeouts"),
+    (Method::Post, "/session/{}/url", "Navigate To"),
+    (Method::Get, "/session/{}/url", "Get Current URL"),
+    (Method::Post, "/session/{}/back", "Back"),
+    (Method::Post, "/session/{}/forward", "Forward"),
+    (Method::Post, "/session/{}/refresh", "Refresh"),
+    (Method::Get, "/session/{}/title", "Get Title"),
+    (Method::Get, "/session/{}/window", "Get Window Handle"),
+    (Method::Delete, "/session/{}/window", "Close Window"),
+    (Method::Post, "/session/{}/window", "Switch To Window"),
+    (Method::Get, "/session/{}/window/handles", "Get Window Handles"),
+    (Method::Post, "/session/{}/window/new", "New Window"),
+    (Method::Post, "/session/{}/frame", "Switch To Frame"),
+    (Method::Post, "/session/{}/frame/parent", "Switch To Parent Frame"),
+    (Method::Get, "/session/{}/window/rect", "Get Window Rect"),
+    (Method::Post, "/session/{}/window/rect", "Set Window Rect"),
+    (Method::Post, "/session/{}/window/maximize", "Maximize Window"),
+    (Method::Post, "/session/{}/window/minimize", "Minimize Window"),
+    (Method::Post, "/session/{}/window/fullscreen", "Fullscreen Window"),
+    (Method::Get, "/session/{}/element/active", "Get Active Element"),
+    (Method::Get, "/session/{}/element/{}/shadow", "Get Element Shadow Root"),
+    (Method::Post, "/session/{}/element", "Find Element"),
+    (Method::Post, "/session/{}/elements", "Find Elements"),
+    (Method::Post, "/session/{}/element/{}/element", "Find Element From Element"),
+    (Method::Post, "/session/{}/element/{}/elements", "Find Elements From Element"),
+    (Method::Post, "/session/{}/shadow/{}/element", "Find Element From Shadow Root"),
+    (Method::Post, "/session/{}/shadow/{}/elements", "Find Elements From Shadow Root"),
+    (Method::Get, "/session/{}/element/{}/selected", "Is Element Selected"),
+    (Method::Get, "/session/{}/element/{}/displayed", "Is Element Displayed"),
+    (Method::Get, "/session/{}/element/{}/attribute/{}", "Get Element Attribute"),
+    (Method::Get, "/session/{}/element/{}/property/{}", "Get Element Property"),
+    (Method::Get, "/session/{}/element/{}/css/{}", "Get Element CSS Value"),
+    (Method::Get, "/session/{}/element/{}/text", "Get Element Text"),
+    (Method::Get, "/session/{}/element/{}/name", "Get Element Tag Name"),
+    (Method::Get, "/session/{}/element/{}/rect", "Get Element Rect"),
+    (Method::Get, "/session/{}/element/{}/enabled", "Is Element Enabled"),
+    (Method::Get, "/session/{}/element/{}/computedrole", "Get Computed Role"),
+    (Method::Get, "/session/{}/element/{}/computedlabel", "Get Computed Label"),
+    (Method::Post, "/session/{}/element/{}/click", "Element Click"),
+    (Method::Post, "/session/{}/element/{}/clear", "Element Clear"),
+    (Method::Post, "/session/{}/element/{}/value", "Element Send Keys"),
+    (Method::Get, "/session/{}/source", "Get Page Source"),
+    (Method::Post, "/session/{}/execute/sync", "Execute Script"),
+    (Method::Post, "/session/{}/execute/async", "Execute Async Script"),
+    (Method::Get, "/session/{}/cookie", "Get All Cookies"),
+    (Method::Get, "/session/{}/cookie/{}", "Get Named Cookie"),
+    (Method::Post, "/session/{}/cookie", "Add Cookie"),
+    (Method::Delete, "/session/{}/cookie/{}", "Delete Cookie"),
+    (Method::Delete, "/session/{}/cookie", "Delete All Cookies"),
+    (Method::Post, "/session/{}/actions", "Perform Actions"),
+    (Method::Delete, "/session/{}/actions", "Release Actions"),
+    (Method::Post, "/session/{}/alert/dismiss", "Dismiss Alert"),
+    (Method::Post, "/session/{}/alert/accept", "Accept Alert"),
+    (Method::Get, "/session/{}/alert/text", "Get Alert Text"),
+    (Method::Post, "/session/{}/alert/text", "Send Alert Text"),
+    (Method::Get, "/session/{}/screenshot", "Take Screenshot"),
+    (Method::Get, "/session/{}/element/{}/screenshot", "Take Element Screenshot"),
+    (Method::Post, "/session/{}/print", "Print Page"),
+    (Method::Post, "/session/{}/se/file", "Upload File"),
+];
+
+/// The WebDriver specification's name for the command at `method` and
+/// `path`.
+pub fn command_name(method: Method, path: &str) -> &'static str {
+    COMMANDS.iter()
+        .find(|&&(m, template, _)| m == method && util::path_matches(template, path))
+        .map(|&(_, _, name)| name)
+        .unwrap_or("Unknown Command")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{command_name, notify};
+    use crate::transport::Method;
+    use std::cell::Cell;
+
+    #[test]
+    fn command_names() {
+        assert_eq!(command_name(Method::Post, "/session"), "New Session");
+        assert_eq!(command_name(Method::Delete, "/session/1234"), "Delete Session");
+        assert_eq!(command_name(Method::Get, "/session/1234/url"), "Get Current URL");
+        assert_eq!(command_name(Method::Post, "/session/1234/url"), "Navigate To");
+        assert_eq!(command_name(Method::Post, "/session/1234/element/abcd/click"),
+                   "Element Click");
+        assert_eq!(command_name(Method::Get, "/session/1234/element/abcd/attribute/href"),
+                   "Get Element Attribute");
+        assert_eq!(command_name(Method::Get, "/session/1234/element/active"),
+                   "Get Active Element");
+        assert_eq!(command_name(Method::Post, "/session/1234/se/file"), "Upload File");
+        assert_eq!(command_name(Method::Get, "/session/1234/nonsense"), "Unknown Command");
+    }
+
+    #[test]
+    fn notify_is_not_reentrant() {
+        let calls = Cell::new(0);
+        notify(|| {
+            calls.set(calls.get() + 1);
+            notify(|| calls.set(calls.get() + 1));
+        });
+        notify(|| calls.set(calls.get() + 1));
+        assert_eq!(calls.get(), 2);
+    }
+}
@@ -0,0 +1,407 @@
+//! Structured scraping: read page content into typed Rust values.
+//!
+//! A type implementing `Extract` lists the fields to read, each with a
+//! locator and a `Source`. `DriverSession::extract()` and
+//! `Element::extract()` read every field in one script, run with
+//! `DriverSession::execute`, and then parse the strings it returns.
+//!
+//! With the `macros` feature, `#[derive(Extract)]` implements `Extract`
+//! for a struct with named fields. Each field read from the page is marked
+//! `#[extract(...)]` with:
+//!
+//! * a locator, such as `css = "..."`, `xpath = "..."` or any other `By`
+//!   constructor, optionally with `nth = N`. Without one the field is read
+//!   from the element being extracted from.
+//! * a source: `text` (the default), `html` for the inner HTML,
+//!   `attr = "..."` for an attribute or `property = "..."` for a DOM
+//!   property.
+//!
+//! Fields without `#[extract]` are set to their `Default` value.
+//!
+//! The type of a field decides how it is read, through `FromExtract`:
+//!
+//! * `String`, `bool`, `char` and the number types parse the first match
+//!   with `FromStr`, after trimming whitespace from anything but a
+//!   `String`. With the `chrono` feature, so do the `chrono` date and time
+//!   types.
+//! * `Option<T>` is `None` when nothing matches.
+//! * `Vec<T>` reads every match.
+//! * Another type deriving `Extract` is read from within the matching
+//!   element, so `Vec<Review>` reads a list of nested structs.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # #[cfg(feature = "macros")]
+//! # mod example {
+//! use webdriver_client::{DriverSession, Error};
+//! use webdriver_client::extract::Extract;
+//!
+//! #[derive(Debug, Extract)]
+//! struct Review {
+//!     #[extract(css = ".stars", attr = "data-stars")] stars: u8,
+//!     #[extract(css = "p")] text: String,
+//! }
+//!
+//! #[derive(Debug, Extract)]
+//! struct Product {
+//!     #[extract(css = "h1")] name: String,
+//!     #[extract(css = ".price")] price: f64,
+//!     #[extract(css = "a.more", attr = "href")] link: Option<String>,
+//!     #[extract(css = "li.tag")] tags: Vec<String>,
+//!     #[extract(css = ".review")] reviews: Vec<Review>,
+//! }
+//!
+//! fn product(sess: &DriverSession) -> Result<Product, Error> {
+//!     sess.extract::<Product>()
+//! }
+//! # }
+//! ```
+
+use super::{By, DriverSession, Element, Error, JsonValue};
+use crate::locator::LOCATE_SCRIPT;
+use crate::messages::ExecuteCmd;
+
+use serde_json::Map;
+use std::fmt::Display;
+use std::str::FromStr;
+
+#[cfg(feature = "macros")]
+pub use webdriver_client_macros::Extract;
+
+/// Run after `LOCATE_SCRIPT`, which defines `locate`.
+pub(crate) const EXTRACT_SCRIPT: &str = r#"var root = arguments[0] || document, fields = arguments[1];
+function read(element, source) {
+  if (element === document) { element = document.documentElement; }
+  if (source === 'text') { return element.innerText.trim(); }
+  if (source === 'html') { return element.innerHTML; }
+  if (source.attribute !== undefined) { return element.getAttribute(source.attribute); }
+  var value = element[source.property];
+  return value === undefined || value === null ? null : String(value);
+}
+function extract(context, fields) {
+  var out = {};
+  fields.forEach(function (field) {
+    var found = locate(context, field.steps);
+    var value = function (e) { return field.fields ? extract(e, field.fields) : read(e, field.source); };
+    out[field.name] = field.all ? found.map(value) : (found.length > 0 ? value(found[0]) : null);
+  });
+  return out;
+}
+return extract(root, fields);"#;
+
+/// What to read from a matching element.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    /// The rendered text, trimmed, as returned by `Element::text`.
+    Text,
+    /// The inner HTML.
+    Html,
+    /// The named attribute.
+    Attribute(String),
+    /// The named DOM property, converted to a string.
+    Property(String),
+}
+
+/// A field of an `Extract` type.
+#[derive(Debug, Clone)]
+pub struct Field {
+    name: &'static str,
+    by: Option<By>,
+    source: Source,
+    shape: Shape,
+}
+
+impl Field {
+    /// A field named `name` of type `T`, read from the elements matching
+    /// `by`, or from the element being extracted from if `by` is `None`.
+    pub fn new<T: FromExtract>(name: &'static str, by: Option<By>, source: Source) -> Self {
+        Field { name, by, source, shape: T::shape() }
+    }
+
+    fn to_json(&self) -> JsonValue {
+        let steps = self.by.as_ref().map_or_else(|| json!([]), By::steps_json);
+        let source = match self.source {
+            Source::Text => json!("text"),
+            Source::Html => json!("html"),
+            Source::Attribute(ref name) => json!({ "attribute": name }),
+            Source::Property(ref name) => json!({ "property": name }),
+        };
+        let fields = self.shape.fields.as_ref().map(|fields| fields_json(fields));
+        json!({
+            "name": self.name,
+            "steps": steps,
+            "source": source,
+            "all": self.shape.all,
+            "fields": fields,
+        })
+    }
+}
+
+fn fields_json(fields: &[Field]) -> JsonValue {
+    JsonValue::Array(fields.iter().map(Field::to_json).collect())
+}
+
+/// How a `FromExtract` type is read: one match or all of them, and
+/// whether each is a single value or a nested `Extract` type.
+#[derive(Debug, Clone)]
+pub struct Shape {
+    all: bool,
+    fields: Option<Vec<Field>>,
+}
+
+impl Shape {
+    /// The value read from the first match.
+    pub fn value() -> Self {
+        Shape { all: false, fields: None }
+    }
+
+    /// The given fields, read from within the first match.
+    pub fn nested(fields: Vec<Field>) -> Self {
+        Shape { all: false, fields: Some(fields) }
+    }
+
+    /// This shape for every match instead of the first.
+    pub fn all(self) -> Self {
+        Shape { all: true, ..self }
+    }
+}
+
+/// A type that can be read from the page. See the module documentation.
+pub trait FromExtract: Sized {
+    fn shape() -> Shape;
+
+    /// Convert what the extraction script returned for this shape: a
+    /// string or `null` for a value, an object for nested fields, or an
+    /// array of either for all matches.
+    fn from_extracted(value: JsonValue) -> Result<Self, Error>;
+}
+
+/// A type whose fields are read from the page; usually derived. See the
+/// module documentation.
+pub trait Extract: FromExtract {
+    fn fields() -> Vec<Field>;
+}
+
+fn error<S: Into<String>>(message: S) -> Error {
+    Error::Extract { path: String::new(), message: message.into() }
+}
+
+/// Prefix the path of an `Error::Extract` with a field name or index.
+fn within(err: Error, step: &str) -> Error {
+    match err {
+        Error::Extract { path, message } => {
+            let path = if path.is_empty() || path.starts_with('[') {
+                format!("{}{}", step, path)
+            } else {
+                format!("{}.{}", step, path)
+            };
+            Error::Extract { path, message }
+        }
+        other => other,
+    }
+}
+
+/// Parse a string value with `FromStr`, for implementing `FromExtract`.
+/// Whitespace is trimmed first unless `trim` is false.
+pub fn parse<T>(value: JsonValue, trim: bool) -> Result<T, Error>
+    where T: FromStr, T::Err: Display
+{
+    match value {
+        JsonValue::String(s) => {
+            let s = if trim { s.trim() } else { &s[..] };
+            s.parse().map_err(|err| error(format!("Invalid value {:?}: {}", s, err)))
+        }
+        JsonValue::Null => Err(error("No matching element or value")),
+        other => Err(error(format!("Expected a string, got {}", other))),
+    }
+}
+
+/// Remove and convert the field `name` of an extracted object. Used by
+/// `#[derive(Extract)]`.
+pub fn field<T: FromExtract>(values: &mut Map<String, JsonValue>, name: &str)
+                             -> Result<T, Error> {
+    let value = values.remove(name).unwrap_or(JsonValue::Null);
+    T::from_extracted(value).map_err(|err| within(err, name))
+}
+
+/// The object of nested fields extracted for an `Extract` type. Used by
+/// `#[derive(Extract)]`.
+pub fn object(value: JsonValue) -> Result<Map<String, JsonValue>, Error> {
+    match value {
+        JsonValue::Object(values) => Ok(values),
+        JsonValue::Null => Err(error("No matching element")),
+        other => Err(error(format!("Expected an object, got {}", other))),
+    }
+}
+
+macro_rules! from_str_extract {
+    ($trim:expr => $($t:ty),*) => {
+        $(
+            impl FromExtract for $t {
+                fn shape() -> Shape {
+                    Shape::value()
+                }
+
+                fn from_extracted(value: JsonValue) -> Result<Self, Error> {
+                    parse(value, $trim)
+                }
+            }
+        )*
+    }
+}
+
+from_str_extract!(false => String);
+from_str_extract!(true => bool, char, f32, f64, i8, i16, i32, i64, i128, isize,
+                  u8, u16, u32, u64, u128, usize);
+#[cfg(feature = "chrono")]
+from_str_extract!(true => chrono::NaiveDate, chrono::NaiveTime, chrono::NaiveDateTime,
+                  chrono::DateTime<chrono::FixedOffset>, chrono::DateTime<chrono::Utc>);
+
+impl<T: FromExtract> FromExtract for Option<T> {
+    fn shape() -> Shape {
+        T::shape()
+    }
+
+    fn from_extracted(value: JsonValue) -> Result<Self, Error> {
+        match value {
+            JsonValue::Null => Ok(None),
+            value => T::from_extracted(value).map(Some),
+        }
+    }
+}
+
+impl<T: FromExtract> FromExtract for Vec<T> {
+    fn shape() -> Shape {
+        T::shape().all()
+    }
+
+    fn from_extracted(value: JsonValue) -> Result<Self, Error> {
+        match value {
+            JsonValue::Array(values) => values.into_iter().enumerate()
+                .map(|(i, value)| T::from_extracted(value)
+                     .map_err(|err| within(err, &format!("[{}]", i))))
+                .collect(),
+            other => Err(error(format!("Expected a list, got {}", other))),
+        }
+    }
+}
+
+/// Run the extraction script for `T` within `root`, or the document.
+pub(crate) fn extract<T: Extract>(session: &DriverSession, root: Option<&Element>)
+                                  -> Result<T, Error> {
+    let root = match root {
+        Some(element) => element.reference()?,
+        None => JsonValue::Null,
+    };
+    let value = session.execute(ExecuteCmd {
+        script: format!("{}\n{}", LOCATE_SCRIPT, EXTRACT_SCRIPT),
+        args: vec![root, fields_json(&T::fields())],
+    })?;
+    T::from_extracted(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{field, FromExtract, JsonValue};
+    use crate::Error;
+
+    fn parse<T: FromExtract>(value: JsonValue) -> Result<T, Error> {
+        T::from_extracted(value)
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(parse::<f64>(json!(" 12.5\n")).unwrap(), 12.5);
+        assert_eq!(parse::<String>(json!(" a ")).unwrap(), " a ");
+        assert_eq!(parse::<Option<u32>>(JsonValue::Null).unwrap(), None);
+        assert_eq!(parse::<Vec<Option<bool>>>(json!(["true", null])).unwrap(),
+                   vec![Some(true), None]);
+        assert!(parse::<String>(JsonValue::Null).is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn dates() {
+        assert_eq!(parse::<chrono::NaiveDate>(json!("2024-02-29")).unwrap(),
+                   chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+        assert!(parse::<chrono::NaiveDate>(json!("2023-02-29")).is_err());
+    }
+
+    #[test]
+    fn errors_name_the_field() {
+        let mut values = json!({ "prices": ["1", "2", "free"] }).as_object().unwrap().clone();
+        match field::<Vec<u32>>(&mut values, "prices") {
+            Err(Error::Extract { path, message }) => {
+                assert_eq!(path, "prices[2]");
+                assert_eq!(message, r#"Invalid value "free": invalid digit found in string"#);
+            }
+            other => panic!("Want Error::Extract, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "macros")]
+    mod derive {
+        use crate::extract::Extract;
+        use crate::Element;
+        use crate::testing::FakeServer;
+        use crate::transport::Method;
+
+        #[derive(Debug, PartialEq, Extract)]
+        struct Review {
+            #[extract(attr = "data-id")] id: u32,
+            #[extract(css = "p")] text: String,
+        }
+
+        #[derive(Debug, PartialEq, Extract)]
+        struct Product {
+            #[extract(css = "h1")] name: String,
+            #[extract(xpath = "//span[@class = 'price']", text)] price: f64,
+            #[extract(css = "a.more", attr = "href")] link: Option<String>,
+            #[extract(css = "li", nth = 1, html)] second_tag: String,
+            #[extract(class_name = "review")] reviews: Vec<Review>,
+            unread: bool,
+        }
+
+        #[test]
+        fn derived_extract() {
+            // Checks the fields sent to the extraction script and answers
+            // with the values a page would give.
+            let server = FakeServer::new();
+            server.on(Method::Post, "/execute/sync", |call| {
+                assert_eq!(call.body["args"][0]["element-6066-11e4-a52e-4f735466cecf"], "main");
+                let fields = &call.body["args"][1];
+                assert_eq!(fields[1], json!({
+                    "name": "price",
+                    "steps": [{ "using": "xpath", "value": "//span[@class = 'price']", "index": null }],
+                    "source": "text", "all": false, "fields": null,
+                }));
+                assert_eq!(fields[3]["steps"], json!([{ "using": "css selector", "value": "li", "index": 1 }]));
+                assert_eq!(fields[3]["source"], "html");
+                assert_eq!(fields[4]["all"], true);
+                assert_eq!(fields[4]["fields"][0], json!({
+                    "name": "id", "steps": [], "source": { "attribute": "data-id" },
+                    "all": false, "fields": null,
+                }));
+                json!({
+                    "name": "Widget", "price": " 12.50 ", "link": null, "second_tag": "<b>b</b>",
+                    "reviews": [{ "id": "1", "text": "Good" }, { "id": "2", "text": "Bad" }],
+                })
+            });
+            let sess = server.session();
+            let product: Product = Element::new(&sess, "main".to_owned()).extract().unwrap();
+            assert_eq!(product, Product {
+                name: "Widget".to_owned(),
+                price: 12.5,
+                link: None,
+                second_tag: "<b>b</b>".to_owned(),
+                reviews: vec![
+                    Review { id: 1, text: "Good".to_owned() },
+                    Review { id: 2, text: "Bad".to_owned() },
+                ],
+                unread: false,
+            });
+            assert_eq!(Product::fields().len(), 5);
+        }
+    }
+}
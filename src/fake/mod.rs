@@ -0,0 +1,861 @@
+//! A fake browser for fast tests without a real browser, enabled by the
+//! `fake` cargo feature.
+//!
+//! `FakeBrowser` parses the HTML of pages registered by the test and
+//! answers WebDriver commands against that document: navigation and
+//! history, finding elements by CSS, link text and XPath, text,
+//! attributes and properties, clicking links, typing into and submitting
+//! forms. It runs no JavaScript; `execute` only understands a few of the
+//! scripts this crate sends itself, listed on `FakeBrowser`.
+//!
+//! It implements both `Driver` and `Transport`, so a `DriverSession`
+//! talks to it with the same JSON commands it would send to a real
+//! driver, only without HTTP.
+//!
+//! Only a subset of XPath is supported; see the `xpath` submodule.
+//!
+//! # Example
+//!
+//! ```
+//! # use webdriver_client::{By, Driver};
+//! # use webdriver_client::fake::FakeBrowser;
+//! # use webdriver_client::messages::NewSessionCmd;
+//! # use webdriver_client::transport::Method;
+//! let browser = FakeBrowser::new();
+//! browser.page("http://app.test/", r#"
+//!     <title>Search</title>
+//!     <form action="/results"><input name="q"><button>Go</button></form>"#);
+//! browser.route(Method::Get, "http://app.test/results", |req| {
+//!     format!("<title>Results</title><p>You asked for {}</p>", req.url)
+//! });
+//!
+//! let sess = browser.clone().session(&NewSessionCmd::default()).unwrap();
+//! sess.go("http://app.test/").unwrap();
+//! sess.find_element(&By::css("input[name=q]")).unwrap()
+//!     .send_keys("cats").unwrap();
+//! sess.find_element(&By::css("button")).unwrap().click().unwrap();
+//!
+//! assert_eq!(sess.get_title().unwrap(), "Results");
+//! assert_eq!(sess.get_current_url().unwrap(), "http://app.test/results?q=cats");
+//! ```
+
+mod page;
+pub mod xpath;
+
+use self::page::{collapse, Page};
+use super::{Driver, Error, JsonValue};
+use crate::{conditions, extract, form, relative, select, snapshot, table};
+use crate::messages::{error_status, ELEMENT_KEY};
+use crate::transport::{Method, Response, Transport};
+#[cfg(feature = "async")]
+use crate::transport::AsyncTransport;
+
+use ego_tree::NodeId;
+use scraper::{ElementRef, Selector};
+use url::Url;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const WINDOW_HANDLE: &str = "fake-window";
+const BLANK_PAGE: &str = "<html><head></head><body></body></html>";
+
+/// A page load made by the fake browser, passed to route handlers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRequest {
+    /// `Method::Get`, or `Method::Post` for forms with `method="post"`.
+    pub method: Method,
+    /// The full URL, including any query string.
+    pub url: String,
+    /// The URL encoded form fields of a POST.
+    pub body: Option<String>,
+}
+
+type Handler = Box<dyn Fn(&PageRequest) -> String + Send + Sync>;
+
+struct Route {
+    method: Method,
+    url: String,
+    handler: Handler,
+}
+
+/// The state of one session: a single window with its history.
+struct Tab {
+    page: Page,
+    history: Vec<PageRequest>,
+    /// Index of the current page in `history`.
+    current: usize,
+}
+
+#[derive(Default)]
+struct State {
+    routes: Vec<Route>,
+    requests: Vec<PageRequest>,
+    sessions: HashMap<String, Tab>,
+    next_session: u64,
+    next_generation: u64,
+}
+
+/// An error to report to the client as a WebDriver error.
+struct Failure {
+    code: &'static str,
+    message: String,
+}
+
+fn fail<T, S: Into<String>>(code: &'static str, message: S) -> Result<T, Failure> {
+    Err(Failure { code, message: message.into() })
+}
+
+/// A fake, DOM-backed browser.
+///
+/// It runs no JavaScript. Execute Script only recognises these scripts,
+/// compared with runs of whitespace collapsed:
+///
+/// * `return document.readyState === 'complete';`, sent by
+///   `DriverSession::wait_for_document_ready`, which is always true.
+/// * `return document.readyState;` and `return document.title;`.
+/// * `return arguments[0].innerHTML;` and `return arguments[0].outerHTML;`,
+///   sent by `Element::inner_html` and `outer_html`.
+/// * `return arguments[0].multiple;`, sent by `Select::is_multiple`.
+///
+/// Any other script fails with an `unsupported operation` error, and so do
+/// the features built on larger scripts: element snapshots,
+/// `Element::table`, relative locators, `DriverSession::extract`, `Form`
+/// and `DriverSession::wait_for_network_idle`. The error names the
+/// feature. Asynchronous scripts, and so `execute_async` and
+/// `evaluate_promise`, are not supported at all.
+///
+/// Clones share the same pages and sessions, so one clone can be moved
+/// into a session while another registers pages and inspects requests.
+#[derive(Clone, Default)]
+pub struct FakeBrowser {
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeBrowser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `html` for GET requests to `url`.
+    pub fn page(&self, url: &str, html: &str) {
+        let html = html.to_owned();
+        self.route(Method::Get, url, move |_| html.clone());
+    }
+
+    /// Serve the HTML returned by `handler` for requests to `url`.
+    ///
+    /// `url` is compared without its query string and fragment. When
+    /// several routes match, the most recently registered one is used.
+    pub fn route<F>(&self, method: Method, url: &str, handler: F)
+        where F: Fn(&PageRequest) -> String + Send + Sync + 'static
+    {
+        self.state.lock().unwrap().routes.push(Route {
+            method,
+            url: strip_url(url),
+            handler: Box::new(handler),
+        });
+    }
+
+    /// The pages loaded so far, oldest first.
+    pub fn requests(&self) -> Vec<PageRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Driver for FakeBrowser {
+    fn url(&self) -> &str {
+        "fake:"
+    }
+
+    fn transport(&self) -> Result<Box<dyn Transport>, Error> {
+        Ok(Box::new(self.clone()))
+    }
+
+    #[cfg(feature = "async")]
+    fn async_transport(&self) -> Result<Box<dyn AsyncTransport>, Error> {
+        Ok(Box::new(self.clone()))
+    }
+}
+
+impl Transport for FakeBrowser {
+    fn send(&self, method: Method, path: &str, body: Option<&str>) -> Result<Response, Error> {
+        let body: JsonValue = match body {
+            Some(body) => serde_json::from_str(body)?,
+            None => JsonValue::Null,
+        };
+        let mut state = self.state.lock().unwrap();
+        let (status, value) = match state.command(method, path, &body) {
+            Ok(value) => (200, value),
+            Err(failure) => (error_status(failure.code), json!({
+                "error": failure.code,
+                "message": failure.message,
+                "stacktrace": "",
+            })),
+        };
+        Ok(Response { status, body: json!({ "value": value }).to_string() })
+    }
+}
+
+/// A URL without its query string and fragment, for matching routes.
+fn strip_url(url: &str) -> String {
+    url.split(['?', '#']).next().unwrap_or("").to_owned()
+}
+
+fn element_json(reference: String) -> JsonValue {
+    json!({ ELEMENT_KEY: reference })
+}
+
+fn string_arg<'a>(body: &'a JsonValue, key: &str) -> Result<&'a str, Failure> {
+    match body[key].as_str() {
+        Some(s) => Ok(s),
+        None => fail("invalid argument", format!("Missing string argument {}", key)),
+    }
+}
+
+impl State {
+    fn command(&mut self, method: Method, path: &str, body: &JsonValue)
+        -> Result<JsonValue, Failure>
+    {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (method, segments.as_slice()) {
+            (Method::Get, ["status"]) => Ok(json!({ "ready": true, "message": "" })),
+            (Method::Post, ["session"]) => Ok(self.new_session()),
+            (Method::Delete, ["session", id]) => {
+                self.sessions.remove(*id);
+                Ok(JsonValue::Null)
+            }
+            (_, ["session", id, rest @ ..]) => {
+                let id = id.to_string();
+                let mut tab = match self.sessions.remove(&id) {
+                    Some(tab) => tab,
+                    None => return fail("invalid session id", format!("No session {}", id)),
+                };
+                let result = self.session_command(&mut tab, method, rest, body);
+                self.sessions.insert(id, tab);
+                result
+            }
+            _ => fail("unknown command", format!("{} {}", method.as_str(), path)),
+        }
+    }
+
+    fn new_session(&mut self) -> JsonValue {
+        self.next_session += 1;
+        let id = format!("fake-session-{}", self.next_session);
+        let page = Page::new(Url::parse("about:blank").unwrap(), BLANK_PAGE, self.generation());
+        self.sessions.insert(id.clone(), Tab { page, history: Vec::new(), current: 0 });
+        json!({
+            "sessionId": id,
+            "capabilities": { "browserName": "fake", "javascriptEnabled": false },
+        })
+    }
+
+    fn generation(&mut self) -> u64 {
+        self.next_generation += 1;
+        self.next_generation
+    }
+
+    /// Fetch and parse a page.
+    fn load(&mut self, request: &PageRequest) -> Result<Page, Failure> {
+        let url = match Url::parse(&request.url) {
+            Ok(url) => url,
+            Err(_) => return fail("invalid argument", format!("Invalid URL {}", request.url)),
+        };
+        self.requests.push(request.clone());
+        let route_url = strip_url(&request.url);
+        let html = match self.routes.iter().rev()
+            .find(|r| r.method == request.method && r.url == route_url)
+        {
+            Some(route) => (route.handler)(request),
+            None => return fail("unknown error",
+                                format!("FakeBrowser has no page for {} {}",
+                                        request.method.as_str(), request.url)),
+        };
+        let generation = self.generation();
+        Ok(Page::new(url, &html, generation))
+    }
+
+    fn navigate(&mut self, tab: &mut Tab, request: PageRequest) -> Result<(), Failure> {
+        // Only the fragment changed: no request is made.
+        if request.method == Method::Get && !tab.history.is_empty()
+            && request.url.contains('#')
+            && request.url.split('#').next() == tab.page.url.as_str().split('#').next()
+        {
+            tab.page.url = Url::parse(&request.url).unwrap_or_else(|_| tab.page.url.clone());
+        } else {
+            tab.page = self.load(&request)?;
+        }
+        tab.history.truncate(tab.current + 1);
+        tab.history.push(request);
+        tab.current = tab.history.len() - 1;
+        Ok(())
+    }
+
+    fn traverse(&mut self, tab: &mut Tab, index: usize) -> Result<(), Failure> {
+        let request = tab.history[index].clone();
+        tab.page = self.load(&request)?;
+        tab.current = index;
+        Ok(())
+    }
+
+    fn session_command(&mut self, tab: &mut Tab, method: Method, rest: &[&str],
+                       body: &JsonValue)
+        -> Result<JsonValue, Failure>
+    {
+        match (method, rest) {
+            (Method::Post, ["url"]) => {
+                let url = string_arg(body, "url")?.to_owned();
+                self.navigate(tab, PageRequest { method: Method::Get, url, body: None })?;
+                Ok(JsonValue::Null)
+            }
+            (Method::Get, ["url"]) => Ok(json!(tab.page.url.as_str())),
+            (Method::Get, ["title"]) => Ok(json!(tab.page.title())),
+            (Method::Get, ["source"]) => Ok(json!(tab.page.html.html())),
+            (Method::Post, ["back"]) => {
+                if tab.current > 0 && !tab.history.is_empty() {
+                    let index = tab.current - 1;
+                    self.traverse(tab, index)?;
+                }
+                Ok(JsonValue::Null)
+            }
+            (Method::Post, ["forward"]) => {
+                if tab.current + 1 < tab.history.len() {
+                    let index = tab.current + 1;
+                    self.traverse(tab, index)?;
+                }
+                Ok(JsonValue::Null)
+            }
+            (Method::Post, ["refresh"]) => {
+                if !tab.history.is_empty() {
+                    let index = tab.current;
+                    self.traverse(tab, index)?;
+                }
+                Ok(JsonValue::Null)
+            }
+            (Method::Get, ["window"]) => Ok(json!(WINDOW_HANDLE)),
+            (Method::Get, ["window", "handles"]) => Ok(json!([WINDOW_HANDLE])),
+            (Method::Post, ["window"]) => {
+                if body["handle"] == json!(WINDOW_HANDLE) {
+                    Ok(JsonValue::Null)
+                } else {
+                    fail("no such window", "FakeBrowser has a single window")
+                }
+            }
+            (Method::Post, ["frame"]) if body["id"].is_null() => Ok(JsonValue::Null),
+            (Method::Post, ["frame"]) => fail("no such frame", "FakeBrowser has no frames"),
+            (Method::Post, ["frame", "parent"]) => Ok(JsonValue::Null),
+            (Method::Get, ["cookie"]) => Ok(json!([])),
+            (Method::Post, ["alert", ..]) | (Method::Get, ["alert", ..]) =>
+                fail("no such alert", "FakeBrowser has no alerts"),
+            (Method::Post, ["element"]) => {
+                let root = tab.page.html.tree.root().id();
+                let found = find(&tab.page, root, body)?;
+                match found.first() {
+                    Some(&id) => Ok(element_json(tab.page.reference(id))),
+                    None => fail("no such element", format!("No element matches {}", body)),
+                }
+            }
+            (Method::Post, ["elements"]) => {
+                let root = tab.page.html.tree.root().id();
+                let found = find(&tab.page, root, body)?;
+                Ok(JsonValue::Array(found.into_iter()
+                    .map(|id| element_json(tab.page.reference(id)))
+                    .collect()))
+            }
+            (Method::Post, ["execute", "sync"]) => execute(&mut tab.page, body),
+            (_, ["element", reference, command @ ..]) => {
+                let id = match tab.page.lookup(reference) {
+                    Ok(id) => id,
+                    Err(true) => return fail("stale element reference",
+                                             format!("Element {} is from an earlier page",
+                                                     reference)),
+                    Err(false) => return fail("no such element",
+                                              format!("No element {}", reference)),
+                };
+                self.element_command(tab, id, method, command, body)
+            }
+            _ => fail("unknown command",
+                      format!("FakeBrowser does not support {} /{}",
+                              method.as_str(), rest.join("/"))),
+        }
+    }
+
+    fn element_command(&mut self, tab: &mut Tab, id: NodeId, method: Method,
+                       command: &[&str], body: &JsonValue)
+        -> Result<JsonValue, Failure>
+    {
+        let page = &mut tab.page;
+        match (method, command) {
+            (Method::Post, ["element"]) => {
+                let found = find(page, id, body)?;
+                match found.first() {
+                    Some(&found) => Ok(element_json(page.reference(found))),
+                    None => fail("no such element", format!("No element matches {}", body)),
+                }
+            }
+            (Method::Post, ["elements"]) => {
+                let found = find(page, id, body)?;
+                Ok(JsonValue::Array(found.into_iter()
+                    .map(|id| element_json(page.reference(id)))
+                    .collect()))
+            }
+            (Method::Get, ["text"]) => Ok(json!(page.text(id))),
+            (Method::Get, ["name"]) => Ok(json!(page.tag(id))),
+            (Method::Get, ["attribute", name]) => Ok(json!(page.attribute(id, name))),
+            (Method::Get, ["property", name]) => Ok(property(page, id, name)),
+            (Method::Get, ["css", _]) => Ok(json!("")),
+            (Method::Get, ["displayed"]) => Ok(json!(page.is_displayed(id))),
+            (Method::Get, ["enabled"]) => Ok(json!(page.is_enabled(id))),
+            (Method::Get, ["selected"]) => Ok(json!(page.is_checked(id))),
+            (Method::Post, ["click"]) => {
+                self.click(tab, id)?;
+                Ok(JsonValue::Null)
+            }
+            (Method::Post, ["clear"]) => {
+                if !page.is_text_control(id) {
+                    return fail("invalid element state", "Only text fields can be cleared");
+                }
+                page.set_value(id, String::new());
+                Ok(JsonValue::Null)
+            }
+            (Method::Post, ["value"]) => {
+                let text = string_arg(body, "text")?.to_owned();
+                self.send_keys(tab, id, &text)?;
+                Ok(JsonValue::Null)
+            }
+            _ => fail("unknown command",
+                      format!("FakeBrowser does not support {} element/{}",
+                              method.as_str(), command.join("/"))),
+        }
+    }
+
+    fn click(&mut self, tab: &mut Tab, id: NodeId) -> Result<(), Failure> {
+        if !tab.page.is_displayed(id) {
+            return fail("element not interactable", "The element is not displayed");
+        }
+        // The click goes to the nearest element with an activation behaviour.
+        let target = tab.page.node(id).ancestors().chain(Some(tab.page.node(id)))
+            .map(|n| n.id())
+            .filter(|&n| ElementRef::wrap(tab.page.node(n)).is_some())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .find(|&n| match tab.page.tag(n) {
+                "a" => tab.page.attr(n, "href").is_some(),
+                "button" | "input" | "option" | "label" => true,
+                _ => false,
+            });
+        let target = match target {
+            Some(target) => target,
+            None => return Ok(()),
+        };
+        if !tab.page.is_enabled(target) {
+            return Ok(());
+        }
+
+        match tab.page.tag(target) {
+            "a" => {
+                let href = tab.page.attr(target, "href").unwrap_or("").to_owned();
+                match tab.page.resolve(&href) {
+                    Some(url) => self.navigate(tab, PageRequest {
+                        method: Method::Get,
+                        url: url.into_string(),
+                        body: None,
+                    }),
+                    None => Ok(()),
+                }
+            }
+            "label" => {
+                let control = match tab.page.attr(target, "for") {
+                    Some(for_id) => tab.page.find_by_id(for_id),
+                    None => tab.page.node(target).descendants().skip(1)
+                        .map(|n| n.id())
+                        .find(|&n| ElementRef::wrap(tab.page.node(n)).is_some()
+                              && ["input", "select", "textarea", "button"]
+                                  .contains(&tab.page.tag(n))),
+                };
+                match control {
+                    Some(control) if control != id => self.click(tab, control),
+                    _ => Ok(()),
+                }
+            }
+            "option" | "input" if !tab.page.is_input(target, &["submit", "image", "reset"]) => {
+                tab.page.toggle(target);
+                Ok(())
+            }
+            _ => {
+                let kind = tab.page.attr(target, "type").unwrap_or("submit").to_lowercase();
+                let form = match tab.page.form_of(target) {
+                    Some(form) => form,
+                    None => return Ok(()),
+                };
+                match kind.as_str() {
+                    "reset" => {
+                        tab.page.reset(form);
+                        Ok(())
+                    }
+                    "submit" | "image" => self.submit(tab, form, Some(target)),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    fn submit(&mut self, tab: &mut Tab, form: NodeId, submitter: Option<NodeId>)
+        -> Result<(), Failure>
+    {
+        let submission = match tab.page.submission(form, submitter) {
+            Some(submission) => submission,
+            None => return fail("unknown error", "The form action is not a valid URL"),
+        };
+        let request = if submission.post {
+            PageRequest {
+                method: Method::Post,
+                url: submission.url.into_string(),
+                body: Some(submission.body),
+            }
+        } else {
+            PageRequest { method: Method::Get, url: submission.url.into_string(), body: None }
+        };
+        self.navigate(tab, request)
+    }
+
+    fn send_keys(&mut self, tab: &mut Tab, id: NodeId, text: &str) -> Result<(), Failure> {
+        let page = &mut tab.page;
+        if !page.is_displayed(id) || !page.is_enabled(id) {
+            return fail("element not interactable", "The element cannot be typed into");
+        }
+        if page.tag(id) == "select" {
+            page.select_by_text(id, text);
+            return Ok(());
+        }
+        if !page.is_text_control(id) && !page.is_input(id, &["file"]) {
+            return fail("element not interactable", "The element does not accept text");
+        }
+        if page.attr(id, "readonly").is_some() {
+            return fail("invalid element state", "The element is read-only");
+        }
+        let mut value = page.value(id);
+        let mut enter = false;
+        for c in text.chars() {
+            match c {
+                // Backspace and Delete, as far as a field without a cursor
+                // position can tell them apart.
+                '\u{E003}' | '\u{E017}' => { value.pop(); }
+                // Return and Enter.
+                '\u{E006}' | '\u{E007}' => {
+                    if page.tag(id) == "textarea" {
+                        value.push('\n');
+                    } else {
+                        enter = true;
+                    }
+                }
+                // Other special keys have no effect on the text.
+                '\u{E000}'..='\u{F8FF}' => {}
+                c => value.push(c),
+            }
+        }
+        page.set_value(id, value);
+        if enter {
+            if let Some(form) = page.form_of(id) {
+                return self.submit(tab, form, None);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Find elements below `context` with the strategy and selector in `body`.
+fn find(page: &Page, context: NodeId, body: &JsonValue) -> Result<Vec<NodeId>, Failure> {
+    let using = string_arg(body, "using")?;
+    let value = string_arg(body, "value")?;
+    let root = page.html.tree.root().id();
+    match using {
+        "css selector" => {
+            let selector = match Selector::parse(value) {
+                Ok(selector) => selector,
+                Err(err) => return fail("invalid selector", format!("{}: {:?}", value, err)),
+            };
+            Ok(if context == root {
+                page.html.select(&selector).map(|e| e.id()).collect()
+            } else {
+                page.element(context).select(&selector).map(|e| e.id())
+                    .filter(|&id| id != context)
+                    .collect()
+            })
+        }
+        "link text" | "partial link text" => {
+            let partial = using == "partial link text";
+            Ok(page.node(context).descendants().skip(1)
+                .filter(|n| n.value().as_element().is_some_and(|e| e.name() == "a"))
+                .map(|n| n.id())
+                .filter(|&id| {
+                    let text = page.text(id);
+                    if partial { text.contains(value) } else { text.trim() == value }
+                })
+                .collect())
+        }
+        "xpath" => match xpath::select(&page.html, context, value) {
+            Ok(found) => Ok(found),
+            Err(err) => fail("invalid selector", format!("{}: {}", value, err)),
+        },
+        "tag name" => Ok(page.node(context).descendants().skip(1)
+            .filter(|n| n.value().as_element().is_some_and(|e| e.name() == value))
+            .map(|n| n.id())
+            .collect()),
+        other => fail("invalid argument", format!("Unknown location strategy {}", other)),
+    }
+}
+
+fn property(page: &Page, id: NodeId, name: &str) -> JsonValue {
+    match name {
+        "value" => json!(page.value(id)),
+        "checked" | "selected" => json!(page.is_checked(id)),
+        "disabled" => json!(!page.is_enabled(id)),
+        "textContent" => json!(page.text_content(id)),
+        "innerText" => json!(page.text(id)),
+        "innerHTML" => json!(page.element(id).inner_html()),
+        "outerHTML" => json!(page.element(id).html()),
+        "tagName" | "nodeName" => json!(page.tag(id).to_uppercase()),
+        "className" => json!(page.attr(id, "class").unwrap_or("")),
+        "href" | "src" | "action" => match page.attr(id, name) {
+            Some(url) => json!(page.resolve(url).map(|u| u.into_string())),
+            None => json!(""),
+        },
+        _ => json!(page.attr(id, name)),
+    }
+}
+
+/// Scripts this crate sends that need a JavaScript engine, and the
+/// features that send them.
+const UNSUPPORTED_SCRIPTS: &[(&str, &str)] = &[
+    (snapshot::SNAPSHOT_SCRIPT, "Element snapshots are"),
+    (table::TABLE_SCRIPT, "Element::table is"),
+    (relative::FILTER_SCRIPT, "Relative locators are"),
+    (extract::EXTRACT_SCRIPT, "DriverSession::extract is"),
+    (form::FIELDS_SCRIPT, "Form is"),
+    (conditions::NETWORK_IDLE_SCRIPT, "DriverSession::wait_for_network_idle is"),
+];
+
+/// Run one of the few scripts this crate sends itself. See `FakeBrowser`.
+fn execute(page: &mut Page, body: &JsonValue) -> Result<JsonValue, Failure> {
+    let raw_script = string_arg(body, "script")?;
+    let script = collapse(raw_script);
+    let arg_element = |page: &Page| -> Result<NodeId, Failure> {
+        let reference = body["args"][0][ELEMENT_KEY].as_str().unwrap_or("");
+        match page.lookup(reference) {
+            Ok(id) => Ok(id),
+            Err(true) => fail("stale element reference", "The element is from an earlier page"),
+            Err(false) => fail("no such element", "The first argument is not an element"),
+        }
+    };
+    match script.as_str() {
+        conditions::DOCUMENT_READY_SCRIPT => Ok(json!(true)),
+        "return document.readyState;" => Ok(json!("complete")),
+        "return document.title;" => Ok(json!(page.title())),
+        "return arguments[0].innerHTML;" => {
+            let id = arg_element(page)?;
+            Ok(json!(page.element(id).inner_html()))
+        }
+        "return arguments[0].outerHTML;" => {
+            let id = arg_element(page)?;
+            Ok(json!(page.element(id).html()))
+        }
+        select::MULTIPLE_SCRIPT => {
+            let id = arg_element(page)?;
+            Ok(json!(page.tag(id) == "select" && page.attr(id, "multiple").is_some()))
+        }
+        _ => match UNSUPPORTED_SCRIPTS.iter().find(|&&(s, _)| raw_script.contains(s)) {
+            Some((_, feature)) =>
+                fail("unsupported operation",
+                     format!("{} not supported: FakeBrowser does not run JavaScript", feature)),
+            None => fail("unsupported operation", "FakeBrowser does not run JavaScript"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FakeBrowser;
+    use super::super::{By, Driver, DriverSession, Error};
+    use crate::messages::NewSessionCmd;
+    use crate::transport::Method;
+
+    const HOME: &str = r#"<html><head><title> Home  page </title></head><body>
+        <h1 id="greeting">Hello, <b>world</b></h1>
+        <p hidden>Secret</p>
+        <a id="about" href="/about">About <span>us</span></a>
+        <ul><li class="item">One</li><li class="item">Two</li></ul>
+        <form id="login" method="post" action="/login">
+          <input name="user" value="guest">
+          <input name="pass" type="password">
+          <input name="remember" type="checkbox" value="yes">
+          <input name="role" type="radio" value="user" checked>
+          <input name="role" type="radio" value="admin">
+          <select name="lang"><option>en</option><option value="fr">French</option></select>
+          <textarea name="bio">Hi</textarea>
+          <input name="off" disabled value="x">
+          <button id="submit">Log in</button>
+          <button type="button">Nothing</button>
+        </form>
+        </body></html>"#;
+
+    fn session() -> (FakeBrowser, DriverSession) {
+        let browser = FakeBrowser::new();
+        browser.page("http://app.test/", HOME);
+        browser.page("http://app.test/about", "<title>About</title><a href='/'>Home</a>");
+        browser.route(Method::Post, "http://app.test/login", |req| {
+            format!("<title>Logged in</title><pre>{}</pre>", req.body.as_ref().unwrap())
+        });
+        let sess = browser.clone().session(&NewSessionCmd::default()).unwrap();
+        sess.go("http://app.test/").unwrap();
+        (browser, sess)
+    }
+
+    #[test]
+    fn navigation_and_history() {
+        let (browser, sess) = session();
+        assert_eq!(sess.get_title().unwrap(), "Home page");
+        sess.find_element(&By::link_text("About us")).unwrap().click().unwrap();
+        assert_eq!(sess.get_current_url().unwrap(), "http://app.test/about");
+        assert_eq!(sess.get_title().unwrap(), "About");
+        sess.back().unwrap();
+        assert_eq!(sess.get_title().unwrap(), "Home page");
+        sess.forward().unwrap();
+        assert_eq!(sess.get_title().unwrap(), "About");
+
+        let urls: Vec<_> = browser.requests().into_iter().map(|r| r.url).collect();
+        assert_eq!(urls, vec!["http://app.test/", "http://app.test/about",
+                              "http://app.test/", "http://app.test/about"]);
+        assert!(sess.go("http://app.test/missing").is_err());
+    }
+
+    #[test]
+    fn finding_elements() {
+        let (_browser, sess) = session();
+        let items = sess.find_elements(&By::css("li.item")).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].text().unwrap(), "Two");
+        let h1 = sess.find_element(&By::xpath("//h1[b='world']")).unwrap();
+        assert_eq!(h1.text().unwrap(), "Hello, world");
+        assert_eq!(h1.attribute("id").unwrap(), "greeting");
+        assert_eq!(h1.find_element(&By::css("b")).unwrap().name().unwrap(), "b");
+        assert!(sess.find_element(&By::link_text("About")).is_err());
+        assert!(sess.find_element(&By::partial_link_text("ut u")).is_ok());
+
+        assert_eq!(sess.find_element(&By::id("greeting")).unwrap().name().unwrap(), "h1");
+        assert_eq!(sess.find_elements(&By::class_name("item")).unwrap().len(), 2);
+        assert_eq!(sess.find_elements(&By::tag_name("li")).unwrap().len(), 2);
+        assert_eq!(sess.find_element(&By::name("pass")).unwrap()
+                       .attribute("type").unwrap(), "password");
+        assert_eq!(sess.find_element(&By::exact_text("Log in")).unwrap()
+                       .attribute("id").unwrap(), "submit");
+        assert!(sess.find_element(&By::exact_text("Log")).is_err());
+        assert_eq!(sess.find_element(&By::contains_text("Hello")).unwrap()
+                       .name().unwrap(), "h1");
+
+        let secret = sess.find_element(&By::css("p")).unwrap();
+        assert!(!secret.is_displayed().unwrap());
+        assert_eq!(secret.text().unwrap(), "");
+
+        match sess.find_element(&By::css("#nope")) {
+            Err(ref err) if err.is_webdriver_error("no such element") => {}
+            other => panic!("Want no such element, got {:?}", other.map(|_| ())),
+        }
+        match sess.find_element(&By::css("!!")) {
+            Err(ref err) if err.is_webdriver_error("invalid selector") => {}
+            other => panic!("Want invalid selector, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn by_locators() {
+        let (_browser, sess) = session();
+        let second = sess.find_element(&By::css("li").within(By::tag_name("ul")).nth(1)).unwrap();
+        assert_eq!(second.text().unwrap(), "Two");
+        let pass = sess.find_elements(&By::tag_name("input").nth(1).within(By::id("login"))).unwrap();
+        assert_eq!(pass.len(), 1);
+        assert_eq!(pass[0].attribute("name").unwrap(), "pass");
+        let h1 = sess.find_element(&By::id("greeting")).unwrap();
+        assert_eq!(h1.find_element(&By::tag_name("b")).unwrap().text().unwrap(), "world");
+
+        let message = |by| match sess.find_element(&by) {
+            Err(Error::WebDriverError(err)) => err.message,
+            other => panic!("Want WebDriverError, got {:?}", other.map(|_| ())),
+        };
+        assert!(message(By::css("li").within(By::id("nope")))
+                    .starts_with(r#"No element found for css "li" within id "nope": "#));
+        assert_eq!(message(By::css("li").nth(5)),
+                   r#"No element found for css "li"[5]: Only 2 elements match"#);
+    }
+
+    #[test]
+    fn form_submission() {
+        let (browser, sess) = session();
+        let user = sess.find_element(&By::css("input[name=user]")).unwrap();
+        user.clear().unwrap();
+        user.send_keys("alice").unwrap();
+        assert_eq!(user.property("value").unwrap(), "alice");
+        sess.find_element(&By::css("input[name=pass]")).unwrap()
+            .send_keys("s3cret!").unwrap();
+        sess.find_element(&By::css("input[name=remember]")).unwrap()
+            .click().unwrap();
+        sess.find_element(&By::css("input[value=admin]")).unwrap()
+            .click().unwrap();
+        sess.find_element(&By::css("option[value=fr]")).unwrap()
+            .click().unwrap();
+        sess.find_element(&By::css("button[type=button]")).unwrap()
+            .click().unwrap();
+        assert_eq!(sess.get_title().unwrap(), "Home page");
+
+        let stale = sess.find_element(&By::css("#submit")).unwrap();
+        stale.click().unwrap();
+        assert_eq!(sess.get_title().unwrap(), "Logged in");
+        let request = browser.requests().pop().unwrap();
+        assert_eq!(request.method, Method::Post);
+        assert_eq!(request.body.unwrap(),
+                   "user=alice&pass=s3cret%21&remember=yes&role=admin&lang=fr&bio=Hi");
+
+        match stale.text() {
+            Err(ref err) if err.is_webdriver_error("stale element reference") => {}
+            other => panic!("Want stale element reference, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn enter_submits_get_form() {
+        let browser = FakeBrowser::new();
+        browser.page("http://app.test/", "<form action='search'><input name='q'></form>");
+        browser.page("http://app.test/search", "<title>Results</title>");
+        let sess = browser.clone().session(&NewSessionCmd::default()).unwrap();
+        sess.go("http://app.test/").unwrap();
+        sess.find_element(&By::css("input")).unwrap()
+            .send_keys("a b\u{E007}").unwrap();
+        assert_eq!(sess.get_current_url().unwrap(), "http://app.test/search?q=a+b");
+    }
+
+    #[test]
+    fn scripts() {
+        let (_browser, sess) = session();
+        sess.wait_for_document_ready().unwrap();
+        let b = sess.find_element(&By::css("h1 b")).unwrap();
+        assert_eq!(b.outer_html().unwrap(), json!("<b>world</b>"));
+        match sess.execute(crate::messages::ExecuteCmd {
+            script: "return 1;".to_owned(),
+            args: vec![],
+        }) {
+            Err(Error::WebDriverError(err)) => assert_eq!(err.error, "unsupported operation"),
+            other => panic!("Want unsupported operation, got {:?}", other),
+        }
+
+        let lang = sess.find_element(&By::css("select")).unwrap();
+        assert!(!crate::select::Select::new(lang.clone()).unwrap().is_multiple().unwrap());
+        match lang.table() {
+            Err(Error::WebDriverError(err)) => {
+                assert_eq!(err.error, "unsupported operation");
+                assert!(err.message.starts_with("Element::table is not supported"), "{}", err.message);
+            }
+            other => panic!("Want unsupported operation, got {:?}", other),
+        }
+    }
+}
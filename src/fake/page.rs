@@ -0,0 +1,407 @@
+//! A loaded document and the state a browser keeps for it: form control
+//! values and the element references handed out to the client.
+
+use ego_tree::{NodeId, NodeRef};
+use scraper::{ElementRef, Html, Node};
+use url::{form_urlencoded, Url};
+
+use std::collections::HashMap;
+
+/// Elements whose content is never rendered.
+const NOT_RENDERED: &[&str] = &["head", "script", "style", "template", "noscript", "title"];
+
+/// Elements that start a new line in rendered text.
+const BLOCKS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "dd", "details", "dialog", "div", "dl", "dt",
+    "fieldset", "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6",
+    "header", "hr", "li", "main", "nav", "ol", "p", "pre", "section", "summary", "table",
+    "tr", "ul",
+];
+
+/// Attributes whose value is reported as `"true"` when present, as the
+/// WebDriver specification requires.
+const BOOLEAN_ATTRIBUTES: &[&str] = &[
+    "async", "autofocus", "autoplay", "checked", "controls", "default", "defer", "disabled",
+    "formnovalidate", "hidden", "ismap", "loop", "multiple", "muted", "nomodule", "novalidate",
+    "open", "readonly", "required", "reversed", "selected",
+];
+
+pub struct Page {
+    pub url: Url,
+    pub html: Html,
+    /// Distinguishes references to elements of this page from those of
+    /// earlier pages, which are stale.
+    pub generation: u64,
+    /// Elements handed out to the client, indexed by reference.
+    elements: Vec<NodeId>,
+    values: HashMap<NodeId, String>,
+    checked: HashMap<NodeId, bool>,
+}
+
+/// A form submission: the method, target URL and encoded fields.
+pub struct Submission {
+    pub post: bool,
+    pub url: Url,
+    pub body: String,
+}
+
+impl Page {
+    pub fn new(url: Url, html: &str, generation: u64) -> Self {
+        Page {
+            url,
+            html: Html::parse_document(html),
+            generation,
+            elements: Vec::new(),
+            values: HashMap::new(),
+            checked: HashMap::new(),
+        }
+    }
+
+    pub fn node(&self, id: NodeId) -> NodeRef<'_, Node> {
+        self.html.tree.get(id).expect("node id from this page")
+    }
+
+    pub fn element(&self, id: NodeId) -> ElementRef<'_> {
+        ElementRef::wrap(self.node(id)).expect("element node")
+    }
+
+    /// The reference to give the client for an element.
+    pub fn reference(&mut self, id: NodeId) -> String {
+        let index = match self.elements.iter().position(|&e| e == id) {
+            Some(index) => index,
+            None => {
+                self.elements.push(id);
+                self.elements.len() - 1
+            }
+        };
+        format!("{}-{}", self.generation, index)
+    }
+
+    /// Look up a reference. `Err(true)` means it belongs to an earlier
+    /// page; `Err(false)` that it was never issued.
+    pub fn lookup(&self, reference: &str) -> Result<NodeId, bool> {
+        let mut parts = reference.splitn(2, '-');
+        let generation = parts.next().and_then(|g| g.parse::<u64>().ok());
+        let index = parts.next().and_then(|i| i.parse::<usize>().ok());
+        match (generation, index) {
+            (Some(g), Some(i)) if g == self.generation =>
+                self.elements.get(i).cloned().ok_or(false),
+            (Some(g), Some(_)) if g < self.generation => Err(true),
+            _ => Err(false),
+        }
+    }
+
+    pub fn title(&self) -> String {
+        self.html.tree.root().descendants()
+            .find(|n| n.value().as_element().is_some_and(|e| e.name() == "title"))
+            .map(|n| collapse(&text_content(n)))
+            .unwrap_or_default()
+    }
+
+    pub fn tag(&self, id: NodeId) -> &str {
+        self.element(id).value().name()
+    }
+
+    pub fn attr(&self, id: NodeId, name: &str) -> Option<&str> {
+        self.element(id).value().attr(name)
+    }
+
+    /// The value of an attribute as Get Element Attribute reports it.
+    pub fn attribute(&self, id: NodeId, name: &str) -> Option<String> {
+        let name = name.to_lowercase();
+        self.attr(id, &name).map(|value| {
+            if BOOLEAN_ATTRIBUTES.contains(&name.as_str()) {
+                "true".to_owned()
+            } else {
+                value.to_owned()
+            }
+        })
+    }
+
+    fn input_type(&self, id: NodeId) -> String {
+        self.attr(id, "type").unwrap_or("text").to_lowercase()
+    }
+
+    /// Whether the element is an `input` of one of the given types.
+    pub fn is_input(&self, id: NodeId, types: &[&str]) -> bool {
+        self.tag(id) == "input" && types.contains(&self.input_type(id).as_str())
+    }
+
+    pub fn is_text_control(&self, id: NodeId) -> bool {
+        self.tag(id) == "textarea"
+            || (self.tag(id) == "input"
+                && !self.is_input(id, &["checkbox", "radio", "submit", "button", "reset",
+                                        "image", "hidden"]))
+    }
+
+    pub fn is_displayed(&self, id: NodeId) -> bool {
+        if self.is_input(id, &["hidden"]) {
+            return false;
+        }
+        self.node(id).ancestors().chain(Some(self.node(id)))
+            .filter_map(|n| n.value().as_element())
+            .all(|el| {
+                let style = el.attr("style").unwrap_or("").replace(' ', "").to_lowercase();
+                !NOT_RENDERED.contains(&el.name())
+                    && el.attr("hidden").is_none()
+                    && !style.contains("display:none")
+                    && !style.contains("visibility:hidden")
+            })
+    }
+
+    pub fn is_enabled(&self, id: NodeId) -> bool {
+        let form_control = ["button", "input", "select", "textarea", "option", "optgroup",
+                            "fieldset"];
+        !form_control.contains(&self.tag(id))
+            || !self.node(id).ancestors().chain(Some(self.node(id)))
+                .filter_map(|n| n.value().as_element())
+                .any(|el| form_control.contains(&el.name()) && el.attr("disabled").is_some())
+    }
+
+    /// The rendered text of an element, roughly as `innerText` computes it.
+    pub fn text(&self, id: NodeId) -> String {
+        if !self.is_displayed(id) {
+            return String::new();
+        }
+        let mut raw = String::new();
+        self.render_text(self.node(id), &mut raw);
+        raw.lines().map(collapse).filter(|l| !l.is_empty()).collect::<Vec<_>>().join("\n")
+    }
+
+    fn render_text(&self, node: NodeRef<'_, Node>, out: &mut String) {
+        match node.value() {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(el) => {
+                let name = el.name();
+                if !self.is_displayed(node.id()) {
+                    return;
+                }
+                if name == "br" {
+                    out.push('\n');
+                    return;
+                }
+                let block = BLOCKS.contains(&name);
+                if block {
+                    out.push('\n');
+                }
+                for child in node.children() {
+                    self.render_text(child, out);
+                }
+                if block {
+                    out.push('\n');
+                } else if name == "td" || name == "th" {
+                    out.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// All text in an element, rendered or not, like `textContent`.
+    pub fn text_content(&self, id: NodeId) -> String {
+        text_content(self.node(id))
+    }
+
+    /// The current value of a form control.
+    pub fn value(&self, id: NodeId) -> String {
+        if let Some(value) = self.values.get(&id) {
+            return value.clone();
+        }
+        match self.tag(id) {
+            "textarea" => self.text_content(id),
+            "select" => self.selected_options(id).first()
+                .map(|&o| self.value(o))
+                .unwrap_or_default(),
+            "option" => self.attr(id, "value").map(str::to_owned)
+                .unwrap_or_else(|| collapse(&self.text_content(id))),
+            _ if self.is_input(id, &["checkbox", "radio"]) =>
+                self.attr(id, "value").unwrap_or("on").to_owned(),
+            _ => self.attr(id, "value").unwrap_or("").to_owned(),
+        }
+    }
+
+    pub fn set_value(&mut self, id: NodeId, value: String) {
+        self.values.insert(id, value);
+    }
+
+    /// Whether a checkbox or radio button is checked, or an option
+    /// selected.
+    pub fn is_checked(&self, id: NodeId) -> bool {
+        if let Some(&checked) = self.checked.get(&id) {
+            return checked;
+        }
+        if self.tag(id) == "option" {
+            return self.selected_options(self.select_of(id).unwrap_or(id)).contains(&id);
+        }
+        self.attr(id, "checked").is_some()
+    }
+
+    fn select_of(&self, option: NodeId) -> Option<NodeId> {
+        self.node(option).ancestors()
+            .find(|n| n.value().as_element().is_some_and(|e| e.name() == "select"))
+            .map(|n| n.id())
+    }
+
+    fn options(&self, select: NodeId) -> Vec<NodeId> {
+        self.node(select).descendants()
+            .filter(|n| n.value().as_element().is_some_and(|e| e.name() == "option"))
+            .map(|n| n.id())
+            .collect()
+    }
+
+    fn selected_options(&self, select: NodeId) -> Vec<NodeId> {
+        let options = self.options(select);
+        let selected: Vec<NodeId> = options.iter().cloned()
+            .filter(|o| match self.checked.get(o) {
+                Some(&checked) => checked,
+                None => self.attr(*o, "selected").is_some(),
+            })
+            .collect();
+        if self.attr(select, "multiple").is_some() {
+            return selected;
+        }
+        // A single select always has exactly one selected option, the last
+        // marked one or else the first.
+        match selected.last() {
+            Some(&last) => vec![last],
+            None => options.into_iter().take(1).collect(),
+        }
+    }
+
+    /// Click a checkbox, radio button or option.
+    pub fn toggle(&mut self, id: NodeId) {
+        if self.tag(id) == "option" {
+            let select = match self.select_of(id) {
+                Some(select) => select,
+                None => return,
+            };
+            if self.attr(select, "multiple").is_some() {
+                let checked = self.is_checked(id);
+                self.checked.insert(id, !checked);
+            } else {
+                for option in self.options(select) {
+                    self.checked.insert(option, option == id);
+                }
+            }
+        } else if self.is_input(id, &["checkbox"]) {
+            let checked = self.is_checked(id);
+            self.checked.insert(id, !checked);
+        } else if self.is_input(id, &["radio"]) {
+            for other in self.radio_group(id) {
+                self.checked.insert(other, false);
+            }
+            self.checked.insert(id, true);
+        }
+    }
+
+    fn radio_group(&self, id: NodeId) -> Vec<NodeId> {
+        let name = match self.attr(id, "name") {
+            Some(name) => name,
+            None => return vec![id],
+        };
+        let scope = self.form_of(id).unwrap_or_else(|| self.html.tree.root().id());
+        self.node(scope).descendants()
+            .filter(|n| ElementRef::wrap(*n).is_some_and(|_| {
+                self.is_input(n.id(), &["radio"]) && self.attr(n.id(), "name") == Some(name)
+            }))
+            .map(|n| n.id())
+            .collect()
+    }
+
+    /// Select the first option whose text starts with `text`, as typing
+    /// into a focused select element does.
+    pub fn select_by_text(&mut self, select: NodeId, text: &str) {
+        let option = self.options(select).into_iter()
+            .find(|&o| collapse(&self.text_content(o)).to_lowercase()
+                  .starts_with(&text.to_lowercase()));
+        if let Some(option) = option {
+            if !self.is_checked(option) {
+                self.toggle(option);
+            }
+        }
+    }
+
+    /// Restore the initial state of a form's controls.
+    pub fn reset(&mut self, form: NodeId) {
+        for node in self.node(form).descendants().map(|n| n.id()).collect::<Vec<_>>() {
+            self.values.remove(&node);
+            self.checked.remove(&node);
+        }
+    }
+
+    /// The form a control belongs to.
+    pub fn form_of(&self, id: NodeId) -> Option<NodeId> {
+        if let Some(form_id) = self.attr(id, "form") {
+            return self.find_by_id(form_id);
+        }
+        self.node(id).ancestors()
+            .find(|n| n.value().as_element().is_some_and(|e| e.name() == "form"))
+            .map(|n| n.id())
+    }
+
+    pub fn find_by_id(&self, id: &str) -> Option<NodeId> {
+        self.html.tree.root().descendants()
+            .find(|n| n.value().as_element().and_then(|e| e.attr("id")) == Some(id))
+            .map(|n| n.id())
+    }
+
+    /// Resolve a URL relative to this page.
+    pub fn resolve(&self, href: &str) -> Option<Url> {
+        self.url.join(href.trim()).ok()
+    }
+
+    /// The submission for `form`, sent by `submitter` if it is a button.
+    pub fn submission(&self, form: NodeId, submitter: Option<NodeId>) -> Option<Submission> {
+        let attr = |name: &str| {
+            submitter.and_then(|s| self.attr(s, &format!("form{}", name)))
+                .or_else(|| self.attr(form, name))
+        };
+        let post = attr("method").is_some_and(|m| m.eq_ignore_ascii_case("post"));
+        let mut url = self.resolve(attr("action").unwrap_or(""))?;
+        url.set_fragment(None);
+
+        let mut fields = form_urlencoded::Serializer::new(String::new());
+        let controls = self.html.tree.root().descendants()
+            .filter(|n| ElementRef::wrap(*n).is_some())
+            .map(|n| n.id())
+            .filter(|&id| ["input", "select", "textarea", "button"].contains(&self.tag(id))
+                    && self.form_of(id) == Some(form));
+        for id in controls {
+            let name = match self.attr(id, "name") {
+                Some(name) if !name.is_empty() && self.is_enabled(id) => name,
+                _ => continue,
+            };
+            let is_button = self.tag(id) == "button"
+                || self.is_input(id, &["submit", "image", "button", "reset"]);
+            if is_button && submitter != Some(id) {
+                continue;
+            }
+            if self.is_input(id, &["checkbox", "radio"]) && !self.is_checked(id) {
+                continue;
+            }
+            if self.tag(id) == "select" {
+                for option in self.selected_options(id) {
+                    fields.append_pair(name, &self.value(option));
+                }
+            } else {
+                fields.append_pair(name, &self.value(id));
+            }
+        }
+        let body = fields.finish();
+        if !post {
+            url.set_query(Some(&body));
+        }
+        Some(Submission { post, url, body })
+    }
+}
+
+fn text_content(node: NodeRef<'_, Node>) -> String {
+    node.descendants()
+        .filter_map(|n| n.value().as_text().map(|t| t.to_string()))
+        .collect()
+}
+
+/// Collapse runs of whitespace to single spaces and trim.
+pub fn collapse(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
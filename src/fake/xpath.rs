@@ -0,0 +1,953 @@
+//! A subset of XPath 1.0, evaluated against a parsed HTML document.
+//!
+//! Supported: absolute and relative location paths, `//`, all axes except
+//! `namespace`, the `.`, `..` and `@` abbreviations, name, `*`, `text()`
+//! and `node()` tests, predicates (including positions), `|`, `and`, `or`,
+//! comparisons, string and number literals, and the functions `last`,
+//! `position`, `count`, `string`, `concat`, `contains`, `starts-with`,
+//! `normalize-space`, `translate`, `string-length`, `substring`,
+//! `substring-before`, `substring-after`, `not`, `true`, `false`,
+//! `boolean`, `number`, `name` and `local-name`, listed in `FUNCTIONS`.
+//! Functions from later XPath versions, such as `ends-with`, are rejected
+//! as they are by browsers.
+//!
+//! Arithmetic is not supported. Element and attribute names are matched
+//! case-insensitively, as browsers do for HTML documents.
+
+use ego_tree::{NodeId, NodeRef};
+use scraper::{Html, Node};
+
+use std::collections::HashMap;
+
+/// The supported core library functions, with their minimum and maximum
+/// argument counts.
+pub const FUNCTIONS: &[(&str, usize, usize)] = &[
+    ("last", 0, 0), ("position", 0, 0), ("count", 1, 1), ("local-name", 0, 1),
+    ("name", 0, 1), ("string", 0, 1), ("concat", 2, usize::MAX), ("starts-with", 2, 2),
+    ("contains", 2, 2), ("substring-before", 2, 2), ("substring-after", 2, 2),
+    ("substring", 2, 3), ("string-length", 0, 1), ("normalize-space", 0, 1),
+    ("translate", 3, 3), ("boolean", 1, 1), ("not", 1, 1), ("true", 0, 0), ("false", 0, 0),
+    ("number", 0, 1),
+];
+
+/// Evaluate `expr` with `context` as the context node, and return the
+/// elements it selects in document order.
+pub fn select(html: &Html, context: NodeId, expr: &str) -> Result<Vec<NodeId>, String> {
+    let expr = Parser::new(expr)?.parse()?;
+    let eval = Evaluator::new(html);
+    let ctx = Context { node: XNode::Node(context), position: 1, size: 1 };
+    match eval.eval(&expr, &ctx)? {
+        Value::Nodes(nodes) => nodes.into_iter()
+            .map(|node| match node {
+                XNode::Node(id) if eval.node(id).value().is_element() => Ok(id),
+                _ => Err("The result is not a set of elements".to_owned()),
+            })
+            .collect(),
+        _ => Err("The result is not a set of elements".to_owned()),
+    }
+}
+
+// -------- Lexer
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Slash,
+    DoubleSlash,
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    At,
+    Comma,
+    Pipe,
+    Dot,
+    DotDot,
+    Axis(String),
+    Star,
+    Op(&'static str),
+    Literal(String),
+    Number(f64),
+    Name(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).cloned();
+        let token = match c {
+            ' ' | '\t' | '\n' | '\r' => { i += 1; continue; }
+            '/' if next == Some('/') => { i += 1; Token::DoubleSlash }
+            '/' => Token::Slash,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '@' => Token::At,
+            ',' => Token::Comma,
+            '|' => Token::Pipe,
+            '*' => Token::Star,
+            '=' => Token::Op("="),
+            '!' if next == Some('=') => { i += 1; Token::Op("!=") }
+            '<' if next == Some('=') => { i += 1; Token::Op("<=") }
+            '>' if next == Some('=') => { i += 1; Token::Op(">=") }
+            '<' => Token::Op("<"),
+            '>' => Token::Op(">"),
+            '.' if next == Some('.') => { i += 1; Token::DotDot }
+            '.' if !next.is_some_and(|n| n.is_ascii_digit()) => Token::Dot,
+            '"' | '\'' => {
+                let end = chars[i + 1..].iter().position(|&ch| ch == c)
+                    .ok_or_else(|| "Unterminated string literal".to_owned())?;
+                let literal = chars[i + 1..i + 1 + end].iter().collect();
+                i += end + 1;
+                Token::Literal(literal)
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let start = i;
+                while i + 1 < chars.len() && (chars[i + 1].is_ascii_digit() || chars[i + 1] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..=i].iter().collect();
+                Token::Number(text.parse().map_err(|_| format!("Invalid number {}", text))?)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i + 1 < chars.len()
+                    && (chars[i + 1].is_alphanumeric() || "_-.".contains(chars[i + 1]))
+                {
+                    i += 1;
+                }
+                let name: String = chars[start..=i].iter().collect();
+                if chars.get(i + 1) == Some(&':') && chars.get(i + 2) == Some(&':') {
+                    i += 2;
+                    Token::Axis(name)
+                } else {
+                    Token::Name(name)
+                }
+            }
+            c => return Err(format!("Unexpected character {:?}", c)),
+        };
+        tokens.push(token);
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+// -------- Parser
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Axis {
+    Ancestor,
+    AncestorOrSelf,
+    Attribute,
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Following,
+    FollowingSibling,
+    Parent,
+    Preceding,
+    PrecedingSibling,
+    Itself,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum NodeTest {
+    Name(String),
+    Any,
+    Text,
+    Node,
+    Comment,
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    axis: Axis,
+    test: NodeTest,
+    predicates: Vec<Expr>,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Compare(&'static str, Box<Expr>, Box<Expr>),
+    Union(Box<Expr>, Box<Expr>),
+    Literal(String),
+    Number(f64),
+    Call(String, Vec<Expr>),
+    /// A location path: absolute if it starts at the document root.
+    Path { absolute: bool, steps: Vec<Step> },
+    /// A primary expression with predicates, then optionally a path.
+    Filter(Box<Expr>, Vec<Expr>, Vec<Step>),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+fn descendant_or_self() -> Step {
+    Step { axis: Axis::DescendantOrSelf, test: NodeTest::Node, predicates: vec![] }
+}
+
+impl Parser {
+    fn new(input: &str) -> Result<Self, String> {
+        Ok(Parser { tokens: tokenize(input)?, pos: 0 })
+    }
+
+    fn parse(mut self) -> Result<Expr, String> {
+        let expr = self.expr()?;
+        match self.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("Unexpected {:?}", token)),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        match self.advance() {
+            Some(ref t) if *t == token => Ok(()),
+            other => Err(format!("Expected {:?}, found {:?}", token, other)),
+        }
+    }
+
+    fn is_name(&self, name: &str) -> bool {
+        match self.peek() {
+            Some(Token::Name(n)) => n == name,
+            _ => false,
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.and_expr()?;
+        while self.is_name("or") {
+            self.advance();
+            left = Expr::Or(Box::new(left), Box::new(self.and_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.equality_expr()?;
+        while self.is_name("and") {
+            self.advance();
+            left = Expr::And(Box::new(left), Box::new(self.equality_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn equality_expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.relational_expr()?;
+        while let Some(&Token::Op(op)) = self.peek() {
+            if op != "=" && op != "!=" {
+                break;
+            }
+            self.advance();
+            left = Expr::Compare(op, Box::new(left), Box::new(self.relational_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn relational_expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.union_expr()?;
+        while let Some(&Token::Op(op)) = self.peek() {
+            if op == "=" || op == "!=" {
+                break;
+            }
+            self.advance();
+            left = Expr::Compare(op, Box::new(left), Box::new(self.union_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn union_expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.path_expr()?;
+        while self.peek() == Some(&Token::Pipe) {
+            self.advance();
+            left = Expr::Union(Box::new(left), Box::new(self.path_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn path_expr(&mut self) -> Result<Expr, String> {
+        let primary = match self.peek() {
+            Some(Token::LParen) | Some(Token::Literal(_)) | Some(Token::Number(_)) => true,
+            Some(Token::Name(name)) => {
+                self.peek_at(1) == Some(&Token::LParen)
+                    && !["text", "node", "comment"].contains(&name.as_str())
+            }
+            _ => false,
+        };
+        if !primary {
+            return self.location_path();
+        }
+
+        let expr = self.primary_expr()?;
+        let predicates = self.predicates()?;
+        let steps = match self.peek() {
+            Some(Token::Slash) => {
+                self.advance();
+                self.relative_path()?
+            }
+            Some(Token::DoubleSlash) => {
+                self.advance();
+                let mut steps = vec![descendant_or_self()];
+                steps.extend(self.relative_path()?);
+                steps
+            }
+            _ => vec![],
+        };
+        if predicates.is_empty() && steps.is_empty() {
+            Ok(expr)
+        } else {
+            Ok(Expr::Filter(Box::new(expr), predicates, steps))
+        }
+    }
+
+    fn primary_expr(&mut self) -> Result<Expr, String> {
+        match self.advance() {
+            Some(Token::LParen) => {
+                let expr = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::Literal(s)) => Ok(Expr::Literal(s)),
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Name(name)) => {
+                self.expect(Token::LParen)?;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    args.push(self.expr()?);
+                    while self.peek() == Some(&Token::Comma) {
+                        self.advance();
+                        args.push(self.expr()?);
+                    }
+                }
+                self.expect(Token::RParen)?;
+                Ok(Expr::Call(name, args))
+            }
+            other => Err(format!("Unexpected {:?}", other)),
+        }
+    }
+
+    fn location_path(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(Token::Slash) => {
+                self.advance();
+                let steps = if self.starts_step() { self.relative_path()? } else { vec![] };
+                Ok(Expr::Path { absolute: true, steps })
+            }
+            Some(Token::DoubleSlash) => {
+                self.advance();
+                let mut steps = vec![descendant_or_self()];
+                steps.extend(self.relative_path()?);
+                Ok(Expr::Path { absolute: true, steps })
+            }
+            _ => Ok(Expr::Path { absolute: false, steps: self.relative_path()? }),
+        }
+    }
+
+    fn starts_step(&self) -> bool {
+        matches!(self.peek(),
+                 Some(Token::Dot) | Some(Token::DotDot) | Some(Token::At) | Some(Token::Star)
+                 | Some(Token::Axis(_)) | Some(Token::Name(_)))
+    }
+
+    fn relative_path(&mut self) -> Result<Vec<Step>, String> {
+        let mut steps = vec![self.step()?];
+        loop {
+            match self.peek() {
+                Some(Token::Slash) => {
+                    self.advance();
+                }
+                Some(Token::DoubleSlash) => {
+                    self.advance();
+                    steps.push(descendant_or_self());
+                }
+                _ => return Ok(steps),
+            }
+            steps.push(self.step()?);
+        }
+    }
+
+    fn step(&mut self) -> Result<Step, String> {
+        let axis = match self.peek().cloned() {
+            Some(Token::Dot) => {
+                self.advance();
+                return Ok(Step { axis: Axis::Itself, test: NodeTest::Node, predicates: vec![] });
+            }
+            Some(Token::DotDot) => {
+                self.advance();
+                return Ok(Step { axis: Axis::Parent, test: NodeTest::Node, predicates: vec![] });
+            }
+            Some(Token::At) => {
+                self.advance();
+                Axis::Attribute
+            }
+            Some(Token::Axis(name)) => {
+                self.advance();
+                match name.as_str() {
+                    "ancestor" => Axis::Ancestor,
+                    "ancestor-or-self" => Axis::AncestorOrSelf,
+                    "attribute" => Axis::Attribute,
+                    "child" => Axis::Child,
+                    "descendant" => Axis::Descendant,
+                    "descendant-or-self" => Axis::DescendantOrSelf,
+                    "following" => Axis::Following,
+                    "following-sibling" => Axis::FollowingSibling,
+                    "parent" => Axis::Parent,
+                    "preceding" => Axis::Preceding,
+                    "preceding-sibling" => Axis::PrecedingSibling,
+                    "self" => Axis::Itself,
+                    other => return Err(format!("Unsupported axis {}", other)),
+                }
+            }
+            _ => Axis::Child,
+        };
+        let test = match self.advance() {
+            Some(Token::Star) => NodeTest::Any,
+            Some(Token::Name(name)) => {
+                if self.peek() == Some(&Token::LParen) {
+                    self.advance();
+                    self.expect(Token::RParen)?;
+                    match name.as_str() {
+                        "text" => NodeTest::Text,
+                        "node" => NodeTest::Node,
+                        "comment" => NodeTest::Comment,
+                        other => return Err(format!("Unsupported node test {}()", other)),
+                    }
+                } else {
+                    NodeTest::Name(name.to_lowercase())
+                }
+            }
+            other => return Err(format!("Expected a node test, found {:?}", other)),
+        };
+        Ok(Step { axis, test, predicates: self.predicates()? })
+    }
+
+    fn predicates(&mut self) -> Result<Vec<Expr>, String> {
+        let mut predicates = Vec::new();
+        while self.peek() == Some(&Token::LBracket) {
+            self.advance();
+            predicates.push(self.expr()?);
+            self.expect(Token::RBracket)?;
+        }
+        Ok(predicates)
+    }
+}
+
+// -------- Evaluator
+
+/// A node in the XPath data model: a tree node, or an attribute given by
+/// its element and its index among the element's attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum XNode {
+    Node(NodeId),
+    Attr(NodeId, usize),
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Nodes(Vec<XNode>),
+    Str(String),
+    Num(f64),
+    Bool(bool),
+}
+
+struct Context {
+    node: XNode,
+    position: usize,
+    size: usize,
+}
+
+struct Evaluator<'a> {
+    html: &'a Html,
+    /// Document order of every tree node.
+    order: HashMap<NodeId, usize>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(html: &'a Html) -> Self {
+        let order = html.tree.root().descendants().enumerate()
+            .map(|(i, node)| (node.id(), i))
+            .collect();
+        Evaluator { html, order }
+    }
+
+    fn node(&self, id: NodeId) -> NodeRef<'a, Node> {
+        self.html.tree.get(id).expect("node id from this document")
+    }
+
+    fn sort_key(&self, node: &XNode) -> (usize, usize) {
+        match *node {
+            XNode::Node(id) => (self.order[&id], 0),
+            XNode::Attr(id, index) => (self.order[&id], index + 1),
+        }
+    }
+
+    fn sort(&self, nodes: &mut Vec<XNode>) {
+        nodes.sort_by_key(|n| self.sort_key(n));
+        nodes.dedup();
+    }
+
+    fn attrs(&self, id: NodeId) -> Vec<(String, String)> {
+        match self.node(id).value() {
+            Node::Element(el) => el.attrs().map(|(k, v)| (k.to_owned(), v.to_owned())).collect(),
+            _ => vec![],
+        }
+    }
+
+    fn string_value(&self, node: &XNode) -> String {
+        match *node {
+            XNode::Attr(id, index) => self.attrs(id).swap_remove(index).1,
+            XNode::Node(id) => {
+                let node = self.node(id);
+                match node.value() {
+                    Node::Text(text) => text.to_string(),
+                    Node::Comment(comment) => comment.to_string(),
+                    _ => node.descendants()
+                        .filter_map(|n| n.value().as_text().map(|t| t.to_string()))
+                        .collect(),
+                }
+            }
+        }
+    }
+
+    fn name(&self, node: &XNode) -> String {
+        match *node {
+            XNode::Attr(id, index) => self.attrs(id).swap_remove(index).0,
+            XNode::Node(id) => match self.node(id).value() {
+                Node::Element(el) => el.name().to_owned(),
+                _ => String::new(),
+            },
+        }
+    }
+
+    fn matches(&self, node: &XNode, axis: Axis, test: &NodeTest) -> bool {
+        match *node {
+            XNode::Attr(..) => match *test {
+                NodeTest::Name(ref name) => self.name(node).to_lowercase() == *name,
+                NodeTest::Any | NodeTest::Node => true,
+                _ => false,
+            },
+            XNode::Node(id) => {
+                let value = self.node(id).value();
+                match *test {
+                    NodeTest::Name(ref name) => match value {
+                        Node::Element(el) if axis != Axis::Attribute =>
+                            el.name().to_lowercase() == *name,
+                        _ => false,
+                    },
+                    NodeTest::Any => value.is_element() && axis != Axis::Attribute,
+                    NodeTest::Text => value.is_text(),
+                    NodeTest::Comment => value.is_comment(),
+                    NodeTest::Node => true,
+                }
+            }
+        }
+    }
+
+    /// The nodes on `axis` from `node`, in document order.
+    fn axis_nodes(&self, node: XNode, axis: Axis) -> Vec<XNode> {
+        let id = match node {
+            XNode::Node(id) => id,
+            XNode::Attr(id, _) => {
+                return match axis {
+                    Axis::Parent => vec![XNode::Node(id)],
+                    Axis::Ancestor | Axis::AncestorOrSelf => {
+                        let mut nodes = self.axis_nodes(XNode::Node(id), Axis::AncestorOrSelf);
+                        if axis == Axis::AncestorOrSelf {
+                            nodes.push(node);
+                        }
+                        nodes
+                    }
+                    Axis::Itself => vec![node],
+                    _ => vec![],
+                };
+            }
+        };
+        let tree_node = self.node(id);
+        let ids: Vec<NodeId> = match axis {
+            Axis::Attribute => {
+                return (0..self.attrs(id).len()).map(|i| XNode::Attr(id, i)).collect();
+            }
+            Axis::Child => tree_node.children().map(|n| n.id()).collect(),
+            Axis::Descendant => tree_node.descendants().skip(1).map(|n| n.id()).collect(),
+            Axis::DescendantOrSelf => tree_node.descendants().map(|n| n.id()).collect(),
+            Axis::Parent => tree_node.parent().map(|n| n.id()).into_iter().collect(),
+            Axis::Ancestor => {
+                let mut ids: Vec<_> = tree_node.ancestors().map(|n| n.id()).collect();
+                ids.reverse();
+                ids
+            }
+            Axis::AncestorOrSelf => {
+                let mut ids: Vec<_> = tree_node.ancestors().map(|n| n.id()).collect();
+                ids.reverse();
+                ids.push(id);
+                ids
+            }
+            Axis::FollowingSibling => tree_node.next_siblings().map(|n| n.id()).collect(),
+            Axis::PrecedingSibling => {
+                let mut ids: Vec<_> = tree_node.prev_siblings().map(|n| n.id()).collect();
+                ids.reverse();
+                ids
+            }
+            Axis::Following => {
+                let position = self.order[&id];
+                let last_descendant = tree_node.descendants().last().map_or(id, |n| n.id());
+                let after = self.order[&last_descendant];
+                self.html.tree.root().descendants()
+                    .filter(|n| self.order[&n.id()] > after && self.order[&n.id()] > position)
+                    .map(|n| n.id())
+                    .collect()
+            }
+            Axis::Preceding => {
+                let position = self.order[&id];
+                let ancestors: Vec<NodeId> = tree_node.ancestors().map(|n| n.id()).collect();
+                self.html.tree.root().descendants()
+                    .filter(|n| self.order[&n.id()] < position && !ancestors.contains(&n.id()))
+                    .map(|n| n.id())
+                    .collect()
+            }
+            Axis::Itself => vec![id],
+        };
+        ids.into_iter().map(XNode::Node).collect()
+    }
+
+    fn is_reverse(axis: Axis) -> bool {
+        matches!(axis, Axis::Ancestor | Axis::AncestorOrSelf | Axis::Preceding
+                       | Axis::PrecedingSibling)
+    }
+
+    fn apply_step(&self, input: &[XNode], step: &Step) -> Result<Vec<XNode>, String> {
+        let mut output = Vec::new();
+        for &node in input {
+            let mut candidates: Vec<XNode> = self.axis_nodes(node, step.axis).into_iter()
+                .filter(|n| self.matches(n, step.axis, &step.test))
+                .collect();
+            // Positions in predicates count outwards from the context node.
+            if Self::is_reverse(step.axis) {
+                candidates.reverse();
+            }
+            for predicate in &step.predicates {
+                candidates = self.filter(candidates, predicate)?;
+            }
+            output.extend(candidates);
+        }
+        self.sort(&mut output);
+        Ok(output)
+    }
+
+    fn filter(&self, nodes: Vec<XNode>, predicate: &Expr) -> Result<Vec<XNode>, String> {
+        let size = nodes.len();
+        let mut kept = Vec::new();
+        for (i, node) in nodes.into_iter().enumerate() {
+            let ctx = Context { node, position: i + 1, size };
+            let keep = match self.eval(predicate, &ctx)? {
+                Value::Num(n) => n == (i + 1) as f64,
+                other => self.boolean(&other),
+            };
+            if keep {
+                kept.push(node);
+            }
+        }
+        Ok(kept)
+    }
+
+    fn apply_steps(&self, mut nodes: Vec<XNode>, steps: &[Step]) -> Result<Vec<XNode>, String> {
+        for step in steps {
+            nodes = self.apply_step(&nodes, step)?;
+        }
+        Ok(nodes)
+    }
+
+    fn eval(&self, expr: &Expr, ctx: &Context) -> Result<Value, String> {
+        Ok(match *expr {
+            Expr::Or(ref a, ref b) => Value::Bool(
+                self.eval_bool(a, ctx)? || self.eval_bool(b, ctx)?),
+            Expr::And(ref a, ref b) => Value::Bool(
+                self.eval_bool(a, ctx)? && self.eval_bool(b, ctx)?),
+            Expr::Compare(op, ref a, ref b) => {
+                let a = self.eval(a, ctx)?;
+                let b = self.eval(b, ctx)?;
+                Value::Bool(self.compare(op, &a, &b))
+            }
+            Expr::Union(ref a, ref b) => {
+                match (self.eval(a, ctx)?, self.eval(b, ctx)?) {
+                    (Value::Nodes(mut a), Value::Nodes(b)) => {
+                        a.extend(b);
+                        self.sort(&mut a);
+                        Value::Nodes(a)
+                    }
+                    _ => return Err("Both sides of | must be node sets".to_owned()),
+                }
+            }
+            Expr::Literal(ref s) => Value::Str(s.clone()),
+            Expr::Number(n) => Value::Num(n),
+            Expr::Call(ref name, ref args) => self.call(name, args, ctx)?,
+            Expr::Path { absolute, ref steps } => {
+                let start = if absolute {
+                    XNode::Node(self.html.tree.root().id())
+                } else {
+                    ctx.node
+                };
+                Value::Nodes(self.apply_steps(vec![start], steps)?)
+            }
+            Expr::Filter(ref primary, ref predicates, ref steps) => {
+                let mut nodes = match self.eval(primary, ctx)? {
+                    Value::Nodes(nodes) => nodes,
+                    _ => return Err("Predicates and paths need a node set".to_owned()),
+                };
+                for predicate in predicates {
+                    nodes = self.filter(nodes, predicate)?;
+                }
+                Value::Nodes(self.apply_steps(nodes, steps)?)
+            }
+        })
+    }
+
+    fn eval_bool(&self, expr: &Expr, ctx: &Context) -> Result<bool, String> {
+        Ok(self.boolean(&self.eval(expr, ctx)?))
+    }
+
+    fn eval_string(&self, expr: &Expr, ctx: &Context) -> Result<String, String> {
+        Ok(self.string(&self.eval(expr, ctx)?))
+    }
+
+    fn boolean(&self, value: &Value) -> bool {
+        match *value {
+            Value::Nodes(ref nodes) => !nodes.is_empty(),
+            Value::Str(ref s) => !s.is_empty(),
+            Value::Num(n) => n != 0.0 && !n.is_nan(),
+            Value::Bool(b) => b,
+        }
+    }
+
+    fn string(&self, value: &Value) -> String {
+        match *value {
+            Value::Nodes(ref nodes) => nodes.first().map(|n| self.string_value(n))
+                .unwrap_or_default(),
+            Value::Str(ref s) => s.clone(),
+            Value::Num(n) if n.fract() == 0.0 && n.is_finite() => format!("{}", n as i64),
+            Value::Num(n) => format!("{}", n),
+            Value::Bool(b) => b.to_string(),
+        }
+    }
+
+    fn number(&self, value: &Value) -> f64 {
+        match *value {
+            Value::Num(n) => n,
+            Value::Bool(b) => if b { 1.0 } else { 0.0 },
+            _ => self.string(value).trim().parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    fn compare(&self, op: &str, a: &Value, b: &Value) -> bool {
+        // A node set compares true if any of its nodes does.
+        match (a, b) {
+            (Value::Nodes(nodes), other) if !matches!(other, Value::Bool(_)) =>
+                return nodes.iter()
+                    .any(|n| self.compare(op, &Value::Str(self.string_value(n)), other)),
+            (other, Value::Nodes(nodes)) if !matches!(other, Value::Bool(_)) =>
+                return nodes.iter()
+                    .any(|n| self.compare(op, other, &Value::Str(self.string_value(n)))),
+            _ => {}
+        }
+        match op {
+            "=" | "!=" => {
+                let equal = match (a, b) {
+                    (Value::Bool(_), _) | (_, Value::Bool(_)) =>
+                        self.boolean(a) == self.boolean(b),
+                    (Value::Num(_), _) | (_, Value::Num(_)) => self.number(a) == self.number(b),
+                    _ => self.string(a) == self.string(b),
+                };
+                equal == (op == "=")
+            }
+            _ => {
+                let (a, b) = (self.number(a), self.number(b));
+                match op {
+                    "<" => a < b,
+                    "<=" => a <= b,
+                    ">" => a > b,
+                    _ => a >= b,
+                }
+            }
+        }
+    }
+
+    fn call(&self, name: &str, args: &[Expr], ctx: &Context) -> Result<Value, String> {
+        match FUNCTIONS.iter().find(|&&(function, _, _)| function == name) {
+            None => return Err(format!("Unsupported function {}()", name)),
+            Some(&(_, min, max)) if args.len() < min || args.len() > max =>
+                return Err(format!("Wrong number of arguments to {}()", name)),
+            Some(_) => {}
+        }
+        // The string value of the first argument, or of the context node.
+        let string_arg = || -> Result<String, String> {
+            match args.first() {
+                Some(arg) => self.eval_string(arg, ctx),
+                None => Ok(self.string_value(&ctx.node)),
+            }
+        };
+        Ok(match name {
+            "last" => Value::Num(ctx.size as f64),
+            "position" => Value::Num(ctx.position as f64),
+            "count" => {
+                match self.eval(&args[0], ctx)? {
+                    Value::Nodes(nodes) => Value::Num(nodes.len() as f64),
+                    _ => return Err("count() needs a node set".to_owned()),
+                }
+            }
+            "string" => Value::Str(string_arg()?),
+            "concat" => {
+                let mut out = String::new();
+                for arg in args {
+                    out.push_str(&self.eval_string(arg, ctx)?);
+                }
+                Value::Str(out)
+            }
+            "contains" | "starts-with" | "substring-before"
+                | "substring-after" =>
+            {
+                let a = self.eval_string(&args[0], ctx)?;
+                let b = self.eval_string(&args[1], ctx)?;
+                match name {
+                    "contains" => Value::Bool(a.contains(&b)),
+                    "starts-with" => Value::Bool(a.starts_with(&b)),
+                    "substring-before" =>
+                        Value::Str(a.find(&b).map(|i| a[..i].to_owned()).unwrap_or_default()),
+                    _ => Value::Str(a.find(&b).map(|i| a[i + b.len()..].to_owned())
+                                     .unwrap_or_default()),
+                }
+            }
+            "normalize-space" => {
+                Value::Str(string_arg()?.split_whitespace().collect::<Vec<_>>().join(" "))
+            }
+            "translate" => {
+                let s = self.eval_string(&args[0], ctx)?;
+                let from: Vec<char> = self.eval_string(&args[1], ctx)?.chars().collect();
+                let to: Vec<char> = self.eval_string(&args[2], ctx)?.chars().collect();
+                Value::Str(s.chars()
+                    .filter_map(|c| match from.iter().position(|&f| f == c) {
+                        Some(i) => to.get(i).cloned(),
+                        None => Some(c),
+                    })
+                    .collect())
+            }
+            "string-length" => {
+                Value::Num(string_arg()?.chars().count() as f64)
+            }
+            "substring" => {
+                let s: Vec<char> = self.eval_string(&args[0], ctx)?.chars().collect();
+                let start = self.number(&self.eval(&args[1], ctx)?).round();
+                let end = match args.get(2) {
+                    Some(len) => start + self.number(&self.eval(len, ctx)?).round(),
+                    None => f64::INFINITY,
+                };
+                Value::Str(s.iter().enumerate()
+                    .filter(|&(i, _)| {
+                        let pos = (i + 1) as f64;
+                        pos >= start && pos < end
+                    })
+                    .map(|(_, c)| *c)
+                    .collect())
+            }
+            "not" => Value::Bool(!self.eval_bool(&args[0], ctx)?),
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            "boolean" => Value::Bool(self.eval_bool(&args[0], ctx)?),
+            "number" => {
+                Value::Num(match args.first() {
+                    Some(arg) => self.number(&self.eval(arg, ctx)?),
+                    None => self.number(&Value::Str(self.string_value(&ctx.node))),
+                })
+            }
+            "name" | "local-name" => {
+                let node = match args.first() {
+                    Some(arg) => match self.eval(arg, ctx)? {
+                        Value::Nodes(nodes) => nodes.first().cloned(),
+                        _ => return Err(format!("{}() needs a node set", name)),
+                    },
+                    None => Some(ctx.node),
+                };
+                Value::Str(node.map(|n| self.name(&n)).unwrap_or_default())
+            }
+            _ => unreachable!("{}() is in FUNCTIONS but not implemented", name),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::select;
+    use scraper::Html;
+
+    const DOC: &str = r#"<html><head><title>T</title></head><body>
+        <div id="a" class="x"><p>One</p><p>Two <b>bold</b></p></div>
+        <div id="b"><a href="/next">Next  page</a><span data-test="it's">Quote</span></div>
+        </body></html>"#;
+
+    fn ids(expr: &str) -> Vec<String> {
+        let html = Html::parse_document(DOC);
+        let root = html.tree.root().id();
+        select(&html, root, expr).unwrap().into_iter()
+            .map(|id| {
+                let node = html.tree.get(id).unwrap();
+                let el = node.value().as_element().unwrap();
+                el.attr("id").map(str::to_owned).unwrap_or_else(|| el.name().to_owned())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn paths_and_predicates() {
+        assert_eq!(ids("//div"), vec!["a", "b"]);
+        assert_eq!(ids("/html/body/div[2]"), vec!["b"]);
+        assert_eq!(ids("//div[@class='x']/p"), vec!["p", "p"]);
+        assert_eq!(ids("//p[last()]/b"), vec!["b"]);
+        assert_eq!(ids("(//p)[1]/.."), vec!["a"]);
+        assert_eq!(ids("//b/ancestor::div"), vec!["a"]);
+        assert_eq!(ids("//div[@id='a']/following-sibling::*"), vec!["b"]);
+        assert_eq!(ids("//*[@id='a' or @id='b']"), vec!["a", "b"]);
+        assert_eq!(ids("//p | //a"), vec!["p", "p", "a"]);
+    }
+
+    #[test]
+    fn text_functions() {
+        assert_eq!(ids("//p[text()='One']"), vec!["p"]);
+        assert_eq!(ids("//p[contains(., 'bold')]"), vec!["p"]);
+        assert_eq!(ids("//a[normalize-space()='Next page']"), vec!["a"]);
+        assert_eq!(ids("//a[starts-with(@href, '/ne')]"), vec!["a"]);
+        assert_eq!(ids(r#"//span[@data-test=concat("it", "'s")]"#), vec!["span"]);
+        assert_eq!(ids("//div[count(p) = 2]"), vec!["a"]);
+        assert_eq!(ids("//div[not(@class)]"), vec!["b"]);
+    }
+
+    #[test]
+    fn errors() {
+        let html = Html::parse_document(DOC);
+        let root = html.tree.root().id();
+        assert!(select(&html, root, "//p/text()").is_err());
+        assert!(select(&html, root, "//p[").is_err());
+        assert!(select(&html, root, "count(//p)").is_err());
+        assert!(select(&html, root, "//p[frobnicate()]").is_err());
+        assert!(select(&html, root, "//p[ends-with(., 'e')]").is_err());
+        assert!(select(&html, root, "//p[contains(.)]").is_err());
+    }
+}
@@ -14,6 +14,7 @@ pub struct GeckoDriverBuilder {
     port: Option<u16>,
     ff_binary: String,
     kill_on_drop: bool,
+    http_config: HttpConfig,
 }
 
 impl GeckoDriverBuilder {
@@ -23,6 +24,7 @@ impl GeckoDriverBuilder {
             port: None,
             ff_binary: "firefox".to_owned(),
             kill_on_drop: true,
+            http_config: HttpConfig::default(),
         }
     }
     pub fn driver_path<S: Into<OsString>>(mut self, path: S) -> Self {
@@ -41,6 +43,11 @@ impl GeckoDriverBuilder {
         self.kill_on_drop = kill;
         self
     }
+    /// Connection settings, such as timeouts, for sessions on this driver.
+    pub fn http_config(mut self, config: HttpConfig) -> Self {
+        self.http_config = config;
+        self
+    }
     pub fn spawn(self) -> Result<GeckoDriver, Error> {
         let port = util::check_tcp_port(self.port)?;
 
@@ -60,6 +67,7 @@ impl GeckoDriverBuilder {
             child: child,
             url: format!("http://localhost:{}", port),
             kill_on_drop: self.kill_on_drop,
+            http_config: self.http_config,
         })
     }
 }
@@ -70,6 +78,7 @@ pub struct GeckoDriver {
     child: Child,
     url: String,
     kill_on_drop: bool,
+    http_config: HttpConfig,
 }
 
 impl GeckoDriver {
@@ -93,6 +102,15 @@ impl Driver for GeckoDriver {
     fn url(&self) -> &str {
         &self.url
     }
+
+    fn transport(&self) -> Result<Box<dyn Transport>, Error> {
+        Ok(Box::new(HttpTransport::with_config(&self.url, &self.http_config)?))
+    }
+
+    #[cfg(feature = "async")]
+    fn async_transport(&self) -> Result<Box<dyn AsyncTransport>, Error> {
+        Ok(Box::new(AsyncHttpTransport::with_config(&self.url, &self.http_config)?))
+    }
 }
 
 
@@ -0,0 +1,362 @@
+//! A wrapper for `<form>` elements, to fill in many fields at once.
+//!
+//! `Form::fill` takes a map or struct serializing to one, such as a
+//! `HashMap<&str, &str>` or a `#[derive(Serialize)]` struct, and sets the
+//! field named by each key. The fields are the form's controls, as in
+//! `form.elements`, so they include controls outside the `<form>` that are
+//! associated with it by a `form` attribute. A field is found by its
+//! `name` attribute, or by its `id` if no field has that name, and set
+//! according to its type:
+//!
+//! * Text inputs and textareas are cleared and the value typed in.
+//!   Numbers and booleans are typed as text.
+//! * A checkbox is checked or unchecked to match a boolean. For a group of
+//!   checkboxes sharing a name, a string or list of strings gives the
+//!   values to check; the others are unchecked.
+//! * The radio button with the given value is clicked.
+//! * The option of a select with the given value is selected. For a
+//!   multi-select, a value or list of values selects exactly those
+//!   options.
+//! * A file input is given the path, or list of paths, of local files to
+//!   upload, with `Element::upload_files`.
+//!
+//! A `null` value, such as `None`, leaves its field unchanged. The fields
+//! are set in the order of the serialized map, which is not necessarily
+//! the order of a struct's fields, so a field that depends on another,
+//! such as a select whose options are loaded when another changes, is
+//! best set with a later `fill` or `set` call.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use webdriver_client::{By, DriverSession};
+//! # use webdriver_client::form::Form;
+//! # use std::collections::{BTreeMap, HashMap};
+//! # fn example(sess: &DriverSession) -> Result<(), webdriver_client::Error> {
+//! let form = Form::new(sess.find_element(&By::id("signup"))?)?;
+//! let mut values = HashMap::new();
+//! values.insert("email", "alice@example.com");
+//! values.insert("plan", "pro");
+//! form.fill(&values)?;
+//! form.set("newsletter", &true)?;
+//! let state: BTreeMap<String, webdriver_client::JsonValue> = form.values()?;
+//! form.submit()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{Element, Error, JsonValue};
+use crate::messages::{ExecuteCmd, WebDriverError};
+use crate::select::Select;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Defines `fields`, the controls of the form in `arguments[0]` that can
+/// be filled in. They are found through `form.elements`, so controls
+/// outside the `<form>` that name it in a `form` attribute are included.
+pub(crate) const FIELDS_SCRIPT: &str = r#"var form = arguments[0];
+var fields = Array.prototype.filter.call(form.elements, function (e) {
+  return e.tagName !== 'BUTTON' && e.tagName !== 'FIELDSET' && e.tagName !== 'OUTPUT'
+    && e.tagName !== 'OBJECT' && ['submit', 'reset', 'button', 'image'].indexOf(e.type) < 0;
+});"#;
+
+/// Returns the fields named `arguments[1]`, or else those with that id.
+const FIND_SCRIPT: &str = r#"var name = arguments[1];
+var named = fields.filter(function (e) { return e.name === name; });
+return named.length > 0 ? named : fields.filter(function (e) { return e.id === name; });"#;
+
+/// Reads the named fields, in the shapes `Form::fill` takes.
+const VALUES_SCRIPT: &str = r#"var values = {}, counts = {};
+fields = fields.filter(function (e) { return e.name; });
+fields.forEach(function (e) { counts[e.name] = (counts[e.name] || 0) + 1; });
+fields.forEach(function (e) {
+  var name = e.name;
+  if (e.type === 'checkbox' && counts[name] === 1) { values[name] = e.checked; }
+  else if (e.type === 'checkbox') {
+    values[name] = values[name] || [];
+    if (e.checked) { values[name].push(e.value); }
+  }
+  else if (e.type === 'radio') {
+    if (!(name in values)) { values[name] = null; }
+    if (e.checked) { values[name] = e.value; }
+  }
+  else if (e.type === 'select-multiple') {
+    values[name] = Array.prototype.filter.call(e.options, function (o) { return o.selected; })
+      .map(function (o) { return o.value; });
+  }
+  else if (e.type === 'file') {
+    values[name] = Array.prototype.map.call(e.files, function (f) { return f.name; });
+  }
+  else { values[name] = e.value; }
+});
+return values;"#;
+
+/// A `<form>` element. See the module documentation.
+#[derive(Clone, Debug)]
+pub struct Form {
+    element: Element,
+}
+
+impl Form {
+    /// Wrap `element`, which must be a `<form>`.
+    pub fn new(element: Element) -> Result<Self, Error> {
+        let name = element.name()?;
+        if !name.eq_ignore_ascii_case("form") {
+            return Err(error("invalid argument",
+                             format!("Element should have been a <form> but was a <{}>", name)));
+        }
+        Ok(Form { element })
+    }
+
+    /// The `<form>` element.
+    pub fn element(&self) -> &Element {
+        &self.element
+    }
+
+    /// Set the field named by each key of `values`, which must serialize
+    /// to a map or struct.
+    pub fn fill<T: Serialize>(&self, values: &T) -> Result<(), Error> {
+        match serde_json::to_value(values)? {
+            JsonValue::Object(values) => {
+                for (name, value) in &values {
+                    self.set_json(name, value)?;
+                }
+                Ok(())
+            }
+            other => Err(error("invalid argument",
+                               format!("Form values must be a map or struct, not {}", other))),
+        }
+    }
+
+    /// Set the field found by `name` to `value`.
+    pub fn set<V: Serialize>(&self, name: &str, value: &V) -> Result<(), Error> {
+        self.set_json(name, &serde_json::to_value(value)?)
+    }
+
+    /// The fields of the form, by name, in the shapes `fill` takes, such
+    /// as a `BTreeMap<String, JsonValue>` or a `Deserialize` struct. A file
+    /// input gives the names of its files, and an unchecked group of radio
+    /// buttons gives `null`.
+    pub fn values<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        self.element.session().execute_typed(&format!("{}\n{}", FIELDS_SCRIPT, VALUES_SCRIPT),
+                                             (&self.element,))
+    }
+
+    /// Submit the form, as clicking its submit button would, running its
+    /// validation and `submit` event handlers.
+    pub fn submit(&self) -> Result<(), Error> {
+        self.element.session().execute_typed(
+            "var form = arguments[0]; \
+             if (form.requestSubmit) { form.requestSubmit(); } else { form.submit(); }",
+            (&self.element,))
+    }
+
+    /// The fields named `name`, or else the field with id `name`.
+    fn fields(&self, name: &str) -> Result<Vec<Element>, Error> {
+        let found = self.element.session().execute_value(ExecuteCmd {
+            script: format!("{}\n{}", FIELDS_SCRIPT, FIND_SCRIPT),
+            args: vec![self.element.reference()?, name.into()],
+        })?;
+        let fields = found.into_elements().ok_or_else(|| {
+            error("javascript error", format!("Looking up form field {:?} failed", name))
+        })?;
+        if fields.is_empty() {
+            return Err(error("no such element",
+                             format!("No form field with the name or id {:?}", name)));
+        }
+        Ok(fields)
+    }
+
+    fn set_json(&self, name: &str, value: &JsonValue) -> Result<(), Error> {
+        if value.is_null() {
+            return Ok(());
+        }
+        let fields = self.fields(name)?;
+        let first = &fields[0];
+        let kind = match first.name()?.to_ascii_lowercase().as_str() {
+            "input" => first.property("type")?.to_ascii_lowercase(),
+            "textarea" => "text".to_owned(),
+            "select" => "select".to_owned(),
+            tag => return Err(error("invalid argument",
+                                    format!("Field {:?} is a <{}>, not a form field", name, tag))),
+        };
+        let invalid = || error("invalid argument",
+                               format!("Can't set the {} field {:?} to {}", kind, name, value));
+        match kind.as_str() {
+            "checkbox" => {
+                let checked = |field: &Element| -> Result<bool, Error> {
+                    match *value {
+                        JsonValue::Bool(checked) => Ok(checked),
+                        _ => Ok(strings(value).ok_or_else(invalid)?
+                                .contains(&field.property("value")?)),
+                    }
+                };
+                for field in &fields {
+                    if field.is_selected()? != checked(field)? {
+                        field.click()?;
+                    }
+                }
+            }
+            "radio" => {
+                let wanted = text(value).ok_or_else(invalid)?;
+                for field in &fields {
+                    if field.property("value")? == wanted {
+                        if !field.is_selected()? {
+                            field.click()?;
+                        }
+                        return Ok(());
+                    }
+                }
+                return Err(error("no such element",
+                                 format!("No radio button {:?} with value {:?}", name, wanted)));
+            }
+            "select" => {
+                let select = Select::new(first.clone())?;
+                if select.is_multiple()? {
+                    let wanted = strings(value).ok_or_else(invalid)?;
+                    select.deselect_all()?;
+                    for value in &wanted {
+                        select.select_by_value(value)?;
+                    }
+                } else {
+                    select.select_by_value(&text(value).ok_or_else(invalid)?)?;
+                }
+            }
+            "file" => first.upload_files(&strings(value).ok_or_else(invalid)?)?,
+            _ => {
+                let text = text(value).ok_or_else(invalid)?;
+                first.clear()?;
+                first.send_keys(&text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A string, number or boolean as text.
+fn text(value: &JsonValue) -> Option<String> {
+    match *value {
+        JsonValue::String(ref s) => Some(s.clone()),
+        JsonValue::Number(ref n) => Some(n.to_string()),
+        JsonValue::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// A value, or list of values, as text.
+fn strings(value: &JsonValue) -> Option<Vec<String>> {
+    match *value {
+        JsonValue::Array(ref values) => values.iter().map(text).collect(),
+        ref value => text(value).map(|text| vec![text]),
+    }
+}
+
+fn error(code: &str, message: String) -> Error {
+    Error::WebDriverError(WebDriverError {
+        error: code.to_owned(),
+        message,
+        stacktrace: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strings, text, Form};
+    use crate::{Element, JsonValue};
+    use crate::testing::{element, FakeServer};
+    use crate::transport::Method;
+
+    #[test]
+    fn fill() {
+        #[derive(Serialize)]
+        struct Values {
+            name: &'static str,
+            agree: bool,
+            unchanged: Option<u32>,
+        }
+
+        // A form "f" with a text input named "name" and an unchecked
+        // checkbox with the id "agree".
+        let server = FakeServer::new();
+        server.on(Method::Post, "/execute/sync", |call| {
+            assert_eq!(call.body["args"][0]["element-6066-11e4-a52e-4f735466cecf"], "f");
+            match call.body["args"][1].as_str().unwrap() {
+                "name" => json!([element("n")]),
+                "agree" => json!([element("c")]),
+                _ => json!([]),
+            }
+        });
+        server.respond(Method::Get, "/element/{}/name", json!("input"));
+        server.respond(Method::Get, "/element/f/name", json!("form"));
+        server.respond(Method::Get, "/element/n/property/type", json!("text"));
+        server.respond(Method::Get, "/element/c/property/type", json!("checkbox"));
+        server.respond(Method::Get, "/element/c/selected", json!(false));
+        for command in &["click", "clear", "value"] {
+            server.respond(Method::Post, &format!("/element/{{}}/{}", command), JsonValue::Null);
+        }
+        let sess = server.session();
+
+        let form = Form::new(Element::new(&sess, "f".to_owned())).unwrap();
+        form.fill(&Values { name: "Alice", agree: true, unchanged: None }).unwrap();
+        let sent: Vec<_> = server.calls().into_iter()
+            .filter(|call| call.method == Method::Post && call.path != "/execute/sync")
+            .map(|call| match call.body["text"].as_str() {
+                Some(text) => format!("{} {}", call.path, text),
+                None => call.path,
+            })
+            .collect();
+        assert_eq!(sent, ["/element/c/click", "/element/n/clear", "/element/n/value Alice"]);
+
+        assert!(form.set("missing", &"x").is_err());
+        assert!(form.set("name", &["a", "b"]).is_err());
+        assert!(form.fill(&"not a map").is_err());
+        assert!(Form::new(Element::new(&sess, "n".to_owned())).is_err());
+    }
+
+    #[test]
+    fn one_value_for_a_multi_select() {
+        // A form "f" with a multi-select "langs" of options "en" (selected)
+        // and "fr".
+        let server = FakeServer::new();
+        server.respond(Method::Get, "/element/f/name", json!("form"));
+        server.respond(Method::Get, "/element/langs/name", json!("select"));
+        server.on(Method::Post, "/execute/sync", |call| {
+            if call.body["script"] == "return arguments[0].multiple;" {
+                json!(true)
+            } else {
+                assert_eq!(call.body["args"][1], "langs");
+                json!([element("langs")])
+            }
+        });
+        server.on(Method::Post, "/element/langs/elements", |call| {
+            match call.body["value"].as_str().unwrap() {
+                "option:checked" => json!([element("en")]),
+                r#"option[value="fr"]"# => json!([element("fr")]),
+                other => panic!("Unexpected selector {}", other),
+            }
+        });
+        server.respond(Method::Get, "/element/en/selected", json!(true));
+        server.respond(Method::Get, "/element/fr/selected", json!(false));
+        server.respond(Method::Post, "/element/{}/click", JsonValue::Null);
+        let sess = server.session();
+
+        let form = Form::new(Element::new(&sess, "f".to_owned())).unwrap();
+        form.set("langs", &"fr").unwrap();
+        let clicked: Vec<_> = server.calls().into_iter()
+            .filter(|call| call.path.ends_with("/click"))
+            .map(|call| call.path)
+            .collect();
+        assert_eq!(clicked, ["/element/en/click", "/element/fr/click"]);
+    }
+
+    #[test]
+    fn values_as_text() {
+        assert_eq!(text(&json!(2.5)), Some("2.5".to_owned()));
+        assert_eq!(text(&json!(false)), Some("false".to_owned()));
+        assert_eq!(text(&json!({})), None);
+        assert_eq!(strings(&json!("a")), Some(vec!["a".to_owned()]));
+        assert_eq!(strings(&json!(["a", 1])), Some(vec!["a".to_owned(), "1".to_owned()]));
+        assert_eq!(strings(&json!([["a"]])), None);
+    }
+}
@@ -0,0 +1,274 @@
+//! `By`, a locator describing how to find elements.
+//!
+//! A `By` pairs a selector with a `LocationStrategy`. It can be narrowed
+//! to the elements inside another locator's match with `within`, and to a
+//! single match with `nth`. Locators are plain values, so they can be
+//! stored in page objects and reused.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use webdriver_client::{By, DriverSession};
+//! # fn example(sess: &DriverSession) -> Result<(), webdriver_client::Error> {
+//! let second_item = By::css("li").within(By::id("menu")).nth(1);
+//! println!("{}", sess.find_element(&second_item)?.text()?);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{Error, JsonValue, LocationStrategy};
+use crate::messages::{FindElementCmd, WebDriverError};
+
+use std::fmt;
+
+/// JavaScript defining `locate(context, steps)`, which returns the
+/// elements within `context` matching the steps of a `By` as encoded by
+/// `By::steps_json`. Scripts that find elements themselves, rather than
+/// with a WebDriver command per locator, start with this.
+pub(crate) const LOCATE_SCRIPT: &str = r#"function find(context, step) {
+  var found = [];
+  switch (step.using) {
+  case 'css selector':
+    found = Array.prototype.slice.call(context.querySelectorAll(step.value));
+    break;
+  case 'tag name':
+    found = Array.prototype.slice.call(context.getElementsByTagName(step.value));
+    break;
+  case 'xpath':
+    var result = (context.ownerDocument || context).evaluate(
+      step.value, context, null, XPathResult.ORDERED_NODE_SNAPSHOT_TYPE, null);
+    for (var i = 0; i < result.snapshotLength; i++) {
+      if (result.snapshotItem(i).nodeType === 1) { found.push(result.snapshotItem(i)); }
+    }
+    break;
+  case 'link text':
+  case 'partial link text':
+    found = Array.prototype.filter.call(context.querySelectorAll('a'), function (a) {
+      var text = a.innerText.trim();
+      return step.using === 'link text' ? text === step.value : text.indexOf(step.value) !== -1;
+    });
+    break;
+  default:
+    throw new Error('Unsupported locator strategy: ' + step.using);
+  }
+  if (step.index !== null) { found = step.index < found.length ? [found[step.index]] : []; }
+  return found;
+}
+function locate(context, steps) {
+  var matches = [context];
+  steps.forEach(function (step, i) {
+    var next = [];
+    matches.forEach(function (m) { next = next.concat(find(m, step)); });
+    matches = i < steps.length - 1 ? next.slice(0, 1) : next;
+  });
+  return matches;
+}"#;
+
+/// How to find an element. See the module documentation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct By {
+    strategy: LocationStrategy,
+    selector: String,
+    parent: Option<Box<By>>,
+    index: Option<usize>,
+}
+
+impl By {
+    pub fn new<S: Into<String>>(strategy: LocationStrategy, selector: S) -> Self {
+        By { strategy, selector: selector.into(), parent: None, index: None }
+    }
+
+    /// Elements matching a CSS selector.
+    pub fn css<S: Into<String>>(selector: S) -> Self {
+        By::new(LocationStrategy::Css, selector)
+    }
+
+    /// Elements matching an XPath expression.
+    pub fn xpath<S: Into<String>>(selector: S) -> Self {
+        By::new(LocationStrategy::XPath, selector)
+    }
+
+    /// Links whose text is `text`.
+    pub fn link_text<S: Into<String>>(text: S) -> Self {
+        By::new(LocationStrategy::LinkText, text)
+    }
+
+    /// Links whose text contains `text`.
+    pub fn partial_link_text<S: Into<String>>(text: S) -> Self {
+        By::new(LocationStrategy::PartialLinkText, text)
+    }
+
+    pub fn tag_name<S: Into<String>>(name: S) -> Self {
+        By::new(LocationStrategy::TagName, name)
+    }
+
+    pub fn id<S: Into<String>>(id: S) -> Self {
+        By::new(LocationStrategy::Id, id)
+    }
+
+    pub fn name<S: Into<String>>(name: S) -> Self {
+        By::new(LocationStrategy::Name, name)
+    }
+
+    pub fn class_name<S: Into<String>>(class: S) -> Self {
+        By::new(LocationStrategy::ClassName, class)
+    }
+
+    pub fn data_test_id<S: Into<String>>(id: S) -> Self {
+        By::new(LocationStrategy::DataTestId, id)
+    }
+
+    pub fn exact_text<S: Into<String>>(text: S) -> Self {
+        By::new(LocationStrategy::ExactText, text)
+    }
+
+    pub fn contains_text<S: Into<String>>(text: S) -> Self {
+        By::new(LocationStrategy::ContainsText, text)
+    }
+
+    /// Only match elements inside the element found by `parent`.
+    pub fn within(mut self, parent: By) -> Self {
+        self.parent = Some(Box::new(match self.parent.take() {
+            // An existing parent is itself searched for within the new one.
+            Some(existing) => existing.within(parent),
+            None => parent,
+        }));
+        self
+    }
+
+    /// Only match the `index`th element that would otherwise match,
+    /// counting from 0.
+    pub fn nth(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    pub fn strategy(&self) -> LocationStrategy {
+        self.strategy
+    }
+
+    pub fn selector(&self) -> &str {
+        &self.selector
+    }
+
+    /// The command to find this locator's matches, ignoring its parent
+    /// and index.
+    pub(crate) fn cmd(&self) -> FindElementCmd<'_> {
+        FindElementCmd { using: self.strategy, value: &self.selector }
+    }
+
+    pub(crate) fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// The locators to resolve in turn: the outermost parent first and
+    /// this locator last.
+    pub(crate) fn steps(&self) -> Vec<&By> {
+        let mut steps = vec![self];
+        let mut by = self;
+        while let Some(ref parent) = by.parent {
+            steps.push(parent);
+            by = parent;
+        }
+        steps.reverse();
+        steps
+    }
+
+    /// The steps as JSON for `LOCATE_SCRIPT`: each has the `using` and
+    /// `value` of its find command and the `index` from `nth`, or null.
+    pub(crate) fn steps_json(&self) -> JsonValue {
+        self.steps().iter().map(|step| {
+            let mut cmd = serde_json::to_value(step.cmd()).unwrap();
+            cmd["index"] = json!(step.index());
+            cmd
+        }).collect()
+    }
+
+    /// The `index`th of `matches`, or a `no such element` error.
+    pub(crate) fn pick<T>(&self, mut matches: Vec<T>, index: usize) -> Result<T, Error> {
+        if index < matches.len() {
+            Ok(matches.swap_remove(index))
+        } else {
+            Err(Error::WebDriverError(WebDriverError {
+                error: "no such element".to_owned(),
+                message: format!("Only {} elements match", matches.len()),
+                stacktrace: None,
+            }))
+        }
+    }
+
+    /// Add this locator to the message of a `no such element` error.
+    pub(crate) fn annotate(&self, err: Error) -> Error {
+        match err {
+            Error::WebDriverError(mut err) if err.error == "no such element" => {
+                err.message = format!("No element found for {}: {}", self, err.message);
+                Error::WebDriverError(err)
+            }
+            other => other,
+        }
+    }
+}
+
+fn strategy_name(strategy: LocationStrategy) -> &'static str {
+    match strategy {
+        LocationStrategy::Css => "css",
+        LocationStrategy::LinkText => "link text",
+        LocationStrategy::PartialLinkText => "partial link text",
+        LocationStrategy::XPath => "xpath",
+        LocationStrategy::TagName => "tag name",
+        LocationStrategy::Id => "id",
+        LocationStrategy::Name => "name",
+        LocationStrategy::ClassName => "class name",
+        LocationStrategy::DataTestId => "data-testid",
+        LocationStrategy::ExactText => "exact text",
+        LocationStrategy::ContainsText => "text containing",
+    }
+}
+
+impl fmt::Display for By {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:?}", strategy_name(self.strategy), self.selector)?;
+        if let Some(index) = self.index {
+            write!(f, "[{}]", index)?;
+        }
+        if let Some(ref parent) = self.parent {
+            write!(f, " within {}", parent)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::By;
+    use super::super::Error;
+
+    #[test]
+    fn display() {
+        assert_eq!(By::css("li.item").to_string(), r#"css "li.item""#);
+        let by = By::xpath("//a").nth(2).within(By::id("menu")).within(By::tag_name("nav"));
+        assert_eq!(by.to_string(), r#"xpath "//a"[2] within id "menu" within tag name "nav""#);
+        let steps: Vec<_> = by.steps().iter().map(|b| b.selector().to_owned()).collect();
+        assert_eq!(steps, vec!["nav", "menu", "//a"]);
+    }
+
+    #[test]
+    fn pick_reports_missing_index() {
+        let by = By::css("li").nth(3);
+        assert_eq!(by.pick(vec!["a", "b", "c", "d"], 3).unwrap(), "d");
+        let err = by.annotate(by.pick(vec!["a"], 3).unwrap_err());
+        assert!(err.is_webdriver_error("no such element"));
+        match err {
+            Error::WebDriverError(err) =>
+                assert_eq!(err.message, r#"No element found for css "li"[3]: Only 1 elements match"#),
+            other => panic!("Want WebDriverError, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "macros")]
+    #[test]
+    fn selector_macros() {
+        assert_eq!(crate::css!("ul > li.item:nth-child(2)"), By::css("ul > li.item:nth-child(2)"));
+        assert_eq!(crate::xpath!("//a[@href]"), By::xpath("//a[@href]"));
+    }
+}
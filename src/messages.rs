@@ -2,7 +2,7 @@
 
 #![allow(non_snake_case)]
 
-use ::util::merge_json_mut;
+use crate::util::{css_identifier, css_string, merge_json_mut, xpath_literal};
 use serde::{Serialize, Deserialize, Serializer, Deserializer};
 use serde::de::{Visitor, MapAccess};
 use serde::de::Error as DeError;
@@ -11,25 +11,84 @@ use serde_json::Value as JsonValue;
 use std::fmt;
 use std::collections::BTreeMap;
 
-#[derive(Debug)]
+/// How `find_element` and `find_elements` interpret their selector.
+///
+/// `Css`, `LinkText`, `PartialLinkText`, `XPath` and `TagName` are the
+/// W3C strategies. The others are conveniences that match their value
+/// exactly; they are sent as an escaped CSS selector or XPath expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LocationStrategy {
     Css,
     LinkText,
     PartialLinkText,
     XPath,
+    /// Elements with the given tag name, such as `"input"`.
+    TagName,
+    /// The element with the given `id` attribute.
+    Id,
+    /// Elements with the given `name` attribute.
+    Name,
+    /// Elements with the given class.
+    ClassName,
+    /// Elements whose `data-testid` attribute is the given value.
+    DataTestId,
+    /// Elements with a text node equal to the given text, once whitespace
+    /// in the page is normalized.
+    ExactText,
+    /// Elements with a text node containing the given text.
+    ContainsText,
+}
+
+impl LocationStrategy {
+    /// The selector sent to the browser for `value`, in the W3C strategy
+    /// this strategy serializes as.
+    pub fn selector(&self, value: &str) -> String {
+        match *self {
+            LocationStrategy::Css | LocationStrategy::LinkText
+                | LocationStrategy::PartialLinkText | LocationStrategy::XPath
+                | LocationStrategy::TagName => value.to_owned(),
+            LocationStrategy::Id => format!("#{}", css_identifier(value)),
+            LocationStrategy::Name => format!("[name={}]", css_string(value)),
+            LocationStrategy::ClassName => format!(".{}", css_identifier(value)),
+            LocationStrategy::DataTestId => format!("[data-testid={}]", css_string(value)),
+            LocationStrategy::ExactText =>
+                format!(".//*[text()[normalize-space(.) = {}]]", xpath_literal(value)),
+            LocationStrategy::ContainsText =>
+                format!(".//*[text()[contains(., {})]]", xpath_literal(value)),
+        }
+    }
 }
 
 impl Serialize for LocationStrategy {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
         match self {
-            &LocationStrategy::Css => s.serialize_str("css selector"),
+            &LocationStrategy::Css | &LocationStrategy::Id | &LocationStrategy::Name
+                | &LocationStrategy::ClassName | &LocationStrategy::DataTestId
+                => s.serialize_str("css selector"),
             &LocationStrategy::LinkText => s.serialize_str("link text"),
             &LocationStrategy::PartialLinkText => s.serialize_str("partial link text"),
-            &LocationStrategy::XPath => s.serialize_str("xpath"),
+            &LocationStrategy::XPath | &LocationStrategy::ExactText
+                | &LocationStrategy::ContainsText => s.serialize_str("xpath"),
+            &LocationStrategy::TagName => s.serialize_str("tag name"),
         }
     }
 }
 
+/// The HTTP status for a WebDriver error code, from the WebDriver
+/// specification.
+pub fn error_status(code: &str) -> u16 {
+    match code {
+        "element click intercepted" | "element not interactable" | "insecure certificate"
+            | "invalid argument" | "invalid cookie domain" | "invalid element state"
+            | "invalid selector" => 400,
+        "detached shadow root" | "invalid session id" | "no such alert" | "no such cookie"
+            | "no such element" | "no such frame" | "no such shadow root" | "no such window"
+            | "stale element reference" | "unknown command" => 404,
+        "unknown method" => 405,
+        _ => 500,
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct WebDriverError {
     pub error: String,
@@ -134,12 +193,29 @@ impl SwitchWindowCmd {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Empty {}
 
-#[derive(Serialize)]
 pub struct FindElementCmd<'a> {
     pub using: LocationStrategy,
     pub value: &'a str,
 }
 
+impl<'a> Serialize for FindElementCmd<'a> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut ss = s.serialize_struct("FindElementCmd", 2)?;
+        ss.serialize_field("using", &self.using)?;
+        ss.serialize_field("value", &self.using.selector(self.value))?;
+        ss.end()
+    }
+}
+
+/// The key identifying an element reference in JSON.
+pub const ELEMENT_KEY: &str = "element-6066-11e4-a52e-4f735466cecf";
+/// The key identifying a shadow root reference in JSON.
+pub const SHADOW_ROOT_KEY: &str = "shadow-6066-11e4-a52e-4f735466cecf";
+/// The key identifying a top level window reference in JSON.
+pub const WINDOW_KEY: &str = "window-fcc6-11e5-b4f8-e2f1e4a0c2b0";
+/// The key identifying a frame reference in JSON.
+pub const FRAME_KEY: &str = "frame-075b-4da1-b6ba-e579c2d3230a";
+
 #[derive(PartialEq, Debug)]
 pub struct ElementReference {
     pub reference: String,
@@ -245,7 +321,7 @@ pub struct SendAlertTextCmd {
 
 #[cfg(test)]
 mod tests {
-    use super::NewSessionCmd;
+    use super::{FindElementCmd, LocationStrategy, NewSessionCmd};
     #[test]
     fn capability_extend() {
         let mut session = NewSessionCmd::default();
@@ -274,4 +350,22 @@ mod tests {
                .always_match("cap", json!("new"));
         assert_eq!(session.capabilities.alwaysMatch.get("cap").unwrap(), &json!("new"));
     }
+    #[test]
+    fn convenience_strategies() {
+        let cmd = |using, value| serde_json::to_value(&FindElementCmd { using, value }).unwrap();
+        assert_eq!(cmd(LocationStrategy::TagName, "input"),
+                   json!({ "using": "tag name", "value": "input" }));
+        assert_eq!(cmd(LocationStrategy::Id, "1st:item"),
+                   json!({ "using": "css selector", "value": r"#\31 st\:item" }));
+        assert_eq!(cmd(LocationStrategy::ClassName, "btn-primary"),
+                   json!({ "using": "css selector", "value": ".btn-primary" }));
+        assert_eq!(cmd(LocationStrategy::Name, r#"say "hi""#),
+                   json!({ "using": "css selector", "value": r#"[name="say \"hi\""]"# }));
+        assert_eq!(cmd(LocationStrategy::DataTestId, "submit"),
+                   json!({ "using": "css selector", "value": r#"[data-testid="submit"]"# }));
+        assert_eq!(cmd(LocationStrategy::ExactText, "Page 2"),
+                   json!({ "using": "xpath", "value": ".//*[text()[normalize-space(.) = 'Page 2']]" }));
+        assert_eq!(cmd(LocationStrategy::ContainsText, "it's"),
+                   json!({ "using": "xpath", "value": r#".//*[text()[contains(., "it's")]]"# }));
+    }
 }
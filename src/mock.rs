@@ -0,0 +1,341 @@
+//! A programmable WebDriver server for tests, enabled by the `mock` cargo
+//! feature.
+//!
+//! `MockServer` listens on a local port and answers WebDriver requests
+//! with responses registered by the test. It implements `Driver`, so
+//! sessions are created against it the usual way and exercise the real
+//! HTTP client. Every request it receives is recorded.
+//!
+//! Out of the box it answers New Session and Delete Session; any other
+//! request gets an `unknown command` error.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate serde_json;
+//! # extern crate webdriver_client;
+//! # use webdriver_client::{By, Driver};
+//! # use webdriver_client::messages::NewSessionCmd;
+//! # use webdriver_client::mock::{MockResponse, MockServer};
+//! # use webdriver_client::transport::Method;
+//! # fn main() {
+//! let server = MockServer::start().unwrap();
+//! server.respond(Method::Get, "/session/{}/title", MockResponse::value(json!("Home")));
+//! server.respond(Method::Post, "/session/{}/element",
+//!                MockResponse::error("no such element", "Unable to locate #missing"));
+//!
+//! let sess = server.clone().session(&NewSessionCmd::default()).unwrap();
+//! assert_eq!(sess.get_title().unwrap(), "Home");
+//! assert!(sess.find_element(&By::css("#missing")).is_err());
+//!
+//! let calls = server.calls();
+//! assert_eq!(calls[1].path, format!("/session/{}/title", sess.session_id()));
+//! # }
+//! ```
+
+// Without the feature only the routing is compiled, for unit tests.
+#![cfg_attr(not(feature = "mock"), allow(dead_code))]
+
+use super::JsonValue;
+#[cfg(feature = "mock")]
+use super::{Driver, Error};
+pub use crate::messages::error_status;
+use crate::transport::Method;
+use crate::util;
+
+#[cfg(feature = "mock")]
+use hyper::server::{Listening, Request, Response};
+#[cfg(feature = "mock")]
+use hyper::status::StatusCode;
+#[cfg(feature = "mock")]
+use hyper::uri::RequestUri;
+#[cfg(feature = "mock")]
+use std::io::Read;
+use std::sync::Mutex;
+#[cfg(feature = "mock")]
+use std::sync::Arc;
+
+/// The session id returned by the default New Session handler.
+pub const SESSION_ID: &str = "mock-session";
+
+/// A request received by a `MockServer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub method: Method,
+    pub path: String,
+    /// The parsed JSON body, or `Null` if there was none.
+    pub body: JsonValue,
+}
+
+/// A response for a `MockServer` to send.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub body: JsonValue,
+}
+
+impl MockResponse {
+    /// A successful response containing `value`.
+    pub fn value(value: JsonValue) -> Self {
+        MockResponse {
+            status: 200,
+            body: json!({ "value": value }),
+        }
+    }
+
+    /// A W3C error response with the given error code, such as
+    /// `"no such element"`, and the HTTP status the specification assigns
+    /// to it.
+    pub fn error(code: &str, message: &str) -> Self {
+        MockResponse {
+            status: error_status(code),
+            body: json!({ "value": {
+                "error": code,
+                "message": message,
+                "stacktrace": "",
+            } }),
+        }
+    }
+}
+
+type Handler = Box<dyn Fn(&Call) -> MockResponse + Send + Sync>;
+
+struct Route {
+    method: Method,
+    template: String,
+    handler: Handler,
+}
+
+/// The routes of a mock server and the calls it has received. Also used
+/// by the in-memory server of the crate's unit tests.
+#[derive(Default)]
+pub(crate) struct Router {
+    routes: Mutex<Vec<Route>>,
+    calls: Mutex<Vec<Call>>,
+}
+
+impl Router {
+    /// See `MockServer::on`.
+    pub fn on<F>(&self, method: Method, path: &str, handler: F)
+        where F: Fn(&Call) -> MockResponse + Send + Sync + 'static
+    {
+        self.routes.lock().unwrap().push(Route {
+            method,
+            template: path.to_owned(),
+            handler: Box::new(handler),
+        });
+    }
+
+    /// The calls received so far, oldest first.
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    #[cfg(feature = "mock")]
+    fn clear_calls(&self) {
+        self.calls.lock().unwrap().clear();
+    }
+
+    /// Record `call`, and answer it with the matching route, if any.
+    pub fn answer(&self, call: Call) -> Option<MockResponse> {
+        debug!("mock received {:?}", call);
+        self.calls.lock().unwrap().push(call.clone());
+        let routes = self.routes.lock().unwrap();
+        // The most recently registered route wins.
+        routes.iter().rev()
+            .find(|r| r.method == call.method && util::path_matches(&r.template, &call.path))
+            .map(|route| (route.handler)(&call))
+    }
+}
+
+#[cfg(feature = "mock")]
+struct Server {
+    listening: Listening,
+    url: String,
+    router: Arc<Router>,
+}
+
+#[cfg(feature = "mock")]
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.listening.close();
+    }
+}
+
+/// A WebDriver server answering requests with programmed responses.
+///
+/// Clones refer to the same server, so one clone can be moved into a
+/// session while another registers responses and inspects calls.
+#[cfg(feature = "mock")]
+#[derive(Clone)]
+pub struct MockServer {
+    server: Arc<Server>,
+}
+
+#[cfg(feature = "mock")]
+impl MockServer {
+    /// Start a server on a free local port.
+    pub fn start() -> Result<Self, Error> {
+        let router = Arc::new(Router::default());
+        let mut server = hyper::Server::http("127.0.0.1:0")?;
+        // Each request gets its own connection, so idle connections from
+        // the client's pool cannot tie up the server's threads.
+        server.keep_alive(None);
+        let handler_router = router.clone();
+        let listening = server.handle(move |req: Request, res: Response| {
+            handle(&handler_router, req, res)
+        })?;
+        let url = format!("http://{}", listening.socket);
+
+        let mock = MockServer {
+            server: Arc::new(Server { listening, url, router }),
+        };
+        mock.respond(Method::Post, "/session", MockResponse::value(json!({
+            "sessionId": SESSION_ID,
+            "capabilities": { "browserName": "mock" },
+        })));
+        mock.respond(Method::Delete, "/session/{}", MockResponse::value(JsonValue::Null));
+        Ok(mock)
+    }
+
+    /// Answer requests to `path` with the response computed by `handler`.
+    ///
+    /// A `{}` segment in `path` matches any single segment, for example
+    /// `/session/{}/element/{}/click`. When several routes match a request
+    /// the most recently registered one is used.
+    pub fn on<F>(&self, method: Method, path: &str, handler: F)
+        where F: Fn(&Call) -> MockResponse + Send + Sync + 'static
+    {
+        self.server.router.on(method, path, handler);
+    }
+
+    /// Answer requests to `path` with `response`. See `on` for the format
+    /// of `path`.
+    pub fn respond(&self, method: Method, path: &str, response: MockResponse) {
+        self.on(method, path, move |_| response.clone());
+    }
+
+    /// The requests received so far, oldest first.
+    pub fn calls(&self) -> Vec<Call> {
+        self.server.router.calls()
+    }
+
+    /// Forget the requests received so far.
+    pub fn clear_calls(&self) {
+        self.server.router.clear_calls();
+    }
+}
+
+#[cfg(feature = "mock")]
+impl Driver for MockServer {
+    fn url(&self) -> &str {
+        &self.server.url
+    }
+}
+
+#[cfg(feature = "mock")]
+fn handle(router: &Router, mut req: Request, mut res: Response) {
+    let method = match req.method {
+        hyper::method::Method::Get => Some(Method::Get),
+        hyper::method::Method::Post => Some(Method::Post),
+        hyper::method::Method::Delete => Some(Method::Delete),
+        _ => None,
+    };
+    let path = match req.uri {
+        RequestUri::AbsolutePath(ref path) => Some(path.clone()),
+        _ => None,
+    };
+    let mut body = String::new();
+    let read = req.read_to_string(&mut body);
+
+    let response = match (method, path, read) {
+        (Some(method), Some(path), Ok(_)) => {
+            let body = if body.is_empty() {
+                JsonValue::Null
+            } else {
+                serde_json::from_str(&body).unwrap_or(JsonValue::String(body))
+            };
+            let description = format!("No mock for {} {}", method.as_str(), path);
+            router.answer(Call { method, path, body })
+                .unwrap_or_else(|| MockResponse::error("unknown command", &description))
+        }
+        (None, _, _) => MockResponse::error("unknown method", "Unsupported HTTP method"),
+        _ => MockResponse::error("invalid argument", "Unreadable request"),
+    };
+
+    *res.status_mut() = StatusCode::from_u16(response.status);
+    res.headers_mut().set(hyper::header::ContentType::json());
+    if let Err(err) = res.send(response.body.to_string().as_bytes()) {
+        warn!("mock failed to send response: {}", err);
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::{error_status, MockResponse, MockServer, SESSION_ID};
+    use super::super::{By, Driver, Error};
+    use crate::messages::NewSessionCmd;
+    use crate::transport::Method;
+
+    #[test]
+    fn default_session_lifecycle() {
+        let server = MockServer::start().unwrap();
+        let sess = server.clone().session(&NewSessionCmd::default()).unwrap();
+        assert_eq!(sess.session_id(), SESSION_ID);
+        assert_eq!(sess.browser_name(), Some("mock"));
+        drop(sess);
+
+        let calls: Vec<_> = server.calls().into_iter().map(|c| (c.method, c.path)).collect();
+        assert_eq!(calls, vec![(Method::Post, "/session".to_owned()),
+                               (Method::Delete, "/session/mock-session".to_owned())]);
+    }
+
+    #[test]
+    fn handlers_see_the_request() {
+        let server = MockServer::start().unwrap();
+        server.on(Method::Post, "/session/{}/url", |call| {
+            MockResponse::value(call.body["url"].clone())
+        });
+        let sess = server.clone().session(&NewSessionCmd::default()).unwrap();
+        sess.go("http://example.com/").unwrap();
+
+        let call = server.calls().pop().unwrap();
+        assert_eq!(call.path, "/session/mock-session/url");
+        assert_eq!(call.body, json!({ "url": "http://example.com/" }));
+    }
+
+    #[test]
+    fn error_responses() {
+        let server = MockServer::start().unwrap();
+        server.respond(Method::Post, "/session/{}/element",
+                       MockResponse::error("no such element", "Not here"));
+        let sess = server.clone().session(&NewSessionCmd::default()).unwrap();
+        match sess.find_element(&By::css("#nope")) {
+            Err(Error::WebDriverError(err)) => {
+                assert_eq!(err.error, "no such element");
+                assert_eq!(err.message, r##"No element found for css "#nope": Not here"##);
+            }
+            other => panic!("Want WebDriverError, got {:?}", other.map(|_| ())),
+        }
+        // Unregistered endpoints are unknown commands.
+        assert!(sess.get_title().unwrap_err().is_webdriver_error("unknown command"));
+    }
+
+    #[test]
+    fn later_routes_take_precedence() {
+        let server = MockServer::start().unwrap();
+        server.respond(Method::Get, "/session/{}/title", MockResponse::value(json!("First")));
+        let sess = server.clone().session(&NewSessionCmd::default()).unwrap();
+        assert_eq!(sess.get_title().unwrap(), "First");
+        server.respond(Method::Get, "/session/{}/title", MockResponse::value(json!("Second")));
+        assert_eq!(sess.get_title().unwrap(), "Second");
+    }
+
+    #[test]
+    fn error_statuses() {
+        assert_eq!(error_status("invalid argument"), 400);
+        assert_eq!(error_status("stale element reference"), 404);
+        assert_eq!(error_status("unknown method"), 405);
+        assert_eq!(error_status("javascript error"), 500);
+    }
+}
@@ -0,0 +1,306 @@
+//! Page objects: structs describing the elements of a page or component.
+//!
+//! With the `macros` feature, `#[derive(PageObject)]` implements
+//! `PageObject` for a struct with named fields:
+//!
+//! * A field marked `#[root]` of type `SearchContext` holds what the page
+//!   object searches within: the session for a page, or an element for a
+//!   component.
+//! * A field marked `#[find(css = "...")]` is a `Lazy<T>` that locates its
+//!   target each time `Lazy::get` is called, so it never holds a stale
+//!   element. `T` is `Element`, `Vec<Element>` for all matches, or another
+//!   page object, which is then a component searched for within this one.
+//!   Instead of `css` the attribute may name any `By` constructor, such as
+//!   `xpath`, `id`, `name` or `data_test_id`. CSS and XPath selectors are
+//!   checked at compile time, like the `css!` and `xpath!` macros.
+//! * Any other field is set to its `Default` value.
+//!
+//! `#[page(url = "...")]` on the struct adds a `URL` constant, an
+//! `open(&DriverSession)` constructor that navigates there, and an
+//! `is_current()` method. A relative URL is resolved against the current
+//! page, so it needs a page from the same site to be loaded first.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # #[cfg(feature = "macros")]
+//! # mod example {
+//! use webdriver_client::{DriverSession, Element, Error};
+//! use webdriver_client::page::{Lazy, PageObject, SearchContext};
+//!
+//! #[derive(PageObject)]
+//! struct SearchBox {
+//!     #[root] root: SearchContext,
+//!     #[find(css = "input[type=search]")] input: Lazy<Element>,
+//!     #[find(css = "button")] submit: Lazy<Element>,
+//! }
+//!
+//! #[derive(PageObject)]
+//! #[page(url = "/index.html")]
+//! struct Home {
+//!     #[root] root: SearchContext,
+//!     #[find(id = "search")] search: Lazy<SearchBox>,
+//!     #[find(css = "ul.results > li")] results: Lazy<Vec<Element>>,
+//! }
+//!
+//! fn search(sess: &DriverSession, query: &str) -> Result<usize, Error> {
+//!     let home = Home::open(sess)?;
+//!     let search = home.search.get()?;
+//!     search.input.get()?.send_keys(query)?;
+//!     search.submit.get()?.click()?;
+//!     Ok(home.results.get()?.len())
+//! }
+//! # }
+//! ```
+
+use super::{By, DriverSession, Element, Error, ShadowRoot};
+
+use std::fmt;
+use std::marker::PhantomData;
+use url::Url;
+
+#[cfg(feature = "macros")]
+pub use webdriver_client_macros::PageObject;
+
+/// Where a page object searches for its elements.
+#[derive(Clone)]
+pub enum SearchContext {
+    Session(DriverSession),
+    Element(Element),
+    ShadowRoot(ShadowRoot),
+}
+
+impl SearchContext {
+    pub fn session(&self) -> &DriverSession {
+        match *self {
+            SearchContext::Session(ref session) => session,
+            SearchContext::Element(ref element) => element.session(),
+            SearchContext::ShadowRoot(ref root) => root.session(),
+        }
+    }
+
+    pub fn find_element(&self, by: &By) -> Result<Element, Error> {
+        match *self {
+            SearchContext::Session(ref session) => session.find_element(by),
+            SearchContext::Element(ref element) => element.find_element(by),
+            SearchContext::ShadowRoot(ref root) => root.find_element(by),
+        }
+    }
+
+    pub fn find_elements(&self, by: &By) -> Result<Vec<Element>, Error> {
+        match *self {
+            SearchContext::Session(ref session) => session.find_elements(by),
+            SearchContext::Element(ref element) => element.find_elements(by),
+            SearchContext::ShadowRoot(ref root) => root.find_elements(by),
+        }
+    }
+}
+
+impl fmt::Debug for SearchContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SearchContext::Session(ref session) => write!(f, "Session({})", session.session_id()),
+            SearchContext::Element(ref element) => write!(f, "Element({})", element.raw_reference()),
+            SearchContext::ShadowRoot(ref root) => write!(f, "ShadowRoot({})", root.raw_reference()),
+        }
+    }
+}
+
+impl From<DriverSession> for SearchContext {
+    fn from(session: DriverSession) -> Self {
+        SearchContext::Session(session)
+    }
+}
+
+impl From<Element> for SearchContext {
+    fn from(element: Element) -> Self {
+        SearchContext::Element(element)
+    }
+}
+
+impl From<ShadowRoot> for SearchContext {
+    fn from(root: ShadowRoot) -> Self {
+        SearchContext::ShadowRoot(root)
+    }
+}
+
+/// A page or component whose elements are found within a `SearchContext`.
+/// Usually derived; see the module documentation.
+pub trait PageObject: Sized {
+    fn from_context(context: SearchContext) -> Self;
+}
+
+/// What a `Lazy` field can locate.
+pub trait Locate: Sized {
+    fn locate(context: &SearchContext, by: &By) -> Result<Self, Error>;
+}
+
+impl Locate for Element {
+    fn locate(context: &SearchContext, by: &By) -> Result<Self, Error> {
+        context.find_element(by)
+    }
+}
+
+impl Locate for Vec<Element> {
+    fn locate(context: &SearchContext, by: &By) -> Result<Self, Error> {
+        context.find_elements(by)
+    }
+}
+
+impl<T: PageObject> Locate for T {
+    fn locate(context: &SearchContext, by: &By) -> Result<Self, Error> {
+        Ok(T::from_context(SearchContext::Element(context.find_element(by)?)))
+    }
+}
+
+/// A `T` that is located when `get` is called, each time it is called.
+pub struct Lazy<T> {
+    context: SearchContext,
+    by: By,
+    target: PhantomData<fn() -> T>,
+}
+
+impl<T: Locate> Lazy<T> {
+    pub fn new(context: &SearchContext, by: By) -> Self {
+        Lazy { context: context.clone(), by, target: PhantomData }
+    }
+
+    /// Locate the target now.
+    pub fn get(&self) -> Result<T, Error> {
+        T::locate(&self.context, &self.by)
+    }
+}
+
+impl<T> Lazy<T> {
+    pub fn by(&self) -> &By {
+        &self.by
+    }
+}
+
+impl<T> Clone for Lazy<T> {
+    fn clone(&self) -> Self {
+        Lazy { context: self.context.clone(), by: self.by.clone(), target: PhantomData }
+    }
+}
+
+impl<T> fmt::Debug for Lazy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Lazy({})", self.by)
+    }
+}
+
+/// Navigate `session` to `url`, resolved against the current page. Used by
+/// the `open()` constructor of a page with `#[page(url = "...")]`.
+pub fn open(session: &DriverSession, url: &str) -> Result<(), Error> {
+    let url = resolve(session, url)?;
+    session.go(url.as_str())
+}
+
+/// Whether the current page of `session` is at `url`, resolved against
+/// the current page. The fragment is ignored, and so is the query string
+/// unless `url` has one. Used by `is_current()` of a page with
+/// `#[page(url = "...")]`.
+pub fn is_current(session: &DriverSession, url: &str) -> Result<bool, Error> {
+    let current = Url::parse(&session.get_current_url()?).map_err(|_| Error::InvalidUrl)?;
+    // A relative URL can't be resolved against a page such as about:blank,
+    // which can't be its page either.
+    Ok(current.join(url).map(|wanted| url_matches(&current, &wanted)).unwrap_or(false))
+}
+
+fn resolve(session: &DriverSession, url: &str) -> Result<Url, Error> {
+    match Url::parse(url) {
+        Ok(url) => Ok(url),
+        Err(_) => Url::parse(&session.get_current_url()?)
+            .and_then(|current| current.join(url))
+            .map_err(|_| Error::InvalidUrl),
+    }
+}
+
+fn url_matches(current: &Url, wanted: &Url) -> bool {
+    current.scheme() == wanted.scheme()
+        && current.host_str() == wanted.host_str()
+        && current.port_or_known_default() == wanted.port_or_known_default()
+        && current.path() == wanted.path()
+        && (wanted.query().is_none() || current.query() == wanted.query())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::url_matches;
+    use url::Url;
+
+    #[test]
+    fn matching_urls() {
+        let current = Url::parse("http://localhost:8080/login.html?next=%2F#form").unwrap();
+        let matches = |url: &str| url_matches(&current, &current.join(url).unwrap());
+        assert!(matches("/login.html"));
+        assert!(matches("login.html"));
+        assert!(matches("http://localhost:8080/login.html"));
+        assert!(matches("/login.html?next=%2F"));
+        assert!(!matches("/login.html?next=%2Fhome"));
+        assert!(!matches("/index.html"));
+        assert!(!matches("https://localhost:8080/login.html"));
+    }
+
+    #[cfg(feature = "macros")]
+    mod derive {
+        use crate::page::{Lazy, PageObject, SearchContext};
+        use crate::{Element, JsonValue};
+        use crate::testing::{element, FakeServer};
+        use crate::transport::Method;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(PageObject)]
+        struct SearchForm {
+            #[find(name = "q")] query: Lazy<Element>,
+        }
+
+        #[derive(PageObject)]
+        #[page(url = "/search")]
+        struct SearchPage {
+            #[root] root: SearchContext,
+            #[find(css = "form")] form: Lazy<SearchForm>,
+            #[find(css = "li.result")] results: Lazy<Vec<Element>>,
+            visits: u32,
+        }
+
+        #[test]
+        fn derived_page_object() {
+            // A site with a search box in a form and two results.
+            let server = FakeServer::new();
+            let url = Arc::new(Mutex::new("http://site/index.html".to_owned()));
+            let current = url.clone();
+            server.on(Method::Post, "/url", move |call| {
+                *current.lock().unwrap() = call.body["url"].as_str().unwrap().to_owned();
+                JsonValue::Null
+            });
+            server.on(Method::Get, "/url", move |_| json!(*url.lock().unwrap()));
+            server.on(Method::Post, "/element", |call| {
+                assert_eq!(call.body, json!({ "using": "css selector", "value": "form" }));
+                element("form")
+            });
+            server.on(Method::Post, "/element/form/element", |call| {
+                assert_eq!(call.body, json!({ "using": "css selector", "value": "[name=\"q\"]" }));
+                element("q")
+            });
+            server.on(Method::Post, "/elements", |call| {
+                assert_eq!(call.body, json!({ "using": "css selector", "value": "li.result" }));
+                json!([element("r1"), element("r2")])
+            });
+            let sess = server.session();
+
+            assert_eq!(SearchPage::URL, "/search");
+            let page = SearchPage::open(&sess).unwrap();
+            assert_eq!(sess.get_current_url().unwrap(), "http://site/search");
+            assert!(page.is_current().unwrap());
+            assert_eq!(page.visits, 0);
+            assert_eq!(page.form.get().unwrap().query.get().unwrap().raw_reference(), "q");
+            let results: Vec<_> = page.results.get().unwrap().iter()
+                .map(|e| e.raw_reference().to_owned()).collect();
+            assert_eq!(results, vec!["r1", "r2"]);
+
+            sess.go("http://site/search/more").unwrap();
+            assert!(!page.is_current().unwrap());
+        }
+    }
+}
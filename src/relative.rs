@@ -0,0 +1,228 @@
+//! Relative locators, which find elements by their position on the page
+//! relative to other elements.
+//!
+//! A `RelativeLocator` starts from an ordinary `By` locator and narrows its
+//! matches with `above`, `below`, `left_of`, `right_of` and `near`. The
+//! positions are compared in the browser by a script, using each element's
+//! bounding box, and the matches are ordered by their distance from the
+//! first anchor element.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use webdriver_client::{By, DriverSession};
+//! # use webdriver_client::relative::RelativeLocator;
+//! # fn example(sess: &DriverSession) -> Result<(), webdriver_client::Error> {
+//! let password = sess.find_element(&By::id("password"))?;
+//! let email = sess.find_element_relative(
+//!     &RelativeLocator::new(By::tag_name("input")).above(&password))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{By, DriverSession, Element, Error};
+use crate::messages::{ExecuteCmd, WebDriverError};
+
+/// The distance used by `RelativeLocator::near`, in CSS pixels.
+pub const DEFAULT_NEAR_DISTANCE: f64 = 50.0;
+
+pub(crate) const FILTER_SCRIPT: &str = r#"var candidates = arguments[0], anchors = arguments[1];
+function rect(e) { return e.getBoundingClientRect(); }
+function gap(a, b) {
+  var dx = Math.max(0, b.left - a.right, a.left - b.right);
+  var dy = Math.max(0, b.top - a.bottom, a.top - b.bottom);
+  return Math.sqrt(dx * dx + dy * dy);
+}
+var tests = {
+  above: function (c, a) { return c.bottom <= a.top; },
+  below: function (c, a) { return c.top >= a.bottom; },
+  left_of: function (c, a) { return c.right <= a.left; },
+  right_of: function (c, a) { return c.left >= a.right; },
+  near: function (c, a, distance) { return gap(c, a) <= distance; }
+};
+var found = candidates.filter(function (e) {
+  return anchors.every(function (anchor) {
+    return e !== anchor.element
+      && tests[anchor.direction](rect(e), rect(anchor.element), anchor.distance);
+  });
+});
+if (anchors.length > 0) {
+  var first = rect(anchors[0].element);
+  var centre = function (r) { return [r.left + r.width / 2, r.top + r.height / 2]; };
+  var origin = centre(first);
+  var distance = function (e) {
+    var c = centre(rect(e));
+    return Math.pow(c[0] - origin[0], 2) + Math.pow(c[1] - origin[1], 2);
+  };
+  found.sort(function (a, b) { return distance(a) - distance(b); });
+}
+return found;"#;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    Above,
+    Below,
+    LeftOf,
+    RightOf,
+    Near,
+}
+
+impl Direction {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            Direction::Above => "above",
+            Direction::Below => "below",
+            Direction::LeftOf => "left_of",
+            Direction::RightOf => "right_of",
+            Direction::Near => "near",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Anchor {
+    direction: Direction,
+    element: Element,
+    distance: f64,
+}
+
+/// Elements matching a locator that are positioned relative to other
+/// elements. See the module documentation.
+#[derive(Debug, Clone)]
+pub struct RelativeLocator {
+    by: By,
+    anchors: Vec<Anchor>,
+}
+
+impl RelativeLocator {
+    /// Elements matching `by`, before any positional filter is added.
+    pub fn new(by: By) -> Self {
+        RelativeLocator {
+            by,
+            anchors: vec![],
+        }
+    }
+
+    fn anchor(mut self, direction: Direction, element: &Element, distance: f64) -> Self {
+        self.anchors.push(Anchor { direction, element: element.clone(), distance });
+        self
+    }
+
+    /// Keep elements whose bottom edge is at or above the top of `element`.
+    pub fn above(self, element: &Element) -> Self {
+        self.anchor(Direction::Above, element, 0.0)
+    }
+
+    /// Keep elements whose top edge is at or below the bottom of `element`.
+    pub fn below(self, element: &Element) -> Self {
+        self.anchor(Direction::Below, element, 0.0)
+    }
+
+    /// Keep elements whose right edge is at or left of the left of
+    /// `element`.
+    pub fn left_of(self, element: &Element) -> Self {
+        self.anchor(Direction::LeftOf, element, 0.0)
+    }
+
+    /// Keep elements whose left edge is at or right of the right of
+    /// `element`.
+    pub fn right_of(self, element: &Element) -> Self {
+        self.anchor(Direction::RightOf, element, 0.0)
+    }
+
+    /// Keep elements within `DEFAULT_NEAR_DISTANCE` pixels of `element`.
+    pub fn near(self, element: &Element) -> Self {
+        self.near_within(element, DEFAULT_NEAR_DISTANCE)
+    }
+
+    /// Keep elements within `distance` pixels of `element`, measured
+    /// between the closest edges of the two.
+    pub fn near_within(self, element: &Element, distance: f64) -> Self {
+        self.anchor(Direction::Near, element, distance)
+    }
+
+    /// All matching elements, closest to the first anchor first.
+    pub(crate) fn find_all(&self, session: &DriverSession) -> Result<Vec<Element>, Error> {
+        let candidates = session.find_elements(&self.by)?;
+        if self.anchors.is_empty() || candidates.is_empty() {
+            return Ok(candidates);
+        }
+        let candidates = candidates.iter()
+            .map(Element::reference)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut anchors = vec![];
+        for anchor in &self.anchors {
+            anchors.push(json!({
+                "direction": anchor.direction.as_str(),
+                "element": anchor.element.reference()?,
+                "distance": anchor.distance,
+            }));
+        }
+        let found = session.execute_value(ExecuteCmd {
+            script: FILTER_SCRIPT.to_owned(),
+            args: vec![candidates.into(), anchors.into()],
+        })?;
+        found.into_elements().ok_or_else(|| Error::WebDriverError(WebDriverError {
+            error: "javascript error".to_owned(),
+            message: "Relative locator script did not return elements".to_owned(),
+            stacktrace: None,
+        }))
+    }
+
+    /// The first matching element, or a `no such element` error.
+    pub(crate) fn find(&self, session: &DriverSession) -> Result<Element, Error> {
+        self.find_all(session)?.into_iter().next().ok_or_else(|| {
+            Error::WebDriverError(WebDriverError {
+                error: "no such element".to_owned(),
+                message: format!("No element found for {} {}", self.by, self.describe_anchors()),
+                stacktrace: None,
+            })
+        })
+    }
+
+    fn describe_anchors(&self) -> String {
+        let parts: Vec<_> = self.anchors.iter()
+            .map(|a| format!("{} {}", a.direction.as_str(), a.element.raw_reference()))
+            .collect();
+        parts.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RelativeLocator;
+    use super::super::{By, Element};
+    use crate::testing::{element, FakeServer};
+    use crate::transport::Method;
+
+    #[test]
+    fn filters_in_the_browser() {
+        // Finds three inputs and answers the filter script with the
+        // candidates in reverse, so the test can check what was sent.
+        let server = FakeServer::new();
+        server.on(Method::Post, "/elements", |call| {
+            assert_eq!(call.body, json!({ "using": "tag name", "value": "input" }));
+            json!([element("a"), element("b"), element("c")])
+        });
+        server.on(Method::Post, "/execute/sync", |call| {
+            assert_eq!(call.body["args"][1], json!([
+                { "direction": "above", "distance": 0.0, "element": {
+                    "element-6066-11e4-a52e-4f735466cecf": "x", "ELEMENT": "x" } },
+                { "direction": "near", "distance": 50.0, "element": {
+                    "element-6066-11e4-a52e-4f735466cecf": "y", "ELEMENT": "y" } },
+            ]));
+            let mut candidates = call.body["args"][0].as_array().unwrap().clone();
+            candidates.reverse();
+            json!(candidates)
+        });
+        let sess = server.session();
+        let x = Element::new(&sess, "x".to_owned());
+        let y = Element::new(&sess, "y".to_owned());
+        let locator = RelativeLocator::new(By::tag_name("input")).above(&x).near(&y);
+
+        let found = sess.find_elements_relative(&locator).unwrap();
+        let refs: Vec<_> = found.iter().map(|e| e.raw_reference()).collect();
+        assert_eq!(refs, vec!["c", "b", "a"]);
+        assert_eq!(sess.find_element_relative(&locator).unwrap().raw_reference(), "c");
+    }
+}
@@ -0,0 +1,687 @@
+//! Values returned by scripts run with `DriverSession::execute`.
+//!
+//! The WebDriver protocol returns DOM nodes and windows from scripts as
+//! JSON objects holding a web reference, for example
+//! `{"element-6066-11e4-a52e-4f735466cecf": "<id>"}`. `ScriptValue`
+//! decodes these into handles bound to the session that ran the script.
+//!
+//! Arguments for `DriverSession::execute_typed` are converted by
+//! `IntoScriptArg`, which encodes handles such as `&Element` as web
+//! references and anything else with serde.
+//!
+//! With the `async` feature, `AsyncScriptValue` and the `AsyncDriverSession`
+//! methods of the same names do the same for the async API.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use webdriver_client::DriverSession;
+//! # use webdriver_client::messages::ExecuteCmd;
+//! # use webdriver_client::script::ScriptValue;
+//! # fn example(sess: &DriverSession) -> Result<(), webdriver_client::Error> {
+//! let value = sess.execute_value(ExecuteCmd {
+//!     script: "return document.querySelectorAll('a');".to_owned(),
+//!     args: vec![],
+//! })?;
+//! for link in value.into_elements().unwrap() {
+//!     println!("{}", link.text()?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{DriverSession, Element, Error, JsonValue, ShadowRoot};
+#[cfg(feature = "async")]
+use super::{AsyncDriverSession, AsyncElement, AsyncShadowRoot};
+use crate::messages::{ElementReference, ELEMENT_KEY, FRAME_KEY, SHADOW_ROOT_KEY, WINDOW_KEY};
+
+use serde::de::{self, DeserializeOwned};
+use serde::Serialize;
+use serde_json::{Map, Number};
+use std::collections::BTreeMap;
+
+/// A value returned by a script.
+#[derive(Debug, Clone)]
+pub enum ScriptValue {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<ScriptValue>),
+    Object(BTreeMap<String, ScriptValue>),
+    Element(Element),
+    ShadowRoot(ShadowRoot),
+    /// A top level browsing context, identified by its window handle as
+    /// accepted by `DriverSession::switch_window`.
+    Window(String),
+    /// A frame's window, identified by its handle. `to_reference` gives the
+    /// value to pass to `DriverSession::switch_to_frame`.
+    Frame(String),
+}
+
+impl ScriptValue {
+    /// Decode `value`, turning web references into handles bound to
+    /// `session`. An object holding a web reference key whose value is not
+    /// a string is a `JsonDecodeError`.
+    pub fn from_json(session: &DriverSession, value: JsonValue) -> Result<ScriptValue, Error> {
+        Ok(match value {
+            JsonValue::Null => ScriptValue::Null,
+            JsonValue::Bool(b) => ScriptValue::Bool(b),
+            JsonValue::Number(n) => ScriptValue::Number(n),
+            JsonValue::String(s) => ScriptValue::String(s),
+            JsonValue::Array(items) => ScriptValue::Array(
+                items.into_iter().map(|v| ScriptValue::from_json(session, v))
+                    .collect::<Result<_, _>>()?),
+            JsonValue::Object(mut map) => match take_web_reference(&mut map)? {
+                Some(WebReference::Element(r)) => ScriptValue::Element(Element::new(session, r)),
+                Some(WebReference::ShadowRoot(r)) =>
+                    ScriptValue::ShadowRoot(ShadowRoot::new(session, r)),
+                Some(WebReference::Window(r)) => ScriptValue::Window(r),
+                Some(WebReference::Frame(r)) => ScriptValue::Frame(r),
+                None => ScriptValue::Object(
+                    map.into_iter()
+                       .map(|(k, v)| Ok((k, ScriptValue::from_json(session, v)?)))
+                       .collect::<Result<_, Error>>()?),
+            },
+        })
+    }
+
+    /// Encode this value as JSON, with handles turned back into web
+    /// references.
+    pub fn to_reference(&self) -> JsonValue {
+        match *self {
+            ScriptValue::Null => JsonValue::Null,
+            ScriptValue::Bool(b) => JsonValue::Bool(b),
+            ScriptValue::Number(ref n) => JsonValue::Number(n.clone()),
+            ScriptValue::String(ref s) => JsonValue::String(s.clone()),
+            ScriptValue::Array(ref items) =>
+                JsonValue::Array(items.iter().map(ScriptValue::to_reference).collect()),
+            ScriptValue::Object(ref map) =>
+                JsonValue::Object(map.iter().map(|(k, v)| (k.clone(), v.to_reference())).collect()),
+            ScriptValue::Element(ref e) => json!({ ELEMENT_KEY: e.raw_reference() }),
+            ScriptValue::ShadowRoot(ref s) => json!({ SHADOW_ROOT_KEY: s.raw_reference() }),
+            ScriptValue::Window(ref h) => json!({ WINDOW_KEY: h }),
+            ScriptValue::Frame(ref h) => json!({ FRAME_KEY: h }),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(*self, ScriptValue::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            ScriptValue::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            ScriptValue::Number(ref n) => n.as_i64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            ScriptValue::Number(ref n) => n.as_f64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            ScriptValue::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[ScriptValue]> {
+        match *self {
+            ScriptValue::Array(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&BTreeMap<String, ScriptValue>> {
+        match *self {
+            ScriptValue::Object(ref map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_element(&self) -> Option<&Element> {
+        match *self {
+            ScriptValue::Element(ref e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn as_shadow_root(&self) -> Option<&ShadowRoot> {
+        match *self {
+            ScriptValue::ShadowRoot(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The element this value holds, if it is one.
+    pub fn into_element(self) -> Option<Element> {
+        match self {
+            ScriptValue::Element(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// The elements of an array holding only elements, such as the result
+    /// of `return document.querySelectorAll(...)`.
+    pub fn into_elements(self) -> Option<Vec<Element>> {
+        match self {
+            ScriptValue::Array(items) => items.into_iter().map(ScriptValue::into_element).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// A web reference found in a script's result.
+enum WebReference {
+    Element(String),
+    ShadowRoot(String),
+    Window(String),
+    Frame(String),
+}
+
+/// Remove the web reference held by the object `map`, if it holds one.
+fn take_web_reference(map: &mut Map<String, JsonValue>) -> Result<Option<WebReference>, Error> {
+    Ok(if let Some(r) = take_reference(map, ELEMENT_KEY)? {
+        Some(WebReference::Element(r))
+    } else if let Some(r) = take_reference(map, SHADOW_ROOT_KEY)? {
+        Some(WebReference::ShadowRoot(r))
+    } else if let Some(r) = take_reference(map, WINDOW_KEY)? {
+        Some(WebReference::Window(r))
+    } else {
+        take_reference(map, FRAME_KEY)?.map(WebReference::Frame)
+    })
+}
+
+/// Remove the web reference stored under `key` in `map`, if there is one.
+fn take_reference(map: &mut Map<String, JsonValue>, key: &str) -> Result<Option<String>, Error> {
+    match map.remove(key) {
+        None => Ok(None),
+        Some(JsonValue::String(reference)) => Ok(Some(reference)),
+        Some(other) => Err(Error::JsonDecodeError(de::Error::custom(
+            format!("Web reference {:?} should be a string, got {}", key, other)))),
+    }
+}
+
+/// A value returned by a script run with `AsyncDriverSession`, like
+/// `ScriptValue` with handles bound to the async session.
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+pub enum AsyncScriptValue {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<AsyncScriptValue>),
+    Object(BTreeMap<String, AsyncScriptValue>),
+    Element(AsyncElement),
+    ShadowRoot(AsyncShadowRoot),
+    /// A top level browsing context, identified by its window handle.
+    Window(String),
+    /// A frame's window, identified by its handle.
+    Frame(String),
+}
+
+#[cfg(feature = "async")]
+impl AsyncScriptValue {
+    /// Decode `value`, turning web references into handles bound to
+    /// `session`. See `ScriptValue::from_json`.
+    pub fn from_json(session: &AsyncDriverSession, value: JsonValue)
+        -> Result<AsyncScriptValue, Error>
+    {
+        Ok(match value {
+            JsonValue::Null => AsyncScriptValue::Null,
+            JsonValue::Bool(b) => AsyncScriptValue::Bool(b),
+            JsonValue::Number(n) => AsyncScriptValue::Number(n),
+            JsonValue::String(s) => AsyncScriptValue::String(s),
+            JsonValue::Array(items) => AsyncScriptValue::Array(
+                items.into_iter().map(|v| AsyncScriptValue::from_json(session, v))
+                    .collect::<Result<_, _>>()?),
+            JsonValue::Object(mut map) => match take_web_reference(&mut map)? {
+                Some(WebReference::Element(r)) =>
+                    AsyncScriptValue::Element(AsyncElement::new(session, r)),
+                Some(WebReference::ShadowRoot(r)) =>
+                    AsyncScriptValue::ShadowRoot(AsyncShadowRoot::new(session, r)),
+                Some(WebReference::Window(r)) => AsyncScriptValue::Window(r),
+                Some(WebReference::Frame(r)) => AsyncScriptValue::Frame(r),
+                None => AsyncScriptValue::Object(
+                    map.into_iter()
+                       .map(|(k, v)| Ok((k, AsyncScriptValue::from_json(session, v)?)))
+                       .collect::<Result<_, Error>>()?),
+            },
+        })
+    }
+
+    /// Encode this value as JSON, with handles turned back into web
+    /// references.
+    pub fn to_reference(&self) -> JsonValue {
+        match *self {
+            AsyncScriptValue::Null => JsonValue::Null,
+            AsyncScriptValue::Bool(b) => JsonValue::Bool(b),
+            AsyncScriptValue::Number(ref n) => JsonValue::Number(n.clone()),
+            AsyncScriptValue::String(ref s) => JsonValue::String(s.clone()),
+            AsyncScriptValue::Array(ref items) =>
+                JsonValue::Array(items.iter().map(AsyncScriptValue::to_reference).collect()),
+            AsyncScriptValue::Object(ref map) =>
+                JsonValue::Object(map.iter().map(|(k, v)| (k.clone(), v.to_reference())).collect()),
+            AsyncScriptValue::Element(ref e) => json!({ ELEMENT_KEY: e.raw_reference() }),
+            AsyncScriptValue::ShadowRoot(ref s) => json!({ SHADOW_ROOT_KEY: s.raw_reference() }),
+            AsyncScriptValue::Window(ref h) => json!({ WINDOW_KEY: h }),
+            AsyncScriptValue::Frame(ref h) => json!({ FRAME_KEY: h }),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(*self, AsyncScriptValue::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            AsyncScriptValue::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            AsyncScriptValue::Number(ref n) => n.as_i64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            AsyncScriptValue::Number(ref n) => n.as_f64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            AsyncScriptValue::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[AsyncScriptValue]> {
+        match *self {
+            AsyncScriptValue::Array(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&BTreeMap<String, AsyncScriptValue>> {
+        match *self {
+            AsyncScriptValue::Object(ref map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_element(&self) -> Option<&AsyncElement> {
+        match *self {
+            AsyncScriptValue::Element(ref e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn as_shadow_root(&self) -> Option<&AsyncShadowRoot> {
+        match *self {
+            AsyncScriptValue::ShadowRoot(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The element this value holds, if it is one.
+    pub fn into_element(self) -> Option<AsyncElement> {
+        match self {
+            AsyncScriptValue::Element(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// The elements of an array holding only elements.
+    pub fn into_elements(self) -> Option<Vec<AsyncElement>> {
+        match self {
+            AsyncScriptValue::Array(items) =>
+                items.into_iter().map(AsyncScriptValue::into_element).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// A value that can be passed to a script.
+///
+/// Implemented for element and shadow root handles, `ScriptValue`, and
+/// every type implementing `Serialize`.
+pub trait IntoScriptArg {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error>;
+}
+
+impl<T: Serialize> IntoScriptArg for T {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error> {
+        serde_json::to_value(self)
+    }
+}
+
+impl IntoScriptArg for &Element {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error> {
+        // Serialized with the legacy key too, which chromedriver requires.
+        serde_json::to_value(ElementReference::from_str(self.raw_reference()))
+    }
+}
+
+impl IntoScriptArg for Element {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error> {
+        (&self).into_script_arg()
+    }
+}
+
+#[cfg(feature = "async")]
+impl IntoScriptArg for &AsyncElement {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error> {
+        serde_json::to_value(ElementReference::from_str(self.raw_reference()))
+    }
+}
+
+#[cfg(feature = "async")]
+impl IntoScriptArg for AsyncElement {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error> {
+        (&self).into_script_arg()
+    }
+}
+
+impl IntoScriptArg for &ShadowRoot {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error> {
+        Ok(json!({ SHADOW_ROOT_KEY: self.raw_reference() }))
+    }
+}
+
+impl IntoScriptArg for ShadowRoot {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error> {
+        (&self).into_script_arg()
+    }
+}
+
+#[cfg(feature = "async")]
+impl IntoScriptArg for &AsyncShadowRoot {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error> {
+        Ok(json!({ SHADOW_ROOT_KEY: self.raw_reference() }))
+    }
+}
+
+#[cfg(feature = "async")]
+impl IntoScriptArg for AsyncShadowRoot {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error> {
+        (&self).into_script_arg()
+    }
+}
+
+impl IntoScriptArg for &ScriptValue {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error> {
+        match *self {
+            ScriptValue::Element(ref e) => e.into_script_arg(),
+            ref other => Ok(other.to_reference()),
+        }
+    }
+}
+
+impl IntoScriptArg for ScriptValue {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error> {
+        (&self).into_script_arg()
+    }
+}
+
+#[cfg(feature = "async")]
+impl IntoScriptArg for &AsyncScriptValue {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error> {
+        match *self {
+            AsyncScriptValue::Element(ref e) => e.into_script_arg(),
+            ref other => Ok(other.to_reference()),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl IntoScriptArg for AsyncScriptValue {
+    fn into_script_arg(self) -> Result<JsonValue, serde_json::Error> {
+        (&self).into_script_arg()
+    }
+}
+
+/// The argument list of a script: `()`, a tuple of up to eight values
+/// implementing `IntoScriptArg`, or a `Vec` of them.
+pub trait ScriptArgs {
+    fn into_script_args(self) -> Result<Vec<JsonValue>, Error>;
+}
+
+fn convert<A: IntoScriptArg>(index: usize, arg: A) -> Result<JsonValue, Error> {
+    arg.into_script_arg().map_err(|error| Error::ScriptArgument { index, error })
+}
+
+impl ScriptArgs for () {
+    fn into_script_args(self) -> Result<Vec<JsonValue>, Error> {
+        Ok(vec![])
+    }
+}
+
+impl<A: IntoScriptArg> ScriptArgs for Vec<A> {
+    fn into_script_args(self) -> Result<Vec<JsonValue>, Error> {
+        self.into_iter().enumerate().map(|(i, arg)| convert(i, arg)).collect()
+    }
+}
+
+macro_rules! tuple_script_args {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name: IntoScriptArg),+> ScriptArgs for ($($name,)+) {
+            fn into_script_args(self) -> Result<Vec<JsonValue>, Error> {
+                Ok(vec![$(convert($index, self.$index)?),+])
+            }
+        }
+    }
+}
+
+tuple_script_args!(A 0);
+tuple_script_args!(A 0, B 1);
+tuple_script_args!(A 0, B 1, C 2);
+tuple_script_args!(A 0, B 1, C 2, D 3);
+tuple_script_args!(A 0, B 1, C 2, D 3, E 4);
+tuple_script_args!(A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_script_args!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_script_args!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+/// Wrap `expression` in an asynchronous script that waits for the promise
+/// it returns and passes the outcome to the WebDriver callback.
+pub(crate) fn promise_script(expression: &str) -> String {
+    // The expression is called with the caller's arguments, leaving off
+    // the callback. It runs inside a `then` so that a synchronous throw
+    // becomes a rejection too.
+    format!(r#"var callback = arguments[arguments.length - 1];
+var args = Array.prototype.slice.call(arguments, 0, arguments.length - 1);
+Promise.resolve().then(function () {{
+  return (function () {{
+    return (
+{}
+    );
+  }}).apply(null, args);
+}}).then(function (value) {{
+  callback({{ "resolved": true, "value": value === undefined ? null : value }});
+}}, function (error) {{
+  var message = error instanceof Error ? error.message : String(error);
+  var stack = error instanceof Error && error.stack ? String(error.stack) : null;
+  callback({{ "resolved": false, "message": message, "stack": stack }});
+}});"#, expression)
+}
+
+/// What the script from `promise_script` reports.
+#[derive(Debug, Deserialize)]
+pub(crate) struct PromiseOutcome {
+    resolved: bool,
+    #[serde(default)]
+    value: JsonValue,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    stack: Option<String>,
+}
+
+/// Turn the outcome of a promise into its value or a `ScriptRejected`
+/// error.
+pub(crate) fn settle<R: DeserializeOwned>(outcome: PromiseOutcome) -> Result<R, Error> {
+    if outcome.resolved {
+        Ok(serde_json::from_value(outcome.value)?)
+    } else {
+        Err(Error::ScriptRejected { message: outcome.message, stack: outcome.stack })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{promise_script, ScriptValue};
+    use super::super::{DriverSession, Element, Error, JsonValue};
+    use std::collections::BTreeMap;
+    use crate::messages::ExecuteCmd;
+    use crate::testing::{element, FakeServer};
+    use crate::transport::Method;
+
+    /// A session answering synchronous scripts with a value mixing all
+    /// kinds of reference, and asynchronous ones with their arguments.
+    /// Scripts wrapped by `promise_script` are answered with their first
+    /// argument, standing in for the promise's outcome.
+    fn session() -> DriverSession {
+        let server = FakeServer::new();
+        server.respond(Method::Post, "/execute/sync", json!({
+            "count": 2,
+            "links": [
+                element("e1"),
+                { "element-6066-11e4-a52e-4f735466cecf": "e2", "ELEMENT": "e2" },
+            ],
+            "root": { "shadow-6066-11e4-a52e-4f735466cecf": "s1" },
+            "window": { "window-fcc6-11e5-b4f8-e2f1e4a0c2b0": "w1" },
+            "frame": { "frame-075b-4da1-b6ba-e579c2d3230a": "f1" },
+            "plain": { "a": null, "b": "text" },
+        }));
+        server.on(Method::Post, "/execute/async", |call| {
+            if call.body["script"].as_str().unwrap().contains("Promise.resolve()") {
+                call.body["args"][0].clone()
+            } else {
+                call.body["args"].clone()
+            }
+        });
+        server.session()
+    }
+
+    #[test]
+    fn decodes_web_references() {
+        let sess = session();
+        let value = sess.execute_value(ExecuteCmd { script: String::new(), args: vec![] }).unwrap();
+        let map = value.as_object().unwrap();
+
+        assert_eq!(map["count"].as_i64(), Some(2));
+        let root = map["root"].as_shadow_root().unwrap();
+        assert_eq!(root.raw_reference(), "s1");
+        match (&map["window"], &map["frame"]) {
+            (ScriptValue::Window(w), ScriptValue::Frame(f)) => assert_eq!((&**w, &**f), ("w1", "f1")),
+            other => panic!("Want window and frame, got {:?}", other),
+        }
+        let plain = map["plain"].as_object().unwrap();
+        assert!(plain["a"].is_null());
+        assert_eq!(plain["b"].as_str(), Some("text"));
+
+        let links = map["links"].clone().into_elements().unwrap();
+        let refs: Vec<_> = links.iter().map(|e| e.raw_reference()).collect();
+        assert_eq!(refs, vec!["e1", "e2"]);
+        assert_eq!(links[0].session().session_id(), "1234");
+        assert!(map["plain"].clone().into_elements().is_none());
+    }
+
+    #[test]
+    fn references_round_trip() {
+        let sess = session();
+        let json = json!([
+            { "element-6066-11e4-a52e-4f735466cecf": "e1" },
+            { "frame-075b-4da1-b6ba-e579c2d3230a": "f1" },
+            { "x": 1.5 },
+        ]);
+        assert_eq!(ScriptValue::from_json(&sess, json.clone()).unwrap().to_reference(), json);
+    }
+
+    #[test]
+    fn malformed_references() {
+        let sess = session();
+        for json in &[
+            json!({ "element-6066-11e4-a52e-4f735466cecf": 1 }),
+            json!([{ "window-fcc6-11e5-b4f8-e2f1e4a0c2b0": null }]),
+            json!({ "a": { "shadow-6066-11e4-a52e-4f735466cecf": {} } }),
+        ] {
+            match ScriptValue::from_json(&sess, json.clone()) {
+                Err(Error::JsonDecodeError(_)) => {}
+                other => panic!("Want JsonDecodeError for {}, got {:?}", json, other),
+            }
+        }
+    }
+
+    #[test]
+    fn typed_arguments() {
+        let sess = session();
+        let element = Element::new(&sess, "e1".to_owned());
+        let echoed: (JsonValue, i32, String, Vec<bool>) =
+            sess.execute_async_typed("", (&element, 1, "two", vec![true])).unwrap();
+        assert_eq!(echoed.0, json!({ "element-6066-11e4-a52e-4f735466cecf": "e1", "ELEMENT": "e1" }));
+        assert_eq!((echoed.1, &*echoed.2, echoed.3), (1, "two", vec![true]));
+
+        let none: Vec<JsonValue> = sess.execute_async_typed("", ()).unwrap();
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn argument_errors_name_the_argument() {
+        let sess = session();
+        let mut bad = BTreeMap::new();
+        bad.insert(vec![1u8], 1);
+        match sess.execute_async_typed::<_, JsonValue>("", ("fine", bad)) {
+            Err(err @ Error::ScriptArgument { index: 1, .. }) =>
+                assert!(err.to_string().starts_with("Invalid script argument 1: "), "{}", err),
+            other => panic!("Want ScriptArgument, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn promise_script_calls_back_with_outcome() {
+        let script = promise_script("fetch(arguments[0]) // a comment");
+        // The expression sits on its own line, so a trailing comment
+        // cannot swallow the rest of the wrapper.
+        assert!(script.contains("\nfetch(arguments[0]) // a comment\n"), "{}", script);
+        assert!(script.starts_with("var callback = arguments[arguments.length - 1];"));
+        assert!(script.contains(".apply(null, args)"));
+        assert!(script.contains(r#"callback({ "resolved": true, "#));
+        assert!(script.contains(r#"callback({ "resolved": false, "#));
+    }
+
+    #[test]
+    fn evaluate_promise_outcomes() {
+        let sess = session();
+        let value: i32 = sess.evaluate_promise(
+            "Promise.resolve(3)", (json!({ "resolved": true, "value": 3 }),)).unwrap();
+        assert_eq!(value, 3);
+
+        let rejected = json!({ "resolved": false, "message": "boom", "stack": "f@x.js:1" });
+        match sess.evaluate_promise::<_, i32>("Promise.reject()", (rejected,)) {
+            Err(Error::ScriptRejected { message, stack }) => {
+                assert_eq!(message, "boom");
+                assert_eq!(stack.as_deref(), Some("f@x.js:1"));
+            }
+            other => panic!("Want ScriptRejected, got {:?}", other),
+        }
+    }
+}
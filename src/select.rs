@@ -0,0 +1,222 @@
+//! A wrapper for `<select>` elements.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use webdriver_client::{By, DriverSession};
+//! # use webdriver_client::select::Select;
+//! # fn example(sess: &DriverSession) -> Result<(), webdriver_client::Error> {
+//! let country = Select::new(sess.find_element(&By::name("country"))?)?;
+//! country.select_by_visible_text("New Zealand")?;
+//! assert_eq!(country.first_selected_option()?.attribute("value")?, "nz");
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{By, Element, Error};
+use crate::messages::WebDriverError;
+use crate::util::{css_string, xpath_literal};
+
+pub(crate) const MULTIPLE_SCRIPT: &str = "return arguments[0].multiple;";
+
+/// A `<select>` element, with methods to read and change which of its
+/// options are selected.
+///
+/// Options are selected and deselected by clicking them, as a user would,
+/// so the page sees `input` and `change` events.
+#[derive(Clone, Debug)]
+pub struct Select {
+    element: Element,
+}
+
+impl Select {
+    /// Wrap `element`, which must be a `<select>`.
+    pub fn new(element: Element) -> Result<Self, Error> {
+        let name = element.name()?;
+        if !name.eq_ignore_ascii_case("select") {
+            return Err(error("invalid argument",
+                             format!("Element should have been a <select> but was a <{}>", name)));
+        }
+        Ok(Select { element })
+    }
+
+    /// The `<select>` element.
+    pub fn element(&self) -> &Element {
+        &self.element
+    }
+
+    /// Whether more than one option can be selected at a time.
+    pub fn is_multiple(&self) -> Result<bool, Error> {
+        self.element.session().execute_typed(MULTIPLE_SCRIPT, (&self.element,))
+    }
+
+    /// All the options, including those in an `<optgroup>`.
+    pub fn options(&self) -> Result<Vec<Element>, Error> {
+        self.element.find_elements(&By::tag_name("option"))
+    }
+
+    /// The options that are selected.
+    pub fn selected_options(&self) -> Result<Vec<Element>, Error> {
+        self.element.find_elements(&By::css("option:checked"))
+    }
+
+    /// The first selected option, or a `no such element` error if none is.
+    pub fn first_selected_option(&self) -> Result<Element, Error> {
+        self.selected_options()?.into_iter().next()
+            .ok_or_else(|| error("no such element", "No option is selected".to_owned()))
+    }
+
+    /// Select the options whose `value` attribute is `value`; only the
+    /// first unless this is a multi-select.
+    pub fn select_by_value(&self, value: &str) -> Result<(), Error> {
+        let options = self.element.find_elements(&by_value(value))?;
+        self.select(options, || format!("No option with value {:?}", value))
+    }
+
+    /// Select the options whose text is `text`, once whitespace is
+    /// normalized; only the first unless this is a multi-select.
+    pub fn select_by_visible_text(&self, text: &str) -> Result<(), Error> {
+        let options = self.element.find_elements(&by_text(text))?;
+        self.select(options, || format!("No option with text {:?}", text))
+    }
+
+    /// Select the option at `index` in `options()`, counting from 0.
+    pub fn select_by_index(&self, index: usize) -> Result<(), Error> {
+        let option = self.option_at(index)?;
+        self.select(vec![option], String::new)
+    }
+
+    /// Deselect all options of a multi-select.
+    pub fn deselect_all(&self) -> Result<(), Error> {
+        self.deselect(self.selected_options()?)
+    }
+
+    /// Deselect the options of a multi-select whose `value` attribute is
+    /// `value`.
+    pub fn deselect_by_value(&self, value: &str) -> Result<(), Error> {
+        self.deselect(self.element.find_elements(&by_value(value))?)
+    }
+
+    /// Deselect the options of a multi-select whose text is `text`, once
+    /// whitespace is normalized.
+    pub fn deselect_by_visible_text(&self, text: &str) -> Result<(), Error> {
+        self.deselect(self.element.find_elements(&by_text(text))?)
+    }
+
+    /// Deselect the option at `index` in `options()` of a multi-select.
+    pub fn deselect_by_index(&self, index: usize) -> Result<(), Error> {
+        let option = self.option_at(index)?;
+        self.deselect(vec![option])
+    }
+
+    fn option_at(&self, index: usize) -> Result<Element, Error> {
+        let mut options = self.options()?;
+        if index >= options.len() {
+            return Err(error("no such element",
+                             format!("No option at index {}, there are {}", index, options.len())));
+        }
+        Ok(options.swap_remove(index))
+    }
+
+    /// Select `options`, or report `missing()` if there are none.
+    fn select<F: FnOnce() -> String>(&self, options: Vec<Element>, missing: F) -> Result<(), Error> {
+        if options.is_empty() {
+            return Err(error("no such element", missing()));
+        }
+        let multiple = self.is_multiple()?;
+        for option in options {
+            if !option.is_selected()? {
+                option.click()?;
+            }
+            if !multiple {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn deselect(&self, options: Vec<Element>) -> Result<(), Error> {
+        if !self.is_multiple()? {
+            return Err(error("invalid element state",
+                             "Only options of a multi-select can be deselected".to_owned()));
+        }
+        for option in options {
+            if option.is_selected()? {
+                option.click()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn by_value(value: &str) -> By {
+    By::css(format!("option[value={}]", css_string(value)))
+}
+
+fn by_text(text: &str) -> By {
+    By::xpath(format!(".//option[normalize-space(.) = normalize-space({})]", xpath_literal(text)))
+}
+
+fn error(code: &str, message: String) -> Error {
+    Error::WebDriverError(WebDriverError {
+        error: code.to_owned(),
+        message,
+        stacktrace: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Select;
+    use crate::{Element, Error, JsonValue};
+    use crate::testing::{element, FakeServer};
+    use crate::transport::Method;
+
+    #[test]
+    fn select_and_deselect() {
+        // A multi-select "s" of options "a" (selected), "b" and "c", and a
+        // paragraph "p".
+        let server = FakeServer::new();
+        server.respond(Method::Get, "/element/s/name", json!("select"));
+        server.respond(Method::Get, "/element/p/name", json!("p"));
+        server.on(Method::Post, "/execute/sync", |call| {
+            assert_eq!(call.body["script"], "return arguments[0].multiple;");
+            json!(true)
+        });
+        server.on(Method::Post, "/element/s/elements", |call| {
+            match call.body["value"].as_str().unwrap() {
+                "option" => json!([element("a"), element("b"), element("c")]),
+                "option:checked" => json!([element("a")]),
+                r#"option[value="b"]"# => json!([element("b")]),
+                ".//option[normalize-space(.) = normalize-space('A')]" => json!([element("a")]),
+                other => panic!("Unexpected selector {}", other),
+            }
+        });
+        server.respond(Method::Get, "/element/{}/selected", json!(false));
+        server.respond(Method::Get, "/element/a/selected", json!(true));
+        server.respond(Method::Post, "/element/{}/click", JsonValue::Null);
+        let sess = server.session();
+
+        let select = Select::new(Element::new(&sess, "s".to_owned())).unwrap();
+        assert!(select.is_multiple().unwrap());
+        assert_eq!(select.options().unwrap().len(), 3);
+        assert_eq!(select.first_selected_option().unwrap().raw_reference(), "a");
+
+        select.select_by_value("b").unwrap();
+        select.select_by_index(2).unwrap();
+        // Already selected.
+        select.select_by_visible_text("A").unwrap();
+        select.deselect_by_visible_text("A").unwrap();
+        let clicked: Vec<_> = server.calls().into_iter()
+            .filter(|call| call.path.ends_with("/click"))
+            .map(|call| call.path[9..10].to_owned())
+            .collect();
+        assert_eq!(clicked, ["b", "c", "a"]);
+        assert!(select.select_by_index(3).is_err());
+
+        match Select::new(Element::new(&sess, "p".to_owned())) {
+            Err(Error::WebDriverError(err)) => assert_eq!(err.error, "invalid argument"),
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! Element snapshots: many properties of elements read in one script.
+//!
+//! Reading the text, attributes, position and visibility of a list of
+//! elements with `Element::text`, `Element::attribute` and friends costs a
+//! round trip to the browser for each value. `Element::snapshot` and
+//! `DriverSession::snapshot_all` instead read the chosen `Field`s in a
+//! single script and return an `ElementSnapshot` for each element.
+//!
+//! The values are computed in the page, so some differ slightly from their
+//! WebDriver commands: `Field::Text` is the element's trimmed `innerText`,
+//! and `Field::Displayed` uses the browser's `checkVisibility()`, falling
+//! back to the element's computed style and layout boxes.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use webdriver_client::{By, DriverSession};
+//! # use webdriver_client::snapshot::Field;
+//! # fn example(sess: &DriverSession) -> Result<(), webdriver_client::Error> {
+//! let fields = [Field::Text, Field::Attribute("data-id".to_owned()), Field::Displayed];
+//! for row in sess.snapshot_all(&By::css("tr"), &fields)? {
+//!     println!("{:?} {:?} {:?}", row.attribute("data-id"), row.text(), row.is_displayed());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{By, DriverSession, Element, Error, JsonValue, ScriptValue};
+use crate::locator::LOCATE_SCRIPT;
+use crate::messages::{ExecuteCmd, WebDriverError};
+
+use std::collections::BTreeMap;
+
+/// Run after `LOCATE_SCRIPT`, which defines `locate`.
+pub(crate) const SNAPSHOT_SCRIPT: &str = r#"var elements = arguments[0].steps
+  ? locate(document, arguments[0].steps) : [arguments[0].element];
+var fields = arguments[1];
+function displayed(e) {
+  if (e.checkVisibility) {
+    return e.checkVisibility({ opacityProperty: true, visibilityProperty: true });
+  }
+  var style = window.getComputedStyle(e);
+  return e.getClientRects().length > 0 && style.visibility !== 'hidden'
+    && style.visibility !== 'collapse' && style.opacity !== '0';
+}
+function plain(value) {
+  if (value === undefined || value === null) { return null; }
+  var type = typeof value;
+  return type === 'string' || type === 'number' || type === 'boolean' ? value : String(value);
+}
+function snapshot(e) {
+  var out = { element: e, attributes: {}, properties: {}, css_values: {} };
+  fields.forEach(function (field) {
+    if (field === 'text') { out.text = e.innerText.trim(); }
+    else if (field === 'tag_name') { out.tag_name = e.tagName.toLowerCase(); }
+    else if (field === 'rect') {
+      var r = e.getBoundingClientRect();
+      out.rect = { x: r.left + window.pageXOffset, y: r.top + window.pageYOffset,
+                   width: r.width, height: r.height };
+    }
+    else if (field === 'displayed') { out.displayed = displayed(e); }
+    else if (field === 'enabled') { out.enabled = !e.matches(':disabled'); }
+    else if (field === 'selected') { out.selected = !!(e.selected || e.checked); }
+    else if (field.attribute !== undefined) { out.attributes[field.attribute] = e.getAttribute(field.attribute); }
+    else if (field.property !== undefined) { out.properties[field.property] = plain(e[field.property]); }
+    else if (field.css !== undefined) {
+      out.css_values[field.css] = window.getComputedStyle(e).getPropertyValue(field.css);
+    }
+  });
+  return out;
+}
+return elements.map(snapshot);"#;
+
+/// A value to read for an `ElementSnapshot`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    /// The visible text, like `Element::text`.
+    Text,
+    /// The lower case tag name, like `Element::name`.
+    TagName,
+    /// An attribute, like `Element::attribute`.
+    Attribute(String),
+    /// A DOM property, like `Element::property`.
+    Property(String),
+    /// A computed CSS property, like `Element::css_value`.
+    CssValue(String),
+    /// The position and size of the element, relative to the document.
+    Rect,
+    /// Whether the element is visible, like `Element::is_displayed`.
+    Displayed,
+    /// Whether the element is enabled, like `Element::is_enabled`.
+    Enabled,
+    /// Whether an option, checkbox or radio button is selected.
+    Selected,
+}
+
+impl Field {
+    fn to_json(&self) -> JsonValue {
+        match *self {
+            Field::Text => json!("text"),
+            Field::TagName => json!("tag_name"),
+            Field::Attribute(ref name) => json!({ "attribute": name }),
+            Field::Property(ref name) => json!({ "property": name }),
+            Field::CssValue(ref name) => json!({ "css": name }),
+            Field::Rect => json!("rect"),
+            Field::Displayed => json!("displayed"),
+            Field::Enabled => json!("enabled"),
+            Field::Selected => json!("selected"),
+        }
+    }
+}
+
+/// The position and size of an element in CSS pixels, relative to the
+/// document.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ElementRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Deserialize)]
+struct RawSnapshot {
+    text: Option<String>,
+    tag_name: Option<String>,
+    #[serde(default)]
+    attributes: BTreeMap<String, Option<String>>,
+    #[serde(default)]
+    properties: BTreeMap<String, JsonValue>,
+    #[serde(default)]
+    css_values: BTreeMap<String, String>,
+    rect: Option<ElementRect>,
+    displayed: Option<bool>,
+    enabled: Option<bool>,
+    selected: Option<bool>,
+}
+
+/// The values of the requested `Field`s of an element. The accessor for a
+/// field that was not requested returns `None`.
+#[derive(Debug, Clone)]
+pub struct ElementSnapshot {
+    element: Element,
+    text: Option<String>,
+    tag_name: Option<String>,
+    attributes: BTreeMap<String, Option<String>>,
+    properties: BTreeMap<String, JsonValue>,
+    css_values: BTreeMap<String, String>,
+    rect: Option<ElementRect>,
+    displayed: Option<bool>,
+    enabled: Option<bool>,
+    selected: Option<bool>,
+}
+
+impl ElementSnapshot {
+    /// The element, for further commands.
+    pub fn element(&self) -> &Element {
+        &self.element
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_ref().map(|s| &s[..])
+    }
+
+    pub fn tag_name(&self) -> Option<&str> {
+        self.tag_name.as_ref().map(|s| &s[..])
+    }
+
+    /// The attribute's value, or `None` if the element has no such
+    /// attribute or it was not requested.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).and_then(|value| value.as_ref()).map(|s| &s[..])
+    }
+
+    /// The property's value. Values other than strings, numbers and
+    /// booleans are converted to strings.
+    pub fn property(&self, name: &str) -> Option<&JsonValue> {
+        self.properties.get(name)
+    }
+
+    pub fn css_value(&self, name: &str) -> Option<&str> {
+        self.css_values.get(name).map(|s| &s[..])
+    }
+
+    pub fn rect(&self) -> Option<ElementRect> {
+        self.rect
+    }
+
+    pub fn is_displayed(&self) -> Option<bool> {
+        self.displayed
+    }
+
+    pub fn is_enabled(&self) -> Option<bool> {
+        self.enabled
+    }
+
+    pub fn is_selected(&self) -> Option<bool> {
+        self.selected
+    }
+}
+
+fn from_json(session: &DriverSession, mut value: JsonValue) -> Result<ElementSnapshot, Error> {
+    let element = ScriptValue::from_json(session, value["element"].take())?.into_element()
+        .ok_or_else(|| script_error("Snapshot script did not return an element".to_owned()))?;
+    let raw: RawSnapshot = serde_json::from_value(value)?;
+    Ok(ElementSnapshot {
+        element,
+        text: raw.text,
+        tag_name: raw.tag_name,
+        attributes: raw.attributes,
+        properties: raw.properties,
+        css_values: raw.css_values,
+        rect: raw.rect,
+        displayed: raw.displayed,
+        enabled: raw.enabled,
+        selected: raw.selected,
+    })
+}
+
+/// Run the snapshot script on `target`: `{ "element": reference }` for one
+/// element or `{ "steps": steps }` for every element matching a locator.
+fn snapshot(session: &DriverSession, target: JsonValue, fields: &[Field])
+                       -> Result<Vec<ElementSnapshot>, Error> {
+    let fields: Vec<_> = fields.iter().map(Field::to_json).collect();
+    let value = session.execute(ExecuteCmd {
+        script: format!("{}\n{}", LOCATE_SCRIPT, SNAPSHOT_SCRIPT),
+        args: vec![target, fields.into()],
+    })?;
+    match value {
+        JsonValue::Array(values) => values.into_iter().map(|v| from_json(session, v)).collect(),
+        other => Err(script_error(format!("Snapshot script returned {}", other))),
+    }
+}
+
+fn script_error(message: String) -> Error {
+    Error::WebDriverError(WebDriverError {
+        error: "javascript error".to_owned(),
+        message,
+        stacktrace: None,
+    })
+}
+
+pub(crate) fn snapshot_element(element: &Element, fields: &[Field])
+                                -> Result<ElementSnapshot, Error> {
+    let target = json!({ "element": element.reference()? });
+    snapshot(element.session(), target, fields)?.into_iter().next()
+        .ok_or_else(|| script_error("Snapshot script returned no elements".to_owned()))
+}
+
+pub(crate) fn snapshot_all(session: &DriverSession, by: &By, fields: &[Field])
+                           -> Result<Vec<ElementSnapshot>, Error> {
+    snapshot(session, json!({ "steps": by.steps_json() }), fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ElementRect, Field};
+    use crate::By;
+    use crate::testing::{element, FakeServer};
+    use crate::transport::Method;
+
+    #[test]
+    fn snapshot_all() {
+        // Answers the snapshot script with two rows, checking what was
+        // asked for.
+        let server = FakeServer::new();
+        server.on(Method::Post, "/execute/sync", |call| {
+            assert_eq!(call.body["args"][0], json!({ "steps": [
+                { "using": "css selector", "value": "#rows", "index": null },
+                { "using": "tag name", "value": "tr", "index": null },
+            ] }));
+            assert_eq!(call.body["args"][1], json!([
+                "text", { "attribute": "data-id" }, { "css": "color" }, "rect", "displayed",
+            ]));
+            json!([
+                { "element": element("r1"), "text": "One", "attributes": { "data-id": "1" },
+                  "properties": {}, "css_values": { "color": "rgb(0, 0, 0)" },
+                  "rect": { "x": 0, "y": 10.5, "width": 100, "height": 20 },
+                  "displayed": true },
+                { "element": element("r2"), "text": "", "attributes": { "data-id": null },
+                  "properties": {}, "css_values": { "color": "rgb(0, 0, 0)" },
+                  "rect": { "x": 0, "y": 30.5, "width": 100, "height": 0 },
+                  "displayed": false },
+            ])
+        });
+        let sess = server.session();
+        let fields = [Field::Text, Field::Attribute("data-id".to_owned()),
+                      Field::CssValue("color".to_owned()), Field::Rect, Field::Displayed];
+        let rows = sess.snapshot_all(&By::tag_name("tr").within(By::id("rows")), &fields).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].element().raw_reference(), "r1");
+        assert_eq!(rows[0].text(), Some("One"));
+        assert_eq!(rows[0].attribute("data-id"), Some("1"));
+        assert_eq!(rows[0].css_value("color"), Some("rgb(0, 0, 0)"));
+        assert_eq!(rows[0].rect(), Some(ElementRect { x: 0.0, y: 10.5, width: 100.0, height: 20.0 }));
+        assert_eq!(rows[0].is_displayed(), Some(true));
+        assert_eq!(rows[1].attribute("data-id"), None);
+        assert_eq!(rows[1].is_displayed(), Some(false));
+        // Not requested.
+        assert_eq!(rows[1].tag_name(), None);
+        assert_eq!(rows[1].is_enabled(), None);
+    }
+}
@@ -0,0 +1,188 @@
+//! Reading HTML tables.
+//!
+//! `Element::table` reads a `<table>` element in a single script and
+//! returns a `Table` of the text of its header and body cells. Cells that
+//! span several columns or rows with `colspan` or `rowspan` are repeated in
+//! every position they cover, so every row has one value for each column.
+//!
+//! The header rows are the leading rows of the table that are in its
+//! `<thead>` or contain only `<th>` cells. When there are several, a
+//! column's name is the distinct texts of its header cells joined with
+//! `" / "`. All other rows, including those in a `<tfoot>`, are body rows.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use webdriver_client::{By, DriverSession};
+//! # fn example(sess: &DriverSession) -> Result<(), webdriver_client::Error> {
+//! let table = sess.find_element(&By::id("prices"))?.table()?;
+//! assert_eq!(table.get(0, "Price"), Some("12.50"));
+//! let row = table.find_row("Name", "Widget").expect("No widget");
+//! println!("{:?}\n{}", row, table.to_csv());
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{Element, Error, JsonValue};
+use crate::messages::{ExecuteCmd, WebDriverError};
+
+pub(crate) const TABLE_SCRIPT: &str = r#"var table = arguments[0];
+if (table.tagName.toLowerCase() !== 'table') { return table.tagName.toLowerCase(); }
+var rows = table.rows, grid = [], onlyTh = [], width = 0;
+for (var r = 0; r < rows.length; r++) {
+  var cells = rows[r].cells, col = 0;
+  grid[r] = grid[r] || [];
+  onlyTh[r] = cells.length > 0;
+  for (var c = 0; c < cells.length; c++) {
+    var cell = cells[c], text = cell.innerText.trim();
+    if (cell.tagName !== 'TH') { onlyTh[r] = false; }
+    while (grid[r][col] !== undefined) { col++; }
+    var colspan = Math.max(cell.colSpan, 1);
+    var rowspan = cell.rowSpan === 0 ? rows.length - r : Math.max(cell.rowSpan, 1);
+    for (var i = 0; i < rowspan && r + i < rows.length; i++) {
+      grid[r + i] = grid[r + i] || [];
+      for (var j = 0; j < colspan; j++) { grid[r + i][col + j] = text; }
+    }
+    col += colspan;
+  }
+}
+grid.forEach(function (row) { width = Math.max(width, row.length); });
+grid = grid.map(function (row) {
+  var out = [];
+  for (var i = 0; i < width; i++) { out.push(row[i] === undefined ? '' : row[i]); }
+  return out;
+});
+var headerRows = 0;
+while (headerRows < rows.length && (rows[headerRows].parentNode.tagName === 'THEAD' || onlyTh[headerRows])) {
+  headerRows++;
+}
+var headers = [];
+for (var i = 0; i < width && headerRows > 0; i++) {
+  var parts = [];
+  for (var r = 0; r < headerRows; r++) {
+    var text = grid[r][i];
+    if (text !== '' && parts[parts.length - 1] !== text) { parts.push(text); }
+  }
+  headers.push(parts.join(' / '));
+}
+return { headers: headers, rows: grid.slice(headerRows) };"#;
+
+/// The text of the cells of an HTML table. See the module documentation.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// The column names, or an empty slice if the table has no header rows.
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// The body rows, each with one value per column.
+    pub fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    /// The index of the first column named `name`.
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.headers.iter().position(|header| header == name)
+    }
+
+    /// The values of the column named `name`, one for each body row.
+    pub fn column(&self, name: &str) -> Option<Vec<&str>> {
+        let index = self.column_index(name)?;
+        Some(self.rows.iter().map(|row| &row[index][..]).collect())
+    }
+
+    /// The value in body row `row` of the column named `name`.
+    pub fn get(&self, row: usize, name: &str) -> Option<&str> {
+        let index = self.column_index(name)?;
+        self.rows.get(row).map(|row| &row[index][..])
+    }
+
+    /// The first body row whose value in the column named `name` is
+    /// `value`.
+    pub fn find_row(&self, name: &str, value: &str) -> Option<&[String]> {
+        let index = self.column_index(name)?;
+        self.rows.iter().find(|row| row[index] == value).map(|row| &row[..])
+    }
+
+    /// The table as CSV: the header line, if there are headers, then a line
+    /// for each body row. Values are quoted when they contain a comma,
+    /// quote or line break, and lines end with `\n`.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        let lines = Some(&self.headers).filter(|headers| !headers.is_empty())
+            .into_iter()
+            .chain(&self.rows);
+        for line in lines {
+            let values: Vec<_> = line.iter().map(|value| csv_value(value)).collect();
+            out.push_str(&values.join(","));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn csv_value(value: &str) -> String {
+    if value.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+pub(crate) fn read_table(element: &Element) -> Result<Table, Error> {
+    let value = element.session().execute(ExecuteCmd {
+        script: TABLE_SCRIPT.to_owned(),
+        args: vec![element.reference()?],
+    })?;
+    match value {
+        JsonValue::String(tag) => Err(Error::WebDriverError(WebDriverError {
+            error: "invalid argument".to_owned(),
+            message: format!("Element is a <{}>, not a <table>", tag),
+            stacktrace: None,
+        })),
+        value => Ok(serde_json::from_value(value)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Table;
+
+    fn table() -> Table {
+        serde_json::from_value(json!({
+            "headers": ["Name", "Price", "Notes"],
+            "rows": [
+                ["Widget", "12.50", ""],
+                ["Gadget", "3", "Red, \"large\""],
+            ],
+        })).unwrap()
+    }
+
+    #[test]
+    fn lookup() {
+        let table = table();
+        assert_eq!(table.column_index("Price"), Some(1));
+        assert_eq!(table.column("Name"), Some(vec!["Widget", "Gadget"]));
+        assert_eq!(table.column("Colour"), None);
+        assert_eq!(table.get(1, "Price"), Some("3"));
+        assert_eq!(table.get(2, "Price"), None);
+        assert_eq!(table.find_row("Name", "Gadget").unwrap()[1], "3");
+        assert_eq!(table.find_row("Name", "Gizmo"), None);
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(table().to_csv(),
+                   "Name,Price,Notes\nWidget,12.50,\nGadget,3,\"Red, \"\"large\"\"\"\n");
+        let headless: Table = serde_json::from_value(json!({
+            "headers": [],
+            "rows": [["a", "b"]],
+        })).unwrap();
+        assert_eq!(headless.to_csv(), "a,b\n");
+    }
+}
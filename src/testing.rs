@@ -0,0 +1,90 @@
+//! An in-memory WebDriver server for unit tests.
+//!
+//! `FakeServer` answers New Session with the session id `1234`, Delete
+//! Session with `null`, and other requests with the values computed by
+//! the routes a test registers. Requests no route matches panic, so a test
+//! fails on any command it did not expect.
+
+use super::{DriverSession, Error, HttpDriverBuilder, JsonValue};
+pub(crate) use crate::mock::Call;
+use crate::mock::{MockResponse, Router};
+use crate::messages::NewSessionCmd;
+use crate::transport::{Method, Response, Transport};
+
+use std::sync::Arc;
+
+const SESSION_PATH: &str = "/session/1234";
+
+/// Answers requests from memory, with the routing of `mock::MockServer`.
+/// Clones share their routes and calls.
+///
+/// Paths of routes and calls are relative to the session, for example
+/// `/element/e1/click`.
+#[derive(Clone)]
+pub(crate) struct FakeServer {
+    router: Arc<Router>,
+}
+
+impl FakeServer {
+    pub fn new() -> Self {
+        let server = FakeServer { router: Arc::new(Router::default()) };
+        server.respond(Method::Delete, "", JsonValue::Null);
+        server
+    }
+
+    /// Answer requests to `path`, relative to the session, with the value
+    /// computed by `handler`. See `MockServer::on`.
+    pub fn on<F>(&self, method: Method, path: &str, handler: F)
+        where F: Fn(&Call) -> JsonValue + Send + Sync + 'static
+    {
+        self.router.on(method, path, move |call| MockResponse::value(handler(call)));
+    }
+
+    /// Answer requests to `path` with `value`. See `on`.
+    pub fn respond(&self, method: Method, path: &str, value: JsonValue) {
+        self.on(method, path, move |_| value.clone());
+    }
+
+    /// The requests within the session received so far, oldest first.
+    pub fn calls(&self) -> Vec<Call> {
+        self.router.calls()
+    }
+
+    /// A session using this server, for a driver at `http://fake`.
+    pub fn session(&self) -> DriverSession {
+        session_with("http://fake", Box::new(self.clone()))
+    }
+}
+
+impl Transport for FakeServer {
+    fn send(&self, method: Method, path: &str, body: Option<&str>) -> Result<Response, Error> {
+        let response = if (method, path) == (Method::Post, "/session") {
+            MockResponse::value(json!({ "sessionId": "1234", "capabilities": {} }))
+        } else {
+            let call = Call {
+                method,
+                path: path.strip_prefix(SESSION_PATH)
+                    .unwrap_or_else(|| panic!("Request outside the session: {}", path))
+                    .to_owned(),
+                body: body.map(|b| serde_json::from_str(b).unwrap()).unwrap_or_default(),
+            };
+            self.router.answer(call).unwrap_or_else(|| {
+                panic!("Unexpected request {:?} {} {:?}", method, path, body)
+            })
+        };
+        Ok(Response { status: response.status, body: response.body.to_string() })
+    }
+}
+
+/// A session for a driver at `url`, sending its commands through
+/// `transport`, such as a `FakeServer` or a transport wrapping one.
+pub(crate) fn session_with(url: &str, transport: Box<dyn Transport>) -> DriverSession {
+    let driver = HttpDriverBuilder::default().url(url).build().unwrap();
+    DriverSession::create_session_with_transport(Box::new(driver), &NewSessionCmd::default(),
+                                                 transport).unwrap()
+}
+
+/// A web element reference with the id `id`.
+pub(crate) fn element(id: &str) -> JsonValue {
+    json!({ "element-6066-11e4-a52e-4f735466cecf": id })
+}
@@ -0,0 +1,186 @@
+//! File uploads through `<input type="file">` elements.
+//!
+//! `Element::upload_file` and `upload_files` give a file input the paths
+//! of local files. A local driver reads the files itself, so it is sent
+//! their absolute paths. A remote driver, such as a Selenium Grid node,
+//! can't see this machine's files, so each file is first zipped and sent
+//! through Selenium's `/se/file` command, and the paths the server saved
+//! them to are sent instead. See `DriverSession::is_remote` for which
+//! sessions are remote.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use webdriver_client::{By, DriverSession};
+//! # fn example(sess: &DriverSession) -> Result<(), webdriver_client::Error> {
+//! let input = sess.find_element(&By::css("input[type=file]"))?;
+//! input.upload_files(&["photos/cat.jpg", "photos/dog.jpg"])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{Element, Error};
+
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A zip archive holding `contents` as a single, uncompressed file called
+/// `name`, as `/se/file` expects.
+pub fn zip_file(name: &str, contents: &[u8]) -> Result<Vec<u8>, Error> {
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput,
+                                      format!("{} is too large to zip", name));
+    let size = u32::try_from(contents.len()).map_err(|_| too_large())?;
+    let name_len = u16::try_from(name.len()).map_err(|_| too_large())?;
+    let crc = crc32(contents);
+
+    // The fields shared by the local file header and the central directory
+    // entry: version needed, UTF-8 name flag, stored, 1980-01-01 00:00,
+    // CRC-32, compressed and uncompressed size, name length and no extra
+    // field.
+    let mut entry = vec![];
+    push_u16(&mut entry, 20);
+    push_u16(&mut entry, 0x0800);
+    push_u16(&mut entry, 0);
+    push_u16(&mut entry, 0);
+    push_u16(&mut entry, 0x21);
+    push_u32(&mut entry, crc);
+    push_u32(&mut entry, size);
+    push_u32(&mut entry, size);
+    push_u16(&mut entry, name_len);
+    push_u16(&mut entry, 0);
+
+    let mut out = vec![];
+    push_u32(&mut out, 0x0403_4b50);
+    out.extend_from_slice(&entry);
+    out.extend_from_slice(name.as_bytes());
+    out.extend_from_slice(contents);
+
+    let directory_offset = u32::try_from(out.len()).map_err(|_| too_large())?;
+    push_u32(&mut out, 0x0201_4b50);
+    push_u16(&mut out, 20);
+    out.extend_from_slice(&entry);
+    // Comment length, disk, internal and external attributes and the
+    // offset of the local header.
+    push_u16(&mut out, 0);
+    push_u16(&mut out, 0);
+    push_u16(&mut out, 0);
+    push_u32(&mut out, 0);
+    push_u32(&mut out, 0);
+    out.extend_from_slice(name.as_bytes());
+    let directory_size = out.len() as u32 - directory_offset;
+
+    push_u32(&mut out, 0x0605_4b50);
+    push_u16(&mut out, 0);
+    push_u16(&mut out, 0);
+    push_u16(&mut out, 1);
+    push_u16(&mut out, 1);
+    push_u32(&mut out, directory_size);
+    push_u32(&mut out, directory_offset);
+    push_u16(&mut out, 0);
+    Ok(out)
+}
+
+fn push_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+/// The CRC-32 (IEEE) checksum of `data`, as used by zip.
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut c = i as u32;
+        for _ in 0..8 {
+            c = if c & 1 == 1 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *entry = c;
+    }
+    !data.iter().fold(!0u32, |crc, &byte| table[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+/// Send a local file to the remote end, returning the path it was saved to.
+fn send_file(element: &Element, path: &Path) -> Result<String, Error> {
+    let name = path.file_name().and_then(|name| name.to_str()).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("{:?} is not a file name", path))
+    })?;
+    let zip = zip_file(name, &fs::read(path)?)?;
+    element.session().upload_zip(&base64::encode(&zip))
+}
+
+pub(crate) fn upload_files<P: AsRef<Path>>(element: &Element, paths: &[P]) -> Result<(), Error> {
+    let mut remote_paths = vec![];
+    for path in paths {
+        let path = fs::canonicalize(path)?;
+        if element.session().is_remote() {
+            remote_paths.push(send_file(element, &path)?);
+        } else {
+            remote_paths.push(path.to_string_lossy().into_owned());
+        }
+    }
+    element.send_keys(&remote_paths.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crc32, zip_file};
+    use crate::{Element, JsonValue};
+    use crate::testing::{self, FakeServer};
+    use crate::transport::Method;
+
+    use std::fs;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn zips() {
+        let zip = zip_file("a.txt", b"hello").unwrap();
+        // Local header, name and contents, central directory and its end.
+        assert_eq!(zip.len(), 30 + 5 + 5 + 46 + 5 + 22);
+        assert_eq!(&zip[..4], b"PK\x03\x04");
+        assert_eq!(&zip[14..18], &crc32(b"hello").to_le_bytes());
+        assert_eq!(&zip[30..40], b"a.txthello");
+        assert_eq!(&zip[40..44], b"PK\x01\x02");
+        assert_eq!(&zip[86..91], b"a.txt");
+        assert_eq!(&zip[91..95], b"PK\x05\x06");
+        // The central directory's size and offset.
+        assert_eq!(&zip[103..111], &[51, 0, 0, 0, 40, 0, 0, 0]);
+    }
+
+    #[test]
+    fn remote_upload() {
+        let dir = std::env::temp_dir().join(format!("webdriver_client_upload_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("one.txt"), "one").unwrap();
+        fs::write(dir.join("two.txt"), "two").unwrap();
+
+        // A remote end that saves uploaded files to "/grid/<name>".
+        let server = FakeServer::new();
+        server.on(Method::Post, "/se/file", |call| {
+            let zip = base64::decode(call.body["file"].as_str().unwrap()).unwrap();
+            let name_len = zip[26] as usize;
+            json!(format!("/grid/{}", String::from_utf8_lossy(&zip[30..30 + name_len])))
+        });
+        server.respond(Method::Post, "/element/f/value", JsonValue::Null);
+        let sess = testing::session_with("http://grid.example.com", Box::new(server.clone()));
+        assert!(sess.is_remote());
+
+        let input = Element::new(&sess, "f".to_owned());
+        input.upload_files(&[dir.join("one.txt"), dir.join("two.txt")]).unwrap();
+        let typed: Vec<_> = server.calls().into_iter()
+            .filter(|call| call.path == "/element/f/value")
+            .map(|call| call.body["text"].clone())
+            .collect();
+        assert_eq!(typed, [json!("/grid/one.txt\n/grid/two.txt")]);
+        assert!(input.upload_file(dir.join("missing.txt")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -15,6 +15,107 @@ pub (crate) fn check_tcp_port(port: Option<u16>) -> io::Result<u16> {
         .map(|x| x.port())
 }
 
+/// Whether `path` matches `template`, where a `{}` segment in the
+/// template matches any single path segment. A query string in `path` is
+/// ignored.
+pub (crate) fn path_matches(template: &str, path: &str) -> bool {
+    let path = path.split('?').next().unwrap_or("");
+    let mut template = template.trim_matches('/').split('/');
+    let mut path = path.trim_matches('/').split('/');
+    loop {
+        match (template.next(), path.next()) {
+            (None, None) => return true,
+            (Some(t), Some(p)) if t == "{}" || t == p => {}
+            _ => return false,
+        }
+    }
+}
+
+/// Whether the host of `url` is `localhost` or a loopback address.
+pub (crate) fn is_loopback_url(url: &str) -> bool {
+    match url::Url::parse(url).ok().as_ref().and_then(|url| url.host()) {
+        Some(url::Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
+/// Escape `value` for use as a CSS identifier, such as an id or class
+/// name in a selector. Follows the CSS Object Model specification.
+///
+/// # Example
+///
+/// ```
+/// # use webdriver_client::util::css_identifier;
+/// assert_eq!(css_identifier("2nd.item"), r"\32 nd\.item");
+/// ```
+pub fn css_identifier(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    let mut out = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        match c {
+            '\0' => out.push('\u{FFFD}'),
+            '\u{1}'..='\u{1f}' | '\u{7f}' => out.push_str(&format!("\\{:x} ", c as u32)),
+            '0'..='9' if i == 0 || (i == 1 && chars[0] == '-') =>
+                out.push_str(&format!("\\{:x} ", c as u32)),
+            '-' if chars.len() == 1 => out.push_str("\\-"),
+            c if c as u32 >= 0x80 || c == '-' || c == '_' || c.is_ascii_alphanumeric() => out.push(c),
+            c => {
+                out.push('\\');
+                out.push(c);
+            }
+        }
+    }
+    out
+}
+
+/// Quote `value` as a CSS string, for use in an attribute selector.
+///
+/// # Example
+///
+/// ```
+/// # use webdriver_client::util::css_string;
+/// assert_eq!(css_string(r#"a "b""#), r#""a \"b\"""#);
+/// ```
+pub fn css_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\0' => out.push('\u{FFFD}'),
+            '\u{1}'..='\u{1f}' | '\u{7f}' => out.push_str(&format!("\\{:x} ", c as u32)),
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Quote `value` as an XPath string literal. XPath 1.0 has no escapes,
+/// so a value containing both kinds of quote becomes a `concat()` call.
+///
+/// # Example
+///
+/// ```
+/// # use webdriver_client::util::xpath_literal;
+/// assert_eq!(xpath_literal("it's"), r#""it's""#);
+/// assert_eq!(xpath_literal(r#"it's "here""#), r#"concat('it', "'", 's "here"')"#);
+/// ```
+pub fn xpath_literal(value: &str) -> String {
+    if !value.contains('\'') {
+        format!("'{}'", value)
+    } else if !value.contains('"') {
+        format!("\"{}\"", value)
+    } else {
+        let parts: Vec<_> = value.split('\'').map(|part| format!("'{}'", part)).collect();
+        format!("concat({})", parts.join(", \"'\", "))
+    }
+}
+
 /// Recursively merge serde_json::Value's from a then b into a new
 /// returned value.
 ///
@@ -0,0 +1,171 @@
+//! Explicit waits: poll a condition until it holds or a timeout expires.
+//!
+//! Ready-made conditions live in the `conditions` module.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate webdriver_client;
+//! # use webdriver_client::{By, DriverSession, Error};
+//! # use webdriver_client::conditions;
+//! # use std::time::Duration;
+//! # fn example(sess: &DriverSession) -> Result<(), Error> {
+//! let button = sess.wait()
+//!                  .timeout(Duration::from_secs(5))
+//!                  .until(conditions::element_clickable(&By::css("#submit")))?;
+//! button.click()?;
+//! # Ok(())
+//! # }
+//! # fn main() {}
+//! ```
+
+use super::{DriverSession, Error};
+
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The timeout used by `DriverSession::wait()` unless overridden.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The poll interval used by `DriverSession::wait()` unless overridden.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Repeatedly checks a condition against a session.
+///
+/// Created by `DriverSession::wait()`.
+pub struct Wait<'a> {
+    session: &'a DriverSession,
+    timeout: Duration,
+    poll_interval: Duration,
+    message: Option<String>,
+}
+
+impl<'a> Wait<'a> {
+    pub fn new(session: &'a DriverSession) -> Self {
+        Wait {
+            session,
+            timeout: DEFAULT_TIMEOUT,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            message: None,
+        }
+    }
+
+    /// How long to keep polling before returning `Error::WaitTimeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How long to sleep between checks of the condition.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// A description of what is being waited for, included in the
+    /// timeout error.
+    pub fn message<S: Into<String>>(mut self, message: S) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Check `condition` until it returns `Ok(Some(value))`, then return
+    /// `value`.
+    ///
+    /// `Ok(None)` means the condition does not hold yet. Errors returned
+    /// by the condition stop the wait and are returned as is.
+    pub fn until<T, F>(self, mut condition: F) -> Result<T, Error>
+        where F: FnMut(&'a DriverSession) -> Result<Option<T>, Error>
+    {
+        let session = self.session;
+        match poll(self.timeout, self.poll_interval, || condition(session))? {
+            Some(value) => Ok(value),
+            None => Err(Error::WaitTimeout(
+                self.message.unwrap_or_else(|| "condition".to_owned()))),
+        }
+    }
+}
+
+/// Call `f` every `interval` until it returns `Ok(Some(_))` or an error,
+/// or until `timeout` has passed, in which case `Ok(None)` is returned.
+///
+/// `f` is always called at least once.
+pub(crate) fn poll<T, F>(timeout: Duration, interval: Duration, mut f: F)
+    -> Result<Option<T>, Error>
+    where F: FnMut() -> Result<Option<T>, Error>
+{
+    let start = Instant::now();
+    loop {
+        if let Some(value) = f()? {
+            return Ok(Some(value));
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Ok(None);
+        }
+        thread::sleep(interval.min(timeout - elapsed));
+    }
+}
+
+/// Like `poll`, awaiting `f` and sleeping on the tokio timer.
+#[cfg(feature = "async")]
+pub(crate) async fn poll_async<T, F, Fut>(timeout: Duration, interval: Duration, mut f: F)
+    -> Result<Option<T>, Error>
+    where F: FnMut() -> Fut,
+          Fut: Future<Output = Result<Option<T>, Error>>
+{
+    let start = Instant::now();
+    loop {
+        if let Some(value) = f().await? {
+            return Ok(Some(value));
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Ok(None);
+        }
+        tokio::time::sleep(interval.min(timeout - elapsed)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::poll;
+    use super::super::Error;
+    use std::time::Duration;
+
+    #[test]
+    fn poll_returns_first_value() {
+        let mut calls = 0;
+        let res = poll(Duration::from_secs(1), Duration::from_millis(1), || {
+            calls += 1;
+            Ok(if calls == 3 { Some(calls) } else { None })
+        });
+        assert_eq!(res.unwrap(), Some(3));
+    }
+
+    #[test]
+    fn poll_times_out() {
+        let mut calls = 0;
+        let res: Result<Option<()>, Error> =
+            poll(Duration::from_millis(20), Duration::from_millis(5), || {
+                calls += 1;
+                Ok(None)
+            });
+        assert_eq!(res.unwrap(), None);
+        assert!(calls > 1, "Want several calls, got {}", calls);
+    }
+
+    #[test]
+    fn poll_stops_on_error() {
+        let mut calls = 0;
+        let res: Result<Option<()>, Error> =
+            poll(Duration::from_secs(1), Duration::from_millis(1), || {
+                calls += 1;
+                Err(Error::ConnectionError)
+            });
+        assert!(res.is_err());
+        assert_eq!(calls, 1);
+    }
+}
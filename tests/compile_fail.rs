@@ -0,0 +1,10 @@
+//! Checks the compile errors reported by the selector macros and
+//! `#[derive(PageObject)]`. Run with `TRYBUILD=overwrite` to update the
+//! expected output in `tests/ui` after changing an error message.
+
+#[cfg(feature = "macros")]
+#[test]
+fn macro_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -13,7 +13,9 @@ use std::path::PathBuf;
 use std::sync::Once;
 use std::thread::sleep;
 use std::time::Duration;
-use webdriver_client::{Driver, DriverSession, HttpDriverBuilder, LocationStrategy};
+use webdriver_client::{By, Driver, DriverSession, HttpDriverBuilder};
+use webdriver_client::conditions;
+use webdriver_client::relative::RelativeLocator;
 use webdriver_client::firefox::GeckoDriver;
 use webdriver_client::chrome::ChromeDriver;
 use webdriver_client::messages::{ExecuteCmd, NewSessionCmd};
@@ -136,10 +138,10 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let element = sess.find_element("span.red", LocationStrategy::Css).expect("Error finding element");
+                let element = sess.find_element(&By::css("span.red")).expect("Error finding element");
                 assert_eq!(element.text().expect("Error getting text"), "Red text", "Wrong element found");
 
-                sess.find_element("body.red", LocationStrategy::Css).expect_err("Want error");
+                sess.find_element(&By::css("body.red")).expect_err("Want error");
             }
 
             #[test]
@@ -147,10 +149,10 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let element = sess.find_element("A really handy WebDriver crate", LocationStrategy::LinkText).expect("Error finding element");
+                let element = sess.find_element(&By::link_text("A really handy WebDriver crate")).expect("Error finding element");
                 assert_eq!(element.text().expect("Error getting text"), "A really handy WebDriver crate", "Wrong element found");
 
-                sess.find_element("A link with this text does not appear on the page", LocationStrategy::LinkText).expect_err("Want error");
+                sess.find_element(&By::link_text("A link with this text does not appear on the page")).expect_err("Want error");
             }
 
             #[test]
@@ -158,10 +160,10 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let element = sess.find_element("crate", LocationStrategy::PartialLinkText).expect("Error finding element");
+                let element = sess.find_element(&By::partial_link_text("crate")).expect("Error finding element");
                 assert_eq!(element.text().expect("Error getting text"), "A really handy WebDriver crate", "Wrong element found");
 
-                sess.find_element("A link with this text does not appear on the page", LocationStrategy::PartialLinkText).expect_err("Want error");
+                sess.find_element(&By::partial_link_text("A link with this text does not appear on the page")).expect_err("Want error");
             }
 
             #[test]
@@ -169,10 +171,10 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let element = sess.find_element("//a", LocationStrategy::XPath).expect("Error finding element");
+                let element = sess.find_element(&By::xpath("//a")).expect("Error finding element");
                 assert_eq!(element.text().expect("Error getting text"), "A really handy WebDriver crate", "Wrong element found");
 
-                sess.find_element("//video", LocationStrategy::XPath).expect_err("Want error");
+                sess.find_element(&By::xpath("//video")).expect_err("Want error");
             }
 
             #[test]
@@ -180,11 +182,11 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let elements = sess.find_elements("span.red", LocationStrategy::Css).expect("Error finding elements");
+                let elements = sess.find_elements(&By::css("span.red")).expect("Error finding elements");
                 let element_texts: Vec<String> = elements.into_iter().map(|elem| elem.text().expect("Error getting text")).collect();
                 assert_eq!(element_texts, vec!["Red text".to_owned(), "More red text".to_owned()], "Wrong element texts");
 
-                let found_elements = sess.find_elements("body.red", LocationStrategy::Css).expect("Error finding absent elements");
+                let found_elements = sess.find_elements(&By::css("body.red")).expect("Error finding absent elements");
                 assert!(found_elements.is_empty(), "Want to find no elements, found {:?}", found_elements);
             }
 
@@ -193,11 +195,11 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let elements = sess.find_elements("A really handy WebDriver crate", LocationStrategy::LinkText).expect("Error finding elements");
+                let elements = sess.find_elements(&By::link_text("A really handy WebDriver crate")).expect("Error finding elements");
                 let element_texts: Vec<String> = elements.into_iter().map(|elem| elem.text().expect("Error getting text")).collect();
                 assert_eq!(element_texts, vec!["A really handy WebDriver crate".to_owned()], "Wrong element texts");
 
-                let found_elements = sess.find_elements("A really bad WebDriver crate", LocationStrategy::LinkText).expect("Error finding absent elements");
+                let found_elements = sess.find_elements(&By::link_text("A really bad WebDriver crate")).expect("Error finding absent elements");
                 assert!(found_elements.is_empty(), "Want to find no elements, found {:?}", found_elements);
             }
 
@@ -206,11 +208,11 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let elements = sess.find_elements("crate", LocationStrategy::PartialLinkText).expect("Error finding elements");
+                let elements = sess.find_elements(&By::partial_link_text("crate")).expect("Error finding elements");
                 let element_texts: Vec<String> = elements.into_iter().map(|elem| elem.text().expect("Error getting text")).collect();
                 assert_eq!(element_texts, vec!["A really handy WebDriver crate".to_owned(), "A WebDriver crate with just the server-side".to_owned()], "Wrong element texts");
 
-                let found_elements = sess.find_elements("A really bad WebDriver crate", LocationStrategy::PartialLinkText).expect("Error finding absent elements");
+                let found_elements = sess.find_elements(&By::partial_link_text("A really bad WebDriver crate")).expect("Error finding absent elements");
                 assert!(found_elements.is_empty(), "Want to find no elements, found {:?}", found_elements);
             }
 
@@ -219,14 +221,50 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let elements = sess.find_elements("//body/span", LocationStrategy::XPath).expect("Error finding elements");
+                let elements = sess.find_elements(&By::xpath("//body/span")).expect("Error finding elements");
                 let element_texts: Vec<String> = elements.into_iter().map(|elem| elem.text().expect("Error getting text")).collect();
                 assert_eq!(element_texts, vec!["Red text".to_owned(), "More red text".to_owned()], "Wrong element texts");
 
-                let found_elements = sess.find_elements("//video", LocationStrategy::XPath).expect("Error finding absent elements");
+                let found_elements = sess.find_elements(&By::xpath("//video")).expect("Error finding absent elements");
                 assert!(found_elements.is_empty(), "Want to find no elements, found {:?}", found_elements);
             }
 
+            #[test]
+            fn find_element_by_convenience_strategies() {
+                let (server, sess) = setup();
+                let page1 = server.url("/page1.html");
+                sess.go(&page1).expect("Error going to page1");
+                let text = |by| {
+                    sess.find_element(&by).expect("Error finding element").text().unwrap()
+                };
+                assert_eq!(text(By::id("child1")), "Inner");
+                assert_eq!(text(By::class_name("red")), "Red text");
+                assert_eq!(text(By::tag_name("span")), "Red text");
+                assert_eq!(text(By::exact_text("Page 2")), "Page 2");
+                assert_eq!(text(By::contains_text("handy")), "A really handy WebDriver crate");
+                sess.find_element(&By::exact_text("Page")).expect_err("Want error");
+            }
+
+            #[test]
+            fn find_element_relative() {
+                let (server, sess) = setup();
+                sess.go(&server.url("/relative.html")).expect("Error going to relative.html");
+                let cell = |id| sess.find_element(&By::id(id)).unwrap();
+                let ids = |locator: RelativeLocator| -> Vec<String> {
+                    sess.find_elements_relative(&locator).expect("Error finding elements").iter()
+                        .map(|e| e.attribute("id").unwrap()).collect()
+                };
+                let cells = || RelativeLocator::new(By::class_name("cell"));
+
+                assert_eq!(ids(cells().above(&cell("centre"))), vec!["top-left", "top-right"]);
+                assert_eq!(ids(cells().below(&cell("centre")).left_of(&cell("centre"))), vec!["bottom-left"]);
+                assert_eq!(ids(cells().right_of(&cell("centre")).below(&cell("top-right"))),
+                           vec!["neighbour", "bottom-right"]);
+                assert_eq!(ids(cells().near(&cell("centre"))), vec!["neighbour"]);
+                let nearest = sess.find_element_relative(&cells().below(&cell("top-left"))).unwrap();
+                assert_eq!(nearest.attribute("id").unwrap(), "centre");
+            }
+
             #[test]
             fn element_attribute_and_property() {
                 let (server, sess) = setup();
@@ -234,7 +272,7 @@ macro_rules! browser_tests {
                 let page2 = server.url("/page2.html");
 
                 sess.go(&page1).expect("Error going to page1");
-                let link = sess.find_element("#link_to_page_2", LocationStrategy::Css).expect("Error finding element");
+                let link = sess.find_element(&By::css("#link_to_page_2")).expect("Error finding element");
 
                 assert_eq!(&link.attribute("href").expect("Error getting attribute"),
                            "/page2.html");
@@ -251,7 +289,7 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let element = sess.find_element("span.red", LocationStrategy::Css).expect("Error finding element");
+                let element = sess.find_element(&By::css("span.red")).expect("Error finding element");
                 if sess.browser_name() == Some("chrome") {
                     assert_eq!(&element.css_value("color").expect("Error getting css value"), "rgba(255, 0, 0, 1)");
                 } else {
@@ -264,10 +302,10 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let output = sess.find_element("#set-text-output", LocationStrategy::Css)
+                let output = sess.find_element(&By::css("#set-text-output"))
                                  .expect("Finding output element");
                 assert_eq!(&output.text().expect("Getting output text"), "Unset");
-                let button = sess.find_element("#set-text-btn", LocationStrategy::Css)
+                let button = sess.find_element(&By::css("#set-text-btn"))
                                  .expect("Finding button element");
                 button.click().expect("Click button");
                 assert_eq!(&output.text().expect("Getting output text"), "Set");
@@ -278,7 +316,7 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let element = sess.find_element("#textfield", LocationStrategy::Css).expect("Error finding element");
+                let element = sess.find_element(&By::css("#textfield")).expect("Error finding element");
                 assert_eq!(&element.property("value").expect("Error getting value [1]"), "Pre-filled");
                 element.clear().expect("Error clearing element");
                 assert_eq!(&element.property("value").expect("Error getting value [2]"), "");
@@ -289,7 +327,7 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let element = sess.find_element("#textfield", LocationStrategy::Css).expect("Error finding element");
+                let element = sess.find_element(&By::css("#textfield")).expect("Error finding element");
                 assert_eq!(&element.property("value").expect("Error getting value [1]"),
                            "Pre-filled");
                 element.send_keys(" hello").expect("Error sending keys to element");
@@ -301,7 +339,7 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let element = sess.find_element("span.red", LocationStrategy::Css).expect("Error finding element");
+                let element = sess.find_element(&By::css("span.red")).expect("Error finding element");
                 assert_eq!(&element.text().expect("Error getting text"), "Red text");
             }
 
@@ -310,7 +348,7 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let element = sess.find_element("span.red", LocationStrategy::Css).expect("Error finding element");
+                let element = sess.find_element(&By::css("span.red")).expect("Error finding element");
                 assert_eq!(&element.name().expect("Error getting name"), "span");
             }
 
@@ -319,32 +357,341 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let element = sess.find_element("#parent", LocationStrategy::Css).expect("Error finding parent element");
+                let element = sess.find_element(&By::css("#parent")).expect("Error finding parent element");
 
-                let child_by_css = element.find_element("span", LocationStrategy::Css).expect("Error finding child by CSS");
+                let child_by_css = element.find_element(&By::css("span")).expect("Error finding child by CSS");
                 assert_eq!(&child_by_css.attribute("id").expect("Error getting id [1]"), "child1");
 
-                let child_by_xpath = element.find_element(".//span", LocationStrategy::XPath).expect("Error finding child by XPath");
+                let child_by_xpath = element.find_element(&By::xpath(".//span")).expect("Error finding child by XPath");
                 assert_eq!(&child_by_xpath.attribute("id").expect("Error getting id [2]"), "child1");
             }
 
+            #[test]
+            fn find_element_within_and_nth() {
+                let (server, sess) = setup();
+                let page1 = server.url("/page1.html");
+                sess.go(&page1).expect("Error going to page1");
+                let second = sess.find_element(&By::tag_name("span").within(By::id("parent")).nth(1))
+                                 .expect("Error finding element");
+                assert_eq!(second.attribute("id").unwrap(), "child2");
+
+                match sess.find_element(&By::css("video").within(By::id("parent"))) {
+                    Err(webdriver_client::Error::WebDriverError(err)) => {
+                        assert_eq!(err.error, "no such element");
+                        assert!(err.message.starts_with(r#"No element found for css "video" within id "parent""#),
+                                "Bad error message: {}", err.message);
+                    }
+                    other => panic!("Want no such element, got {:?}", other),
+                }
+            }
+
+            #[cfg(feature = "macros")]
+            #[test]
+            fn page_objects() {
+                use webdriver_client::Element;
+                use webdriver_client::page::{Lazy, PageObject, SearchContext};
+
+                #[derive(PageObject)]
+                struct Parent {
+                    #[find(tag_name = "span")] spans: Lazy<Vec<Element>>,
+                    #[find(css = "span", nth = 1)] second: Lazy<Element>,
+                }
+
+                #[derive(PageObject)]
+                #[page(url = "/page1.html")]
+                struct Page1 {
+                    #[root] root: SearchContext,
+                    #[find(id = "parent")] parent: Lazy<Parent>,
+                    #[find(xpath = "//a[@id = 'link_to_page_2']")] link: Lazy<Element>,
+                }
+
+                let (server, sess) = setup();
+                // The page URL is relative, so start on the same site.
+                sess.go(&server.url("/page2.html")).expect("Error going to page2");
+                let page = Page1::open(&sess).expect("Error opening page1");
+                assert!(page.is_current().unwrap());
+                let parent = page.parent.get().expect("Error finding parent");
+                assert_eq!(parent.spans.get().unwrap().len(), 2);
+                assert_eq!(parent.second.get().unwrap().attribute("id").unwrap(), "child2");
+
+                page.link.get().expect("Error finding link").click().unwrap();
+                assert!(!page.is_current().unwrap());
+            }
+
+            #[cfg(feature = "macros")]
+            #[test]
+            fn extract() {
+                use webdriver_client::extract::Extract;
+
+                #[derive(Debug, PartialEq, Extract)]
+                struct Review {
+                    #[extract(attr = "data-id")] id: u32,
+                    #[extract(css = ".stars", attr = "data-stars")] stars: u8,
+                    #[extract(css = "p", html)] html: String,
+                }
+
+                #[derive(Debug, PartialEq, Extract)]
+                struct Product {
+                    #[extract(css = "h1")] name: String,
+                    #[extract(css = ".price")] price: f64,
+                    #[extract(tag_name = "time", attr = "datetime")] date: String,
+                    #[extract(css = "li.tag")] tags: Vec<String>,
+                    #[extract(xpath = ".//div[@class = 'review']")] reviews: Vec<Review>,
+                    #[extract(id = "quantity", property = "value")] quantity: u32,
+                    #[extract(css = "a.more", attr = "href")] more: Option<String>,
+                }
+
+                let (server, sess) = setup();
+                sess.go(&server.url("/extract.html")).expect("Error going to extract.html");
+                let product: Product = sess.extract().expect("Error extracting product");
+                assert_eq!(product, Product {
+                    name: "Widget".to_owned(),
+                    price: 12.5,
+                    date: "2024-02-29".to_owned(),
+                    tags: vec!["blue".to_owned(), "small".to_owned()],
+                    reviews: vec![
+                        Review { id: 1, stars: 5, html: "Works well.".to_owned() },
+                        Review { id: 2, stars: 2, html: "Too <b>small</b>.".to_owned() },
+                    ],
+                    quantity: 3,
+                    more: None,
+                });
+
+                let element = sess.find_element(&By::id("product")).unwrap();
+                let within: Product = element.extract().expect("Error extracting within element");
+                assert_eq!(within, product);
+            }
+
+            #[test]
+            fn snapshots() {
+                use webdriver_client::snapshot::Field;
+
+                let (server, sess) = setup();
+                sess.go(&server.url("/page1.html")).expect("Error going to page1");
+                let fields = [Field::Text, Field::TagName, Field::Attribute("id".to_owned()),
+                              Field::Displayed, Field::Rect];
+                let spans = sess.snapshot_all(&By::css("span").within(By::id("parent")), &fields)
+                                .expect("Error taking snapshots");
+                let ids: Vec<_> = spans.iter().map(|s| s.attribute("id").unwrap()).collect();
+                assert_eq!(ids, vec!["child1", "child2"]);
+                assert_eq!(spans[0].text(), Some("Inner"));
+                assert_eq!(spans[0].tag_name(), Some("span"));
+                assert_eq!(spans[0].is_displayed(), Some(true));
+                assert!(spans[0].rect().unwrap().width > 0.0);
+                assert_eq!(spans[1].element().text().unwrap(), "Other inner");
+
+                let input = sess.find_element(&By::id("textfield")).unwrap();
+                let snapshot = input.snapshot(&[Field::Property("value".to_owned()), Field::Enabled])
+                                    .expect("Error taking snapshot");
+                assert_eq!(snapshot.property("value"), Some(&json!("Pre-filled")));
+                assert_eq!(snapshot.is_enabled(), Some(true));
+                assert_eq!(snapshot.text(), None);
+            }
+
+            #[test]
+            fn tables() {
+                let (server, sess) = setup();
+                sess.go(&server.url("/table.html")).expect("Error going to table.html");
+                let table = sess.find_element(&By::id("stock")).unwrap().table()
+                                .expect("Error reading table");
+                assert_eq!(table.headers(), ["Name", "Stock / Shelf", "Stock / Store"]);
+                assert_eq!(table.rows(), [
+                    ["Widget", "3", "12"],
+                    ["Gadget, large", "0", "12"],
+                    ["Discontinued", "Discontinued", "Discontinued"],
+                ]);
+                assert_eq!(table.column("Stock / Store"), Some(vec!["12", "12", "Discontinued"]));
+                assert_eq!(table.find_row("Name", "Widget").unwrap()[1], "3");
+                assert_eq!(table.to_csv(), "Name,Stock / Shelf,Stock / Store\n\
+                                            Widget,3,12\n\
+                                            \"Gadget, large\",0,12\n\
+                                            Discontinued,Discontinued,Discontinued\n");
+
+                let plain = sess.find_element(&By::id("plain")).unwrap().table().unwrap();
+                assert_eq!(plain.headers(), ["Key", "Value"]);
+                assert_eq!(plain.get(0, "Value"), Some("blue"));
+
+                let not_table = sess.find_element(&By::id("not-a-table")).unwrap();
+                assert!(not_table.table().is_err());
+            }
+
+            #[test]
+            fn select() {
+                use webdriver_client::select::Select;
+
+                let (server, sess) = setup();
+                sess.go(&server.url("/select.html")).expect("Error going to select.html");
+                let values = |options: Vec<webdriver_client::Element>| -> Vec<String> {
+                    options.iter().map(|o| o.attribute("value").unwrap()).collect()
+                };
+
+                let single = Select::new(sess.find_element(&By::id("single")).unwrap()).unwrap();
+                assert!(!single.is_multiple().unwrap());
+                assert_eq!(values(single.options().unwrap()), ["nz", "uk", "us"]);
+                assert_eq!(values(single.selected_options().unwrap()), ["uk"]);
+                single.select_by_visible_text("United States").unwrap();
+                assert_eq!(values(single.selected_options().unwrap()), ["us"]);
+                single.select_by_value("nz").unwrap();
+                assert_eq!(single.first_selected_option().unwrap().text().unwrap(), "New Zealand");
+                assert!(single.select_by_value("fr").is_err());
+                assert!(single.deselect_all().is_err());
+
+                let multiple = Select::new(sess.find_element(&By::id("multiple")).unwrap()).unwrap();
+                assert!(multiple.is_multiple().unwrap());
+                multiple.select_by_index(1).unwrap();
+                assert_eq!(values(multiple.selected_options().unwrap()), ["red", "green", "blue"]);
+                multiple.deselect_by_value("red").unwrap();
+                multiple.deselect_by_visible_text("Blue").unwrap();
+                assert_eq!(values(multiple.selected_options().unwrap()), ["green"]);
+                multiple.deselect_all().unwrap();
+                assert!(multiple.selected_options().unwrap().is_empty());
+
+                assert!(Select::new(sess.find_element(&By::id("not-a-select")).unwrap()).is_err());
+            }
+
+            #[test]
+            fn forms() {
+                use std::collections::{BTreeMap, HashMap};
+                use webdriver_client::JsonValue;
+                use webdriver_client::form::Form;
+
+                #[derive(serde_derive::Serialize)]
+                struct Signup {
+                    name: &'static str,
+                    bio: &'static str,
+                    agree: bool,
+                    topics: Vec<&'static str>,
+                    plan: &'static str,
+                    country: &'static str,
+                    languages: Vec<&'static str>,
+                    avatar: Option<String>,
+                }
+
+                let (server, sess) = setup();
+                sess.go(&server.url("/form.html")).expect("Error going to form.html");
+                let form = Form::new(sess.find_element(&By::id("signup")).unwrap()).unwrap();
+                let avatar = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/www/favicon.ico");
+                form.fill(&Signup {
+                    name: "Alice",
+                    bio: "Hello",
+                    agree: true,
+                    topics: vec!["web", "music"],
+                    plan: "pro",
+                    country: "uk",
+                    languages: vec!["fr", "mi"],
+                    avatar: Some(avatar.to_str().unwrap().to_owned()),
+                }).expect("Error filling form");
+                let mut by_id = HashMap::new();
+                by_id.insert("email", "alice@example.com");
+                form.fill(&by_id).expect("Error filling email");
+                // Outside the <form>, associated by its form attribute.
+                form.set("referrer", &"friend").expect("Error filling referrer");
+
+                let values: BTreeMap<String, JsonValue> = form.values().expect("Error reading form");
+                assert_eq!(values["name"], "Alice");
+                assert_eq!(values["bio"], "Hello");
+                assert_eq!(values["agree"], true);
+                assert_eq!(values["topics"], json!(["web", "music"]));
+                assert_eq!(values["plan"], "pro");
+                assert_eq!(values["country"], "uk");
+                assert_eq!(values["languages"], json!(["fr", "mi"]));
+                assert_eq!(values["avatar"], json!(["favicon.ico"]));
+                assert_eq!(sess.find_element(&By::id("email")).unwrap().property("value").unwrap(),
+                           "alice@example.com");
+                assert_eq!(values["referrer"], "friend");
+                assert!(form.set("missing", &"x").is_err());
+
+                form.set("languages", &"en").expect("Error selecting one language");
+                let values: BTreeMap<String, JsonValue> = form.values().expect("Error reading form");
+                assert_eq!(values["languages"], json!(["en"]));
+
+                form.submit().expect("Error submitting form");
+                sess.wait().until(conditions::url_contains("page2.html"))
+                    .expect("Form was not submitted");
+                assert!(sess.get_current_url().unwrap().contains("name=Alice"));
+            }
+
+            #[test]
+            fn upload_files() {
+                let (server, sess) = setup();
+                sess.go(&server.url("/form.html")).expect("Error going to form.html");
+                assert!(!sess.is_remote());
+                let www = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/www");
+
+                let avatar = sess.find_element(&By::name("avatar")).unwrap();
+                avatar.upload_file(www.join("favicon.ico")).expect("Error uploading file");
+                assert!(avatar.property("value").unwrap().ends_with("favicon.ico"));
+
+                let attachments = sess.find_element(&By::name("attachments")).unwrap();
+                attachments.upload_files(&[www.join("page1.html"), www.join("page2.html")])
+                           .expect("Error uploading files");
+                let names: Vec<String> = sess.execute_typed(
+                    "return Array.prototype.map.call(arguments[0].files, function (f) { return f.name; });",
+                    (&attachments,)).unwrap();
+                assert_eq!(names, ["page1.html", "page2.html"]);
+
+                assert!(avatar.upload_file(www.join("missing.txt")).is_err());
+            }
+
             #[test]
             fn element_children() {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let element = sess.find_element("#parent", LocationStrategy::Css).expect("Error finding parent element");
+                let element = sess.find_element(&By::css("#parent")).expect("Error finding parent element");
 
-                let children = element.find_elements("span", LocationStrategy::Css).expect("Error finding children by CSS");
+                let children = element.find_elements(&By::css("span")).expect("Error finding children by CSS");
                 assert_eq!(children.iter().map(|e| e.attribute("id").expect("Error getting id")).collect::<Vec<_>>(), vec!["child1".to_owned(), "child2".to_owned()]);
             }
 
+            #[test]
+            fn element_outlives_session_handle() {
+                let (server, sess) = setup();
+                let page1 = server.url("/page1.html");
+                sess.go(&page1).expect("Error going to page1");
+
+                struct Page {
+                    red: webdriver_client::Element,
+                }
+                let page = Page {
+                    red: sess.find_element(&By::css("span.red")).expect("Error finding element"),
+                };
+                // The element keeps the session alive.
+                drop(sess);
+                assert_eq!(&page.red.text().expect("Error getting text"), "Red text");
+                assert_eq!(&page.red.session().get_current_url().expect("Error getting url"), &page1);
+            }
+
+            #[test]
+            fn commands_from_several_threads() {
+                let (server, sess) = setup();
+                let page1 = server.url("/page1.html");
+                sess.go(&page1).expect("Error going to page1");
+                let element = sess.find_element(&By::css("span.red")).expect("Error finding element");
+
+                let threads: Vec<_> = (0..4).map(|i| {
+                    let sess = sess.clone();
+                    let element = element.clone();
+                    let page1 = page1.clone();
+                    std::thread::spawn(move || {
+                        for _ in 0..5 {
+                            assert_eq!(&sess.get_current_url().expect("Error getting url"), &page1, "Wrong URL in thread {}", i);
+                            assert_eq!(&element.text().expect("Error getting text"), "Red text", "Wrong text in thread {}", i);
+                        }
+                    })
+                }).collect();
+                for t in threads {
+                    t.join().expect("Thread panicked");
+                }
+                assert_eq!(&sess.get_title().expect("Error getting title"), "Test page 1 title");
+            }
+
             #[test]
             fn refresh() {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let elem = sess.find_element("#textfield", LocationStrategy::Css).expect("Error finding element [1]");
+                let elem = sess.find_element(&By::css("#textfield")).expect("Error finding element [1]");
                 assert_eq!(elem.property("value").expect("Error getting value [1]"), "Pre-filled".to_owned());
 
                 elem.clear().expect("Error clearing");
@@ -352,7 +699,7 @@ macro_rules! browser_tests {
 
                 sess.refresh().expect("Error refreshing");
                 elem.text().expect_err("Want stale element error");
-                let elem2 = sess.find_element("#textfield", LocationStrategy::Css).expect("Error finding element [1]");
+                let elem2 = sess.find_element(&By::css("#textfield")).expect("Error finding element [1]");
                 assert_eq!(elem2.property("value").expect("Error getting value [2]"), "Pre-filled".to_owned());
             }
 
@@ -382,6 +729,55 @@ macro_rules! browser_tests {
 
             }
 
+            #[test]
+            fn execute_value() {
+                let (server, sess) = setup();
+                let page1 = server.url("/page1.html");
+                sess.go(&page1).expect("Error going to page1");
+                let value = sess.execute_value(ExecuteCmd {
+                    script: "return { spans: document.querySelectorAll('#parent span'), n: 2 };".to_owned(),
+                    args: vec![],
+                }).expect("Error executing script");
+                let map = value.as_object().unwrap();
+                assert_eq!(map["n"].as_i64(), Some(2));
+                let spans = map["spans"].clone().into_elements().expect("Want elements");
+                let texts: Vec<_> = spans.iter().map(|e| e.text().unwrap()).collect();
+                assert_eq!(texts, vec!["Inner", "Other inner"]);
+            }
+
+            #[test]
+            fn execute_typed() {
+                let (server, sess) = setup();
+                let page1 = server.url("/page1.html");
+                sess.go(&page1).expect("Error going to page1");
+                let field = sess.find_element(&By::css("#textfield")).unwrap();
+                let value: String = sess.execute_typed(
+                    "return arguments[0].value + arguments[1] + arguments[2].length;",
+                    (&field, " ", vec![1, 2, 3])).expect("Error executing script");
+                assert_eq!(value, "Pre-filled 3");
+
+                let sum: i64 = sess.execute_async_typed(
+                    "arguments[arguments.length - 1](arguments[0] + arguments[1]);",
+                    (1, 2)).expect("Error executing async script");
+                assert_eq!(sum, 3);
+            }
+
+            #[test]
+            fn evaluate_promise() {
+                let (server, sess) = setup();
+                let page1 = server.url("/page1.html");
+                sess.go(&page1).expect("Error going to page1");
+                let sum: i64 = sess.evaluate_promise(
+                    "new Promise(resolve => setTimeout(() => resolve(arguments[0] + arguments[1]), 100))",
+                    (1, 2)).expect("Error evaluating promise");
+                assert_eq!(sum, 3);
+
+                match sess.evaluate_promise::<_, i64>("Promise.reject(new Error('nope'))", ()) {
+                    Err(webdriver_client::Error::ScriptRejected { message, .. }) => assert_eq!(message, "nope"),
+                    other => panic!("Want ScriptRejected, got {:?}", other),
+                }
+            }
+
             #[test]
             fn browser_name() {
                 let sess = test_browser().session();
@@ -404,9 +800,8 @@ macro_rules! browser_tests {
                 assert_eq!(exec_int, 1);
             }
 
-            // TODO: Test cookies
-
-            // TODO: Test window handles
+            // Cookies and window handles are tested against MockServer in
+            // tests/mock.rs.
 
             #[test]
             fn frame_switch() {
@@ -417,22 +812,22 @@ macro_rules! browser_tests {
                 // switching to parent from parent is harmless
                 sess.switch_to_parent_frame().unwrap();
 
-                let frames = sess.find_elements("iframe", LocationStrategy::Css).unwrap();
+                let frames = sess.find_elements(&By::css("iframe")).unwrap();
                 assert_eq!(frames.len(), 1);
 
                 sess.switch_to_frame(frames[0].reference().unwrap()).unwrap();
-                let frames = sess.find_elements("iframe", LocationStrategy::Css).unwrap();
+                let frames = sess.find_elements(&By::css("iframe")).unwrap();
                 assert_eq!(frames.len(), 2);
 
                 for f in &frames {
                     sess.switch_to_frame(f.reference().unwrap()).unwrap();
-                    let childframes = sess.find_elements("iframe", LocationStrategy::Css).unwrap();
+                    let childframes = sess.find_elements(&By::css("iframe")).unwrap();
                     assert_eq!(childframes.len(), 0);
                     sess.switch_to_parent_frame().unwrap();
                 }
 
                 sess.switch_to_parent_frame().unwrap();
-                let frames = sess.find_elements("iframe", LocationStrategy::Css).unwrap();
+                let frames = sess.find_elements(&By::css("iframe")).unwrap();
                 assert_eq!(frames.len(), 1);
             }
 
@@ -463,6 +858,36 @@ macro_rules! browser_tests {
                 assert_eq!(url, test_url);
             }
 
+            #[cfg(feature = "async")]
+            #[test]
+            fn async_session() {
+                use webdriver_client::AsyncDriverSession;
+
+                ensure_logging_init();
+                let server = FileServer::new();
+                let page1 = server.url("/page1.html");
+                let runtime = tokio::runtime::Runtime::new().expect("Error creating runtime");
+                runtime.block_on(async {
+                    let sess = AsyncDriverSession::create_session(test_browser().driver(),
+                                                                  &test_browser().new_session_cmd())
+                        .await.expect("Error starting session");
+                    sess.go(&page1).await.expect("Error going to page1");
+                    assert_eq!(&sess.get_title().await.expect("Error getting title"), "Test page 1 title");
+
+                    let parent = sess.find_element(&By::css("#parent")).await
+                                     .expect("Error finding parent element");
+                    let children = parent.find_elements(&By::css("span")).await
+                                         .expect("Error finding children");
+                    let mut ids = Vec::new();
+                    for child in &children {
+                        ids.push(child.attribute("id").await.expect("Error getting id"));
+                    }
+                    assert_eq!(ids, vec!["child1".to_owned(), "child2".to_owned()]);
+
+                    sess.delete().await.expect("Error deleting session");
+                });
+            }
+
             #[test]
             fn screenshot_frame() {
                 let (server, sess) = setup();
@@ -480,7 +905,7 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let ss = sess.find_element("#parent", LocationStrategy::Css).expect("element")
+                let ss = sess.find_element(&By::css("#parent")).expect("element")
                              .screenshot().expect("Screenshot");
                 std::fs::create_dir_all("target/screenshots").expect("Create screenshot dir");
                 ss.save_file(&format!("target/screenshots/{:?}_element.png",
@@ -493,7 +918,7 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let btn = sess.find_element("#alert-btn", LocationStrategy::Css).expect("btn");
+                let btn = sess.find_element(&By::css("#alert-btn")).expect("btn");
                 btn.click().expect("click");
                 sess.dismiss_alert().expect("dismiss alert");
             }
@@ -503,11 +928,11 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let btn = sess.find_element("#confirm-btn", LocationStrategy::Css)
+                let btn = sess.find_element(&By::css("#confirm-btn"))
                               .expect("find btn");
                 btn.click().expect("click");
                 sess.accept_alert().expect("accept alert");
-                let out = sess.find_element("#alerts-out", LocationStrategy::Css)
+                let out = sess.find_element(&By::css("#alerts-out"))
                               .expect("find output");
                 assert_eq!("true", out.text().expect("output text"));
             }
@@ -517,11 +942,11 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let btn = sess.find_element("#confirm-btn", LocationStrategy::Css)
+                let btn = sess.find_element(&By::css("#confirm-btn"))
                               .expect("find btn");
                 btn.click().expect("click");
                 sess.dismiss_alert().expect("accept alert");
-                let out = sess.find_element("#alerts-out", LocationStrategy::Css)
+                let out = sess.find_element(&By::css("#alerts-out"))
                               .expect("find output");
                 assert_eq!("false", out.text().expect("output text"));
             }
@@ -531,7 +956,7 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let btn = sess.find_element("#alert-btn", LocationStrategy::Css)
+                let btn = sess.find_element(&By::css("#alert-btn"))
                               .expect("find btn");
                 btn.click().expect("click");
                 assert_eq!("Alert", sess.get_alert_text().expect("get_alert_text"));
@@ -543,16 +968,117 @@ macro_rules! browser_tests {
                 let (server, sess) = setup();
                 let page1 = server.url("/page1.html");
                 sess.go(&page1).expect("Error going to page1");
-                let btn = sess.find_element("#prompt-btn", LocationStrategy::Css)
+                let btn = sess.find_element(&By::css("#prompt-btn"))
                               .expect("find btn");
                 btn.click().expect("click");
                 sess.send_alert_text("foobar").expect("send_alert_text");
                 sess.accept_alert().expect("accept alert");
-                let out = sess.find_element("#alerts-out", LocationStrategy::Css)
+                let out = sess.find_element(&By::css("#alerts-out"))
                               .expect("find output");
                 assert_eq!("foobar", out.text().expect("output text"));
             }
 
+            #[test]
+            fn wait_for_element() {
+                let (server, sess) = setup();
+                let page = server.url("/wait.html");
+                sess.go(&page).expect("Error going to wait page");
+                sess.find_element(&By::css("#added")).expect_err("Want error before waiting");
+
+                sess.find_element(&By::css("#start-btn")).expect("find btn")
+                    .click().expect("click");
+                let added = sess.wait()
+                                .until(conditions::element_visible(&By::css("#added")))
+                                .expect("Error waiting for element");
+                assert_eq!(&added.text().expect("Error getting text"), "Added later");
+            }
+
+            #[test]
+            fn wait_for_text_title_and_invisibility() {
+                let (server, sess) = setup();
+                let page = server.url("/wait.html");
+                sess.go(&page).expect("Error going to wait page");
+                sess.find_element(&By::css("#start-btn")).expect("find btn")
+                    .click().expect("click");
+
+                sess.wait().until(conditions::text_present(&By::css("#replaced"), "Replacement"))
+                    .expect("Error waiting for text");
+                sess.wait().until(conditions::title_is("Waited"))
+                    .expect("Error waiting for title");
+                sess.wait().until(conditions::element_invisible(&By::css("#hidden-later")))
+                    .expect("Error waiting for invisibility");
+                sess.wait().until(conditions::url_contains("wait.html"))
+                    .expect("Error waiting for url");
+            }
+
+            #[test]
+            fn wait_for_staleness() {
+                let (server, sess) = setup();
+                let page = server.url("/wait.html");
+                sess.go(&page).expect("Error going to wait page");
+                let original = sess.find_element(&By::css("#replaced")).expect("find original");
+                sess.find_element(&By::css("#start-btn")).expect("find btn")
+                    .click().expect("click");
+                sess.wait().until(conditions::staleness_of(&original))
+                    .expect("Error waiting for staleness");
+            }
+
+            #[test]
+            fn wait_for_alert_and_frame() {
+                let (server, sess) = setup();
+                let page1 = server.url("/page1.html");
+                sess.go(&page1).expect("Error going to page1");
+                sess.find_element(&By::css("#alert-btn")).expect("btn")
+                    .click().expect("click");
+                let text = sess.wait().until(conditions::alert_present()).expect("Error waiting for alert");
+                assert_eq!(&text, "Alert");
+                sess.dismiss_alert().expect("dismiss alert");
+
+                let page3 = server.url("/page3.html");
+                sess.go(&page3).expect("Error going to page3");
+                sess.wait().until(conditions::number_of_windows_is(1)).expect("Error waiting for windows");
+                sess.wait().until(conditions::frame_available_and_switch(&By::css("iframe")))
+                    .expect("Error waiting for frame");
+                let frames = sess.find_elements(&By::css("iframe")).unwrap();
+                assert_eq!(frames.len(), 2);
+            }
+
+            #[test]
+            fn wait_timeout() {
+                let (server, sess) = setup();
+                let page1 = server.url("/page1.html");
+                sess.go(&page1).expect("Error going to page1");
+                let err = sess.wait()
+                              .timeout(Duration::from_millis(300))
+                              .poll_interval(Duration::from_millis(50))
+                              .message("a video")
+                              .until(conditions::element_present(&By::css("video")))
+                              .expect_err("Want timeout");
+                match err {
+                    webdriver_client::Error::WaitTimeout(ref what) => assert_eq!(what, "a video"),
+                    other => panic!("Wrong error type: {:?}", other),
+                }
+            }
+
+            #[test]
+            fn wait_for_document_ready() {
+                let (server, sess) = setup();
+                let page1 = server.url("/page1.html");
+                sess.go(&page1).expect("Error going to page1");
+                sess.wait_for_document_ready().expect("Error waiting for document");
+            }
+
+            #[test]
+            fn wait_for_network_idle() {
+                let (server, sess) = setup();
+                let page = server.url("/network.html");
+                sess.go(&page).expect("Error going to network page");
+                sess.wait_for_network_idle(Duration::from_millis(500))
+                    .expect("Error waiting for network idle");
+                let status = sess.find_element(&By::css("#status")).expect("find status");
+                assert_eq!(&status.text().expect("status text"), "Done");
+            }
+
             fn setup() -> (FileServer, DriverSession) {
                 ensure_logging_init();
 
@@ -640,6 +1166,8 @@ impl FileServer {
 
         match req.uri {
             hyper::uri::RequestUri::AbsolutePath(ref path) => {
+                // Ignore the query, as sent by a submitted form.
+                let path = path.split('?').next().unwrap();
                 if path.starts_with("/") {
                     let abs_path = www_root.join(&path[1..]);
                     let file_path = std::fs::canonicalize(&abs_path);
@@ -0,0 +1,264 @@
+//! The integration tests that do not depend on rendering a page, run
+//! against `MockServer` so they need no browser.
+
+#![cfg(feature = "mock")]
+
+#[macro_use]
+extern crate serde_json;
+extern crate webdriver_client;
+
+use serde_json::Value as JsonValue;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use webdriver_client::{By, Driver, DriverSession, Error};
+use webdriver_client::messages::{ExecuteCmd, NewSessionCmd};
+use webdriver_client::mock::{Call, MockResponse, MockServer, SESSION_ID};
+use webdriver_client::transport::Method;
+
+fn setup() -> (MockServer, DriverSession) {
+    let server = MockServer::start().expect("Error starting mock server");
+    let sess = server.clone().session(&NewSessionCmd::default()).expect("Error starting session");
+    (server, sess)
+}
+
+/// The method and path of each call after New Session.
+fn requests(server: &MockServer) -> Vec<(Method, String)> {
+    server.calls().into_iter().skip(1).map(|c| (c.method, c.path)).collect()
+}
+
+/// The W3C error code of a `WebDriverError`.
+fn error_code(err: &Error) -> Option<&str> {
+    match *err {
+        Error::WebDriverError(ref err) => Some(&err.error),
+        _ => None,
+    }
+}
+
+fn path(suffix: &str) -> String {
+    format!("/session/{}{}", SESSION_ID, suffix)
+}
+
+/// Answer the navigation commands like a browser with a history.
+fn browser_history(server: &MockServer) {
+    // The history, and the index of the current page in it.
+    let history = Arc::new(Mutex::new((vec!["about:blank".to_owned()], 0)));
+    let h = history.clone();
+    server.on(Method::Post, "/session/{}/url", move |call: &Call| {
+        let mut h = h.lock().unwrap();
+        let current = h.1;
+        h.0.truncate(current + 1);
+        h.0.push(call.body["url"].as_str().unwrap().to_owned());
+        h.1 += 1;
+        MockResponse::value(JsonValue::Null)
+    });
+    let h = history.clone();
+    server.on(Method::Get, "/session/{}/url", move |_: &Call| {
+        let h = h.lock().unwrap();
+        MockResponse::value(json!(h.0[h.1]))
+    });
+    let h = history.clone();
+    server.on(Method::Post, "/session/{}/back", move |_: &Call| {
+        let mut h = h.lock().unwrap();
+        h.1 = h.1.saturating_sub(1);
+        MockResponse::value(JsonValue::Null)
+    });
+    let h = history;
+    server.on(Method::Post, "/session/{}/forward", move |_: &Call| {
+        let mut h = h.lock().unwrap();
+        h.1 = (h.1 + 1).min(h.0.len() - 1);
+        MockResponse::value(JsonValue::Null)
+    });
+    server.respond(Method::Post, "/session/{}/refresh", MockResponse::value(JsonValue::Null));
+}
+
+#[test]
+fn session_lifecycle() {
+    let (server, sess) = setup();
+    assert_eq!(sess.session_id(), SESSION_ID);
+    assert_eq!(sess.browser_name(), Some("mock"));
+    assert!(!sess.is_remote());
+
+    let new_session = &server.calls()[0];
+    assert_eq!((new_session.method, new_session.path.as_str()), (Method::Post, "/session"));
+    assert!(new_session.body["capabilities"].is_object(), "Bad body {}", new_session.body);
+
+    drop(sess);
+    assert_eq!(requests(&server), vec![(Method::Delete, path(""))]);
+}
+
+#[test]
+fn attached_session() {
+    let server = MockServer::start().unwrap();
+    server.respond(Method::Get, "/session/{}/title", MockResponse::value(json!("Attached")));
+    // Attaching checks that the session exists.
+    server.respond(Method::Get, "/session/{}/url", MockResponse::value(json!("about:blank")));
+    let mut sess = DriverSession::attach(server.url(), "existing").unwrap();
+    assert_eq!(sess.session_id(), "existing");
+    assert_eq!(sess.get_title().unwrap(), "Attached");
+
+    // The session is left running when told not to delete it.
+    sess.drop_session(false);
+    drop(sess);
+    let calls: Vec<_> = server.calls().into_iter().map(|c| (c.method, c.path)).collect();
+    assert_eq!(calls, vec![(Method::Get, "/session/existing/url".to_owned()),
+                           (Method::Get, "/session/existing/title".to_owned())]);
+
+    // A session that does not exist cannot be attached to.
+    server.respond(Method::Get, "/session/{}/url",
+                   MockResponse::error("invalid session id", "No such session"));
+    match DriverSession::attach(server.url(), "gone") {
+        Err(err) => assert_eq!(error_code(&err), Some("invalid session id")),
+        Ok(_) => panic!("Want invalid session id"),
+    }
+}
+
+#[test]
+fn navigation() {
+    let (server, sess) = setup();
+    browser_history(&server);
+    let page1 = "http://example.com/page1.html";
+    let page2 = "http://example.com/page2.html";
+
+    sess.go(page1).expect("Error going to page1");
+    assert_eq!(sess.get_current_url().expect("Error getting url [1]"), page1);
+    sess.go(page2).expect("Error going to page2");
+    assert_eq!(sess.get_current_url().expect("Error getting url [2]"), page2);
+    sess.back().expect("Error going back");
+    assert_eq!(sess.get_current_url().expect("Error getting url [3]"), page1);
+    sess.forward().expect("Error going forward");
+    assert_eq!(sess.get_current_url().expect("Error getting url [4]"), page2);
+    sess.refresh().expect("Error refreshing");
+    assert_eq!(sess.get_current_url().expect("Error getting url [5]"), page2);
+
+    let go = server.calls().into_iter().find(|c| c.path == path("/url")).unwrap();
+    assert_eq!(go.body, json!({ "url": page1 }));
+}
+
+#[test]
+fn title_and_page_source() {
+    let (server, sess) = setup();
+    let source = "<html><head><title>Test page 1 title</title></head></html>";
+    server.respond(Method::Get, "/session/{}/title",
+                   MockResponse::value(json!("Test page 1 title")));
+    server.respond(Method::Get, "/session/{}/source", MockResponse::value(json!(source)));
+    assert_eq!(sess.get_title().expect("Error getting title"), "Test page 1 title");
+    assert_eq!(sess.get_page_source().expect("Error getting page source"), source);
+}
+
+#[test]
+fn cookies() {
+    let (server, sess) = setup();
+    server.respond(Method::Get, "/session/{}/cookie", MockResponse::value(json!([
+        { "name": "session", "value": "abc", "path": "/", "domain": "example.com",
+          "secure": true, "httpOnly": true },
+        { "name": "theme", "value": "dark", "path": "/app", "domain": ".example.com",
+          "secure": false, "httpOnly": false, "expiry": 1700000000 },
+    ])));
+    let cookies = sess.get_cookies().expect("Error getting cookies");
+    let summary: Vec<_> = cookies.iter()
+        .map(|c| (c.name.as_str(), c.value.as_str(), c.path.as_str(), c.domain.as_str(),
+                  c.secure, c.httpOnly))
+        .collect();
+    assert_eq!(summary, vec![("session", "abc", "/", "example.com", true, true),
+                             ("theme", "dark", "/app", ".example.com", false, false)]);
+
+    server.respond(Method::Get, "/session/{}/cookie", MockResponse::value(json!([])));
+    assert!(sess.get_cookies().unwrap().is_empty());
+}
+
+#[test]
+fn window_handles() {
+    let (server, mut sess) = setup();
+    let current = Arc::new(Mutex::new("main".to_owned()));
+    let c = current.clone();
+    server.on(Method::Get, "/session/{}/window", move |_: &Call| {
+        MockResponse::value(json!(*c.lock().unwrap()))
+    });
+    let c = current.clone();
+    server.on(Method::Post, "/session/{}/window", move |call: &Call| {
+        *c.lock().unwrap() = call.body["handle"].as_str().unwrap().to_owned();
+        MockResponse::value(JsonValue::Null)
+    });
+    server.respond(Method::Get, "/session/{}/window/handles",
+                   MockResponse::value(json!(["main", "popup"])));
+    server.respond(Method::Delete, "/session/{}/window", MockResponse::value(json!(["main"])));
+
+    assert_eq!(sess.get_window_handle().unwrap(), "main");
+    let handles = sess.get_window_handles().unwrap();
+    assert_eq!(handles, vec!["main", "popup"]);
+    sess.switch_window(&handles[1]).unwrap();
+    assert_eq!(sess.get_window_handle().unwrap(), "popup");
+    sess.close_window().unwrap();
+
+    assert_eq!(requests(&server), vec![(Method::Get, path("/window")),
+                                       (Method::Get, path("/window/handles")),
+                                       (Method::Post, path("/window")),
+                                       (Method::Get, path("/window")),
+                                       (Method::Delete, path("/window"))]);
+}
+
+#[test]
+fn webdriver_errors() {
+    let (server, sess) = setup();
+    server.respond(Method::Post, "/session/{}/element",
+                   MockResponse::error("no such element", "Unable to locate element"));
+    server.respond(Method::Post, "/session/{}/elements", MockResponse::value(json!([])));
+    server.respond(Method::Post, "/session/{}/execute/sync",
+                   MockResponse::error("javascript error", "foo"));
+    server.respond(Method::Get, "/session/{}/alert/text",
+                   MockResponse::error("no such alert", "No user prompt is open"));
+
+    match sess.find_element(&By::css("body.red")) {
+        Err(Error::WebDriverError(err)) => {
+            assert_eq!(err.error, "no such element");
+            assert!(err.message.starts_with(r#"No element found for css "body.red""#),
+                    "Bad error message: {}", err.message);
+        }
+        other => panic!("Want no such element, got {:?}", other.map(|_| ())),
+    }
+    let found = sess.find_elements(&By::css("body.red")).expect("Error finding absent elements");
+    assert!(found.is_empty(), "Want to find no elements, found {:?}", found);
+
+    let exec_error = sess.execute(ExecuteCmd { script: "throw 'foo';".to_owned(), args: vec![] })
+        .expect_err("Want error");
+    assert_eq!(error_code(&exec_error), Some("javascript error"), "Wrong error: {:?}", exec_error);
+    assert_eq!(error_code(&sess.get_alert_text().unwrap_err()), Some("no such alert"));
+    // Commands the server does not implement.
+    assert_eq!(error_code(&sess.dismiss_alert().unwrap_err()), Some("unknown command"));
+}
+
+#[test]
+fn stale_elements() {
+    let (server, sess) = setup();
+    server.respond(Method::Post, "/session/{}/element",
+                   MockResponse::value(json!({ "element-6066-11e4-a52e-4f735466cecf": "e1" })));
+    server.respond(Method::Get, "/session/{}/element/e1/text",
+                   MockResponse::error("stale element reference", "Element is gone"));
+    let elem = sess.find_element(&By::css("#textfield")).unwrap();
+    let err = elem.text().expect_err("Want stale element error");
+    assert_eq!(error_code(&err), Some("stale element reference"), "Wrong error: {:?}", err);
+}
+
+#[test]
+fn invalid_responses() {
+    let (server, sess) = setup();
+    server.respond(Method::Get, "/session/{}/title",
+                   MockResponse { status: 200, body: json!({ "value": 42 }) });
+    match sess.get_title() {
+        Err(Error::JsonDecodeError(_)) => {}
+        other => panic!("Want JsonDecodeError, got {:?}", other),
+    }
+}
+
+#[test]
+fn connection_errors() {
+    // A local port with nothing listening on it.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    match DriverSession::attach(&url, "gone") {
+        Err(Error::ConnectionError) => {}
+        Err(other) => panic!("Want ConnectionError, got {:?}", other),
+        Ok(_) => panic!("Want ConnectionError"),
+    }
+}
@@ -0,0 +1,6 @@
+use webdriver_client::css;
+
+fn main() {
+    let _ = css!("ul > > li");
+    let _ = css!("a:hovr");
+}
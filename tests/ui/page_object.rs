@@ -0,0 +1,26 @@
+use webdriver_client::Element;
+use webdriver_client::page::{Lazy, PageObject, SearchContext};
+
+#[derive(PageObject)]
+struct BadSelector {
+    #[find(css = "li:nth-child(")] items: Lazy<Vec<Element>>,
+}
+
+#[derive(PageObject)]
+struct UnknownArgument {
+    #[find(selector = "li")] items: Lazy<Vec<Element>>,
+}
+
+#[derive(PageObject)]
+#[page(url = "/search")]
+struct UrlWithoutRoot {
+    #[find(css = "li")] items: Lazy<Vec<Element>>,
+}
+
+#[derive(PageObject)]
+struct TwoRoots {
+    #[root] first: SearchContext,
+    #[root] second: SearchContext,
+}
+
+fn main() {}
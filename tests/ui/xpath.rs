@@ -0,0 +1,6 @@
+use webdriver_client::xpath;
+
+fn main() {
+    let _ = xpath!("//a[@href");
+    let _ = xpath!("//a[frobnicate()]");
+}
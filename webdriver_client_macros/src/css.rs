@@ -0,0 +1,308 @@
+//! CSS selector validation, using the `selectors` crate from Servo.
+//!
+//! `selectors` needs a `SelectorImpl` to say which pseudo-classes and
+//! pseudo-elements exist. This one accepts those that browsers support in
+//! `querySelector`, so a misspelt pseudo-class is an error while the
+//! selector itself is never matched against anything.
+
+use crate::SyntaxError;
+
+use cssparser::{CowRcStr, ParseError, ParseErrorKind, BasicParseErrorKind, SourceLocation,
+                ToCss, Token};
+use precomputed_hash::PrecomputedHash;
+use selectors::parser::{self, ParseRelative, SelectorList, SelectorParseErrorKind};
+
+use std::fmt;
+
+/// Check that `selector` is a valid CSS selector list.
+pub fn validate(selector: &str) -> Result<(), SyntaxError> {
+    let mut input = cssparser::ParserInput::new(selector);
+    let mut input = cssparser::Parser::new(&mut input);
+    SelectorList::<Browser>::parse(&BrowserParser, &mut input, ParseRelative::No)
+        .map(|_| ())
+        .map_err(|err| SyntaxError {
+            position: offset(selector, err.location),
+            message: describe(&err),
+        })
+}
+
+/// The character offset in `selector` of a cssparser location, which counts
+/// columns in UTF-16 code units.
+fn offset(selector: &str, location: SourceLocation) -> usize {
+    let mut offset = 0;
+    for (i, line) in selector.split('\n').enumerate() {
+        if i as u32 == location.line {
+            let mut units = 1;
+            for c in line.chars() {
+                if units >= location.column {
+                    break;
+                }
+                units += c.len_utf16() as u32;
+                offset += 1;
+            }
+            return offset;
+        }
+        offset += line.chars().count() + 1;
+    }
+    offset
+}
+
+fn describe(err: &ParseError<SelectorParseErrorKind>) -> String {
+    use self::SelectorParseErrorKind::*;
+    match err.kind {
+        ParseErrorKind::Basic(BasicParseErrorKind::UnexpectedToken(ref token)) =>
+            format!("unexpected {}", token_name(token)),
+        ParseErrorKind::Basic(BasicParseErrorKind::EndOfInput) =>
+            "unexpected end of input".to_owned(),
+        ParseErrorKind::Basic(ref other) => format!("{:?}", other),
+        ParseErrorKind::Custom(ref kind) => match *kind {
+            EmptySelector => "expected a selector".to_owned(),
+            DanglingCombinator => "expected a selector after the combinator".to_owned(),
+            UnsupportedPseudoClassOrElement(ref name) =>
+                format!("unknown pseudo-class or pseudo-element `{}`", name),
+            UnexpectedIdent(ref name) => format!("unexpected `{}`", name),
+            ExpectedNamespace(ref prefix) => format!("undeclared namespace prefix `{}`", prefix),
+            ClassNeedsIdent(ref token) =>
+                format!("expected a class name after `.`, found {}", token_name(token)),
+            NoIdentForPseudo(ref token) | PseudoElementExpectedIdent(ref token) =>
+                format!("expected a pseudo-class name, found {}", token_name(token)),
+            NoQualifiedNameInAttributeSelector(ref token) | InvalidQualNameInAttr(ref token) =>
+                format!("expected an attribute name, found {}", token_name(token)),
+            UnexpectedTokenInAttributeSelector(ref token) | ExpectedBarInAttr(ref token) =>
+                format!("expected an attribute operator or `]`, found {}", token_name(token)),
+            BadValueInAttr(ref token) =>
+                format!("expected an attribute value, found {}", token_name(token)),
+            ref other => format!("{:?}", other),
+        },
+    }
+}
+
+fn token_name(token: &Token) -> String {
+    match *token {
+        Token::WhiteSpace(_) => "whitespace".to_owned(),
+        Token::CloseParenthesis => "`)`".to_owned(),
+        Token::CloseSquareBracket => "`]`".to_owned(),
+        Token::CloseCurlyBracket => "`}`".to_owned(),
+        Token::BadString(_) => "unterminated string".to_owned(),
+        ref token => format!("`{}`", token.to_css_string()),
+    }
+}
+
+/// Non-tree-structural pseudo-classes that browsers implement. The
+/// structural ones such as `:first-child` and `:not()` are parsed by
+/// `selectors` itself.
+const PSEUDO_CLASSES: &[&str] = &[
+    "active", "any-link", "autofill", "blank", "checked", "default", "defined", "disabled",
+    "enabled", "focus", "focus-visible", "focus-within", "fullscreen", "hover", "in-range",
+    "indeterminate", "invalid", "link", "local-link", "modal", "muted", "open", "optional",
+    "out-of-range", "paused", "picture-in-picture", "placeholder-shown", "playing",
+    "popover-open", "read-only", "read-write", "required", "target", "target-within",
+    "user-invalid", "user-valid", "valid", "visited",
+    // CSS 2 pseudo-elements may be written with a single colon.
+    "after", "before", "first-letter", "first-line",
+];
+
+const FUNCTIONAL_PSEUDO_CLASSES: &[&str] = &["dir", "lang", "state", "host-context"];
+
+const PSEUDO_ELEMENTS: &[&str] = &[
+    "after", "backdrop", "before", "cue", "file-selector-button", "first-letter", "first-line",
+    "grammar-error", "marker", "placeholder", "selection", "spelling-error", "target-text",
+];
+
+const FUNCTIONAL_PSEUDO_ELEMENTS: &[&str] = &["cue", "highlight", "part", "slotted"];
+
+fn known(names: &[&str], name: &str) -> bool {
+    // Vendor-prefixed names such as `:-webkit-autofill` are passed through.
+    name.starts_with('-') || names.iter().any(|n| n.eq_ignore_ascii_case(name))
+}
+
+/// Skip the arguments of a functional pseudo-class or pseudo-element.
+fn skip_arguments<'i, 't>(input: &mut cssparser::Parser<'i, 't>)
+                          -> Result<(), ParseError<'i, SelectorParseErrorKind<'i>>> {
+    while input.next().is_ok() {}
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Browser;
+
+/// A name or value within a selector. Only its validity matters here.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Name(String);
+
+impl<'a> From<&'a str> for Name {
+    fn from(s: &'a str) -> Self {
+        Name(s.to_owned())
+    }
+}
+
+impl ToCss for Name {
+    fn to_css<W: fmt::Write>(&self, dest: &mut W) -> fmt::Result {
+        dest.write_str(&self.0)
+    }
+}
+
+impl PrecomputedHash for Name {
+    fn precomputed_hash(&self) -> u32 {
+        0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PseudoClass(String);
+
+impl ToCss for PseudoClass {
+    fn to_css<W: fmt::Write>(&self, dest: &mut W) -> fmt::Result {
+        dest.write_str(":")?;
+        dest.write_str(&self.0)
+    }
+}
+
+impl parser::NonTSPseudoClass for PseudoClass {
+    type Impl = Browser;
+
+    fn is_active_or_hover(&self) -> bool {
+        self.0 == "active" || self.0 == "hover"
+    }
+
+    fn is_user_action_state(&self) -> bool {
+        self.is_active_or_hover() || self.0 == "focus"
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PseudoElement(String);
+
+impl ToCss for PseudoElement {
+    fn to_css<W: fmt::Write>(&self, dest: &mut W) -> fmt::Result {
+        dest.write_str("::")?;
+        dest.write_str(&self.0)
+    }
+}
+
+impl parser::PseudoElement for PseudoElement {
+    type Impl = Browser;
+
+    fn accepts_state_pseudo_classes(&self) -> bool {
+        true
+    }
+}
+
+impl parser::SelectorImpl for Browser {
+    type ExtraMatchingData<'a> = ();
+    type AttrValue = Name;
+    type Identifier = Name;
+    type LocalName = Name;
+    type NamespaceUrl = Name;
+    type NamespacePrefix = Name;
+    type BorrowedNamespaceUrl = Name;
+    type BorrowedLocalName = Name;
+    type NonTSPseudoClass = PseudoClass;
+    type PseudoElement = PseudoElement;
+}
+
+struct BrowserParser;
+
+impl<'i> parser::Parser<'i> for BrowserParser {
+    type Impl = Browser;
+    type Error = SelectorParseErrorKind<'i>;
+
+    fn parse_nth_child_of(&self) -> bool {
+        true
+    }
+
+    fn parse_is_and_where(&self) -> bool {
+        true
+    }
+
+    fn parse_has(&self) -> bool {
+        true
+    }
+
+    fn parse_host(&self) -> bool {
+        true
+    }
+
+    fn parse_non_ts_pseudo_class(&self, location: SourceLocation, name: CowRcStr<'i>)
+                                 -> Result<PseudoClass, ParseError<'i, Self::Error>> {
+        if known(PSEUDO_CLASSES, &name) {
+            Ok(PseudoClass(name.to_ascii_lowercase()))
+        } else {
+            Err(location.new_custom_error(
+                SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name)))
+        }
+    }
+
+    fn parse_non_ts_functional_pseudo_class<'t>(&self, name: CowRcStr<'i>,
+                                                input: &mut cssparser::Parser<'i, 't>,
+                                                _after_part: bool)
+                                                -> Result<PseudoClass, ParseError<'i, Self::Error>> {
+        if known(FUNCTIONAL_PSEUDO_CLASSES, &name) {
+            skip_arguments(input)?;
+            Ok(PseudoClass(name.to_ascii_lowercase()))
+        } else {
+            Err(input.new_custom_error(
+                SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name)))
+        }
+    }
+
+    fn parse_pseudo_element(&self, location: SourceLocation, name: CowRcStr<'i>)
+                            -> Result<PseudoElement, ParseError<'i, Self::Error>> {
+        if known(PSEUDO_ELEMENTS, &name) {
+            Ok(PseudoElement(name.to_ascii_lowercase()))
+        } else {
+            Err(location.new_custom_error(
+                SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name)))
+        }
+    }
+
+    fn parse_functional_pseudo_element<'t>(&self, name: CowRcStr<'i>,
+                                           input: &mut cssparser::Parser<'i, 't>)
+                                           -> Result<PseudoElement, ParseError<'i, Self::Error>> {
+        if known(FUNCTIONAL_PSEUDO_ELEMENTS, &name) {
+            skip_arguments(input)?;
+            Ok(PseudoElement(name.to_ascii_lowercase()))
+        } else {
+            Err(input.new_custom_error(
+                SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate;
+
+    fn error(selector: &str) -> (usize, String) {
+        let err = validate(selector).unwrap_err();
+        (err.position, err.message)
+    }
+
+    #[test]
+    fn accepts_browser_selectors() {
+        for selector in &[
+            "div",
+            "#menu > li.item:nth-child(2n+1)",
+            "a[href^='https://'], a[target=_blank i]",
+            "input:checked + label::before",
+            "ul li:not(.hidden):is(.a, .b)",
+            "section:has(> h2)",
+            "p:lang(en)",
+            "input:-webkit-autofill",
+            "*",
+        ] {
+            assert!(validate(selector).is_ok(), "{} should be valid", selector);
+        }
+    }
+
+    #[test]
+    fn reports_the_bad_token() {
+        assert_eq!(error("div > > p"), (6, "expected a selector after the combinator".to_owned()));
+        assert_eq!(error("a:hovr"), (2, "unknown pseudo-class or pseudo-element `hovr`".to_owned()));
+        assert_eq!(error("ul li."), (6, "unexpected end of input".to_owned()));
+        assert_eq!(error("a[href=]").0, 7);
+        assert_eq!(error("a[x~y]").1, "expected an attribute operator or `]`, found `~`");
+        assert_eq!(error("").1, "expected a selector");
+        assert_eq!(error("é, a!").0, 4);
+    }
+}
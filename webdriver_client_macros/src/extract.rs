@@ -0,0 +1,93 @@
+//! `#[derive(Extract)]`.
+
+use crate::locator::Locator;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields, LitStr};
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => return Err(syn::Error::new(
+                input.ident.span(), "Extract can only be derived for a struct with named fields")),
+        },
+        _ => return Err(syn::Error::new(
+            input.ident.span(), "Extract can only be derived for a struct")),
+    };
+
+    let mut specs = vec![];
+    let mut inits = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let attr = match field.attrs.iter().find(|attr| attr.path().is_ident("extract")) {
+            Some(attr) => attr,
+            None => {
+                inits.push(quote!(#ident: ::std::default::Default::default()));
+                continue;
+            }
+        };
+        let mut locator = Locator::default();
+        let mut source = None;
+        attr.parse_nested_meta(|meta| {
+            if locator.parse_meta(&meta)? {
+                return Ok(());
+            }
+            let found = if meta.path.is_ident("text") {
+                quote!(::webdriver_client::extract::Source::Text)
+            } else if meta.path.is_ident("html") {
+                quote!(::webdriver_client::extract::Source::Html)
+            } else if meta.path.is_ident("attr") {
+                let name: LitStr = meta.value()?.parse()?;
+                quote!(::webdriver_client::extract::Source::Attribute(#name.to_owned()))
+            } else if meta.path.is_ident("property") {
+                let name: LitStr = meta.value()?.parse()?;
+                quote!(::webdriver_client::extract::Source::Property(#name.to_owned()))
+            } else {
+                return Err(meta.error("unknown argument, expected a locator such as \
+                                       `css = \"...\"` or a source: `text`, `html`, \
+                                       `attr = \"...\"` or `property = \"...\"`"));
+            };
+            if source.replace(found).is_some() {
+                return Err(meta.error("only one source may be given"));
+            }
+            Ok(())
+        })?;
+        let by = match locator.to_optional_by(attr.span())? {
+            Some(by) => quote!(::std::option::Option::Some(#by)),
+            None => quote!(::std::option::Option::None),
+        };
+        let source = source.unwrap_or_else(|| quote!(::webdriver_client::extract::Source::Text));
+        let name = ident.unraw().to_string();
+        specs.push(quote!(::webdriver_client::extract::Field::new::<#ty>(#name, #by, #source)));
+        inits.push(quote!(#ident: ::webdriver_client::extract::field(&mut values, #name)?));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::webdriver_client::extract::Extract for #name #ty_generics #where_clause {
+            fn fields() -> ::std::vec::Vec<::webdriver_client::extract::Field> {
+                vec![#(#specs),*]
+            }
+        }
+
+        impl #impl_generics ::webdriver_client::extract::FromExtract for #name #ty_generics #where_clause {
+            fn shape() -> ::webdriver_client::extract::Shape {
+                ::webdriver_client::extract::Shape::nested(
+                    <Self as ::webdriver_client::extract::Extract>::fields())
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn from_extracted(value: ::webdriver_client::JsonValue)
+                              -> ::std::result::Result<Self, ::webdriver_client::Error> {
+                let mut values = ::webdriver_client::extract::object(value)?;
+                ::std::result::Result::Ok(#name { #(#inits,)* })
+            }
+        }
+    })
+}
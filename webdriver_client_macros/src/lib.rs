@@ -0,0 +1,156 @@
+//! Procedural macros for `webdriver_client`, re-exported by that crate when
+//! its `macros` feature is enabled. Use them through `webdriver_client`
+//! rather than depending on this crate directly.
+
+extern crate proc_macro;
+
+mod css;
+mod extract;
+mod locator;
+mod page_object;
+mod xpath;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use std::ops::Range;
+use syn::{parse_macro_input, DeriveInput, LitStr};
+
+/// A syntax error in a selector, at a character offset within it.
+#[derive(Debug)]
+struct SyntaxError {
+    position: usize,
+    message: String,
+}
+
+impl SyntaxError {
+    /// The message for a compiler error, with the selector and a caret
+    /// under the bad token.
+    fn render(&self, what: &str, selector: &str) -> String {
+        let mut column = self.position;
+        let mut line = selector;
+        for l in selector.split('\n') {
+            let len = l.chars().count();
+            if column <= len {
+                line = l;
+                break;
+            }
+            column -= len + 1;
+        }
+        format!("invalid {}: {} at column {}\n  {}\n  {}^",
+                what, self.message, column + 1, line, " ".repeat(column))
+    }
+}
+
+/// A `By` locator for a CSS selector that is checked at compile time.
+///
+/// `css!("ul > li.item")` expands to
+/// `By::new(LocationStrategy::Css, "ul > li.item")`. A selector that a
+/// browser would reject, such as `"ul > > li"` or `"a:hovr"`, is a compile
+/// error that shows where in the selector the problem is.
+#[proc_macro]
+pub fn css(input: TokenStream) -> TokenStream {
+    let selector = parse_macro_input!(input as LitStr);
+    match check_css(&selector) {
+        Ok(()) => quote!(
+            ::webdriver_client::By::new(::webdriver_client::LocationStrategy::Css, #selector)
+        ).into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// A `By` locator for an XPath 1.0 expression that is checked at compile
+/// time.
+///
+/// `xpath!("//a[@href]")` expands to
+/// `By::new(LocationStrategy::XPath, "//a[@href]")`. An invalid expression,
+/// such as an unclosed predicate or an unknown function, is a compile error
+/// that shows where in the expression the problem is.
+#[proc_macro]
+pub fn xpath(input: TokenStream) -> TokenStream {
+    let selector = parse_macro_input!(input as LitStr);
+    match check_xpath(&selector) {
+        Ok(()) => quote!(
+            ::webdriver_client::By::new(::webdriver_client::LocationStrategy::XPath, #selector)
+        ).into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Implements `webdriver_client::page::PageObject`. See the documentation
+/// of the `webdriver_client::page` module.
+#[proc_macro_derive(PageObject, attributes(find, page, root))]
+pub fn derive_page_object(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    page_object::derive(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Implements `webdriver_client::extract::Extract`. See the documentation
+/// of the `webdriver_client::extract` module.
+#[proc_macro_derive(Extract, attributes(extract))]
+pub fn derive_extract(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    extract::derive(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn check_css(selector: &LitStr) -> syn::Result<()> {
+    css::validate(&selector.value()).map_err(|err| {
+        syn::Error::new(error_span(selector, err.position),
+                        err.render("CSS selector", &selector.value()))
+    })
+}
+
+fn check_xpath(selector: &LitStr) -> syn::Result<()> {
+    xpath::validate(&selector.value()).map_err(|err| {
+        syn::Error::new(error_span(selector, err.position),
+                        err.render("XPath expression", &selector.value()))
+    })
+}
+
+/// The span of the character at `position` in the value of `lit`, or of
+/// the whole literal where the compiler can't point inside it. Only
+/// nightly compilers can, and only for literals without escapes.
+fn error_span(lit: &LitStr, position: usize) -> Span {
+    source_range(&lit.token().to_string(), &lit.value(), position)
+        .and_then(|range| lit.token().subspan(range))
+        .unwrap_or_else(|| lit.span())
+}
+
+/// The byte range in `source`, the source text of a string literal whose
+/// value is `value`, of the character at `position` in `value`. A
+/// position at the end of `value` gives the closing quote. `None` if the
+/// literal contains escapes, so its source and value differ.
+fn source_range(source: &str, value: &str, position: usize) -> Option<Range<usize>> {
+    let start = source.find('"')? + 1;
+    let end = source.rfind('"')?;
+    if end < start || &source[start..end] != value {
+        return None;
+    }
+    let (offset, len) = value.char_indices().nth(position)
+        .map_or((value.len(), 1), |(offset, c)| (offset, c.len_utf8()));
+    Some(start + offset..start + offset + len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{source_range, SyntaxError};
+
+    #[test]
+    fn render_points_at_the_token() {
+        let err = SyntaxError { position: 6, message: "unexpected `>`".to_owned() };
+        assert_eq!(err.render("CSS selector", "div > > p"),
+                   "invalid CSS selector: unexpected `>` at column 7\n  div > > p\n        ^");
+        let err = SyntaxError { position: 5, message: "unexpected `!`".to_owned() };
+        assert_eq!(err.render("CSS selector", "a,\nb, !"),
+                   "invalid CSS selector: unexpected `!` at column 3\n  b, !\n    ^");
+    }
+
+    #[test]
+    fn source_ranges() {
+        assert_eq!(source_range(r#""a > > b""#, "a > > b", 4), Some(5..6));
+        assert_eq!(source_range(r##"r#"é!"#"##, "é!", 1), Some(5..6));
+        // The closing quote.
+        assert_eq!(source_range(r#""a[""#, "a[", 2), Some(3..4));
+        assert_eq!(source_range(r#""a\tb""#, "a\tb", 1), None);
+    }
+}
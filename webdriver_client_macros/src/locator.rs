@@ -0,0 +1,75 @@
+//! Locators written as attribute arguments, such as
+//! `#[find(css = "li.item", nth = 2)]`.
+
+use crate::{check_css, check_xpath};
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
+use syn::{LitInt, LitStr};
+
+/// The `By` constructors that can be named in an attribute.
+const STRATEGIES: &[&str] = &[
+    "css", "xpath", "link_text", "partial_link_text", "tag_name", "id", "name", "class_name",
+    "data_test_id", "exact_text", "contains_text",
+];
+
+#[derive(Default)]
+pub struct Locator {
+    strategy: Option<(String, LitStr)>,
+    nth: Option<LitInt>,
+}
+
+impl Locator {
+    /// Take `meta` if it is part of a locator, returning whether it was.
+    pub fn parse_meta(&mut self, meta: &ParseNestedMeta) -> syn::Result<bool> {
+        if meta.path.is_ident("nth") {
+            self.nth = Some(meta.value()?.parse()?);
+            return Ok(true);
+        }
+        let name = match STRATEGIES.iter().find(|s| meta.path.is_ident(s)) {
+            Some(name) => name.to_string(),
+            None => return Ok(false),
+        };
+        if self.strategy.is_some() {
+            return Err(meta.error("only one locator strategy may be given"));
+        }
+        let selector: LitStr = meta.value()?.parse()?;
+        match name.as_str() {
+            "css" => check_css(&selector)?,
+            "xpath" => check_xpath(&selector)?,
+            _ => {}
+        }
+        self.strategy = Some((name, selector));
+        Ok(true)
+    }
+
+    /// The error for an argument that `parse_meta` did not take.
+    pub fn unknown(meta: &ParseNestedMeta) -> syn::Error {
+        meta.error(format!("unknown argument, expected a locator such as `css = \"...\"`; \
+                            the strategies are {}", STRATEGIES.join(", ")))
+    }
+
+    /// An expression building the `By`, or an error at `span` if no
+    /// strategy was given.
+    pub fn to_by(&self, span: Span) -> syn::Result<TokenStream> {
+        self.to_optional_by(span)?
+            .ok_or_else(|| syn::Error::new(span, "expected a locator such as `css = \"...\"`"))
+    }
+
+    /// An expression building the `By`, or `None` if no locator was given.
+    pub fn to_optional_by(&self, span: Span) -> syn::Result<Option<TokenStream>> {
+        let (name, selector) = match self.strategy {
+            Some(ref strategy) => strategy,
+            None if self.nth.is_some() =>
+                return Err(syn::Error::new(span, "`nth` needs a locator such as `css = \"...\"`")),
+            None => return Ok(None),
+        };
+        let constructor = format_ident!("{}", name);
+        let by = quote!(::webdriver_client::By::#constructor(#selector));
+        Ok(Some(match self.nth {
+            Some(ref nth) => quote!(#by.nth(#nth)),
+            None => by,
+        }))
+    }
+}
@@ -0,0 +1,107 @@
+//! `#[derive(PageObject)]`.
+
+use crate::locator::Locator;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields, LitStr};
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => return Err(syn::Error::new(
+                input.ident.span(), "PageObject can only be derived for a struct with named fields")),
+        },
+        _ => return Err(syn::Error::new(
+            input.ident.span(), "PageObject can only be derived for a struct")),
+    };
+
+    let mut root = None;
+    let mut inits = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let is_root = field.attrs.iter().any(|attr| attr.path().is_ident("root"));
+        let find = field.attrs.iter().find(|attr| attr.path().is_ident("find"));
+        match (is_root, find) {
+            (true, Some(attr)) =>
+                return Err(syn::Error::new(attr.span(), "a `#[root]` field can't also be `#[find]`")),
+            (true, None) => {
+                if root.is_some() {
+                    return Err(syn::Error::new(field.span(), "only one field can be `#[root]`"));
+                }
+                root = Some(ident);
+                inits.push(quote!(#ident: ::std::clone::Clone::clone(&context)));
+            }
+            (false, Some(attr)) => {
+                let mut locator = Locator::default();
+                attr.parse_nested_meta(|meta| {
+                    if locator.parse_meta(&meta)? {
+                        Ok(())
+                    } else {
+                        Err(Locator::unknown(&meta))
+                    }
+                })?;
+                let by = locator.to_by(attr.span())?;
+                inits.push(quote!(#ident: ::webdriver_client::page::Lazy::new(&context, #by)));
+            }
+            (false, None) => inits.push(quote!(#ident: ::std::default::Default::default())),
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut out = quote! {
+        impl #impl_generics ::webdriver_client::page::PageObject for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn from_context(context: ::webdriver_client::page::SearchContext) -> Self {
+                #name { #(#inits,)* }
+            }
+        }
+    };
+
+    if let Some(url) = page_url(input)? {
+        let root = root.ok_or_else(|| syn::Error::new(
+            url.span(), "a page with a URL needs a `#[root]` field of type `SearchContext`"))?;
+        out.extend(quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// The URL of this page, which may be relative.
+                pub const URL: &'static str = #url;
+
+                /// Navigate to `URL` and return this page.
+                pub fn open(session: &::webdriver_client::DriverSession)
+                            -> ::std::result::Result<Self, ::webdriver_client::Error> {
+                    ::webdriver_client::page::open(session, Self::URL)?;
+                    ::std::result::Result::Ok(<Self as ::webdriver_client::page::PageObject>::from_context(
+                        ::webdriver_client::page::SearchContext::Session(session.clone())))
+                }
+
+                /// Whether the browser is at `URL`.
+                pub fn is_current(&self) -> ::std::result::Result<bool, ::webdriver_client::Error> {
+                    ::webdriver_client::page::is_current(self.#root.session(), Self::URL)
+                }
+            }
+        });
+    }
+    Ok(out)
+}
+
+/// The URL from a `#[page(url = "...")]` attribute, if there is one.
+fn page_url(input: &DeriveInput) -> syn::Result<Option<LitStr>> {
+    let mut url = None;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("page")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("url") {
+                url = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown argument, expected `url = \"...\"`"))
+            }
+        })?;
+    }
+    if url.is_none() && input.attrs.iter().any(|attr| attr.path().is_ident("page")) {
+        return Err(syn::Error::new(Span::call_site(), "expected `#[page(url = \"...\")]`"));
+    }
+    Ok(url)
+}
@@ -0,0 +1,556 @@
+//! XPath 1.0 expression validation.
+//!
+//! Browsers evaluate locators with `document.evaluate`, which implements
+//! XPath 1.0. This is a recursive descent parser for the grammar in the
+//! XPath 1.0 recommendation that checks the syntax, axis names and the
+//! names and argument counts of core library functions, and builds
+//! nothing.
+
+use crate::SyntaxError;
+
+/// Check that `expr` is a valid XPath 1.0 expression.
+pub fn validate(expr: &str) -> Result<(), SyntaxError> {
+    let tokens = tokenize(expr)?;
+    let mut parser = Parser { tokens: &tokens, pos: 0 };
+    parser.expr()?;
+    match parser.peek() {
+        Token::End => Ok(()),
+        _ => Err(parser.unexpected("an operator")),
+    }
+}
+
+const AXES: &[&str] = &[
+    "ancestor", "ancestor-or-self", "attribute", "child", "descendant", "descendant-or-self",
+    "following", "following-sibling", "namespace", "parent", "preceding", "preceding-sibling",
+    "self",
+];
+
+const NODE_TYPES: &[&str] = &["comment", "text", "processing-instruction", "node"];
+
+/// Core library functions, with their minimum and maximum argument counts.
+const FUNCTIONS: &[(&str, usize, usize)] = &[
+    ("last", 0, 0), ("position", 0, 0), ("count", 1, 1), ("id", 1, 1),
+    ("local-name", 0, 1), ("namespace-uri", 0, 1), ("name", 0, 1),
+    ("string", 0, 1), ("concat", 2, usize::MAX), ("starts-with", 2, 2), ("contains", 2, 2),
+    ("substring-before", 2, 2), ("substring-after", 2, 2), ("substring", 2, 3),
+    ("string-length", 0, 1), ("normalize-space", 0, 1), ("translate", 3, 3),
+    ("boolean", 1, 1), ("not", 1, 1), ("true", 0, 0), ("false", 0, 0), ("lang", 1, 1),
+    ("number", 0, 1), ("sum", 1, 1), ("floor", 1, 1), ("ceiling", 1, 1), ("round", 1, 1),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Dot,
+    DotDot,
+    At,
+    Comma,
+    ColonColon,
+    Slash,
+    DoubleSlash,
+    Pipe,
+    Plus,
+    Minus,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Star,
+    /// A QName, or a name test of the form `prefix:*`.
+    Name(String),
+    Literal,
+    Number,
+    Variable,
+    End,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        let s = match *self {
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
+            Token::Dot => ".",
+            Token::DotDot => "..",
+            Token::At => "@",
+            Token::Comma => ",",
+            Token::ColonColon => "::",
+            Token::Slash => "/",
+            Token::DoubleSlash => "//",
+            Token::Pipe => "|",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Eq => "=",
+            Token::NotEq => "!=",
+            Token::Lt => "<",
+            Token::LtEq => "<=",
+            Token::Gt => ">",
+            Token::GtEq => ">=",
+            Token::Star => "*",
+            Token::Name(ref name) => return format!("`{}`", name),
+            Token::Literal => return "a string".to_owned(),
+            Token::Number => return "a number".to_owned(),
+            Token::Variable => return "a variable".to_owned(),
+            Token::End => return "end of expression".to_owned(),
+        };
+        format!("`{}`", s)
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c as u32 >= 0x80
+}
+
+fn is_name_char(c: char) -> bool {
+    is_name_start(c) || c.is_ascii_digit() || c == '-' || c == '.'
+}
+
+/// Split `expr` into tokens, each with its character offset.
+fn tokenize(expr: &str) -> Result<Vec<(Token, usize)>, SyntaxError> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).cloned();
+        let start = i;
+        let (token, len) = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => (Token::LParen, 1),
+            ')' => (Token::RParen, 1),
+            '[' => (Token::LBracket, 1),
+            ']' => (Token::RBracket, 1),
+            '@' => (Token::At, 1),
+            ',' => (Token::Comma, 1),
+            '|' => (Token::Pipe, 1),
+            '+' => (Token::Plus, 1),
+            '-' => (Token::Minus, 1),
+            '=' => (Token::Eq, 1),
+            '*' => (Token::Star, 1),
+            ':' if next == Some(':') => (Token::ColonColon, 2),
+            '/' if next == Some('/') => (Token::DoubleSlash, 2),
+            '/' => (Token::Slash, 1),
+            '!' if next == Some('=') => (Token::NotEq, 2),
+            '<' if next == Some('=') => (Token::LtEq, 2),
+            '<' => (Token::Lt, 1),
+            '>' if next == Some('=') => (Token::GtEq, 2),
+            '>' => (Token::Gt, 1),
+            '.' if next == Some('.') => (Token::DotDot, 2),
+            '.' if !next.is_some_and(|n| n.is_ascii_digit()) => (Token::Dot, 1),
+            '"' | '\'' => match chars[i + 1..].iter().position(|&q| q == c) {
+                Some(end) => (Token::Literal, end + 2),
+                None => return Err(SyntaxError {
+                    position: start,
+                    message: "unterminated string".to_owned(),
+                }),
+            },
+            '.' | '0'..='9' => {
+                let mut end = i;
+                while end < chars.len() && chars[end].is_ascii_digit() {
+                    end += 1;
+                }
+                if end < chars.len() && chars[end] == '.' {
+                    end += 1;
+                    while end < chars.len() && chars[end].is_ascii_digit() {
+                        end += 1;
+                    }
+                }
+                (Token::Number, end - i)
+            }
+            '$' => match name_at(&chars, i + 1) {
+                Some(end) => (Token::Variable, end - i),
+                None => return Err(SyntaxError {
+                    position: start + 1,
+                    message: "expected a variable name after `$`".to_owned(),
+                }),
+            },
+            c if is_name_start(c) => {
+                let end = name_at(&chars, i).unwrap();
+                (Token::Name(chars[i..end].iter().collect()), end - i)
+            }
+            c => return Err(SyntaxError {
+                position: start,
+                message: format!("unexpected character `{}`", c),
+            }),
+        };
+        tokens.push((token, start));
+        i += len;
+    }
+    tokens.push((Token::End, chars.len()));
+    Ok(tokens)
+}
+
+/// The end of the QName or `prefix:*` name test starting at `start`, if
+/// there is one.
+fn name_at(chars: &[char], start: usize) -> Option<usize> {
+    let ncname_end = |from: usize| {
+        if from < chars.len() && is_name_start(chars[from]) {
+            let mut end = from + 1;
+            while end < chars.len() && is_name_char(chars[end]) {
+                end += 1;
+            }
+            Some(end)
+        } else {
+            None
+        }
+    };
+    let end = ncname_end(start)?;
+    if end + 1 < chars.len() && chars[end] == ':' && chars[end + 1] != ':' {
+        if chars[end + 1] == '*' {
+            return Some(end + 2);
+        }
+        if let Some(local_end) = ncname_end(end + 1) {
+            return Some(local_end);
+        }
+    }
+    Some(end)
+}
+
+struct Parser<'a> {
+    tokens: &'a [(Token, usize)],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn peek_at(&self, ahead: usize) -> &Token {
+        let i = (self.pos + ahead).min(self.tokens.len() - 1);
+        &self.tokens[i].0
+    }
+
+    fn advance(&mut self) {
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_name(&mut self, name: &str) -> bool {
+        match *self.peek() {
+            Token::Name(ref n) if n == name => {
+                self.advance();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), SyntaxError> {
+        if self.eat(&token) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&token.describe()))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> SyntaxError {
+        self.error(format!("expected {}, found {}", expected, self.peek().describe()))
+    }
+
+    fn error(&self, message: String) -> SyntaxError {
+        SyntaxError { position: self.tokens[self.pos].1, message }
+    }
+
+    fn expr(&mut self) -> Result<(), SyntaxError> {
+        self.and_expr()?;
+        while self.eat_name("or") {
+            self.and_expr()?;
+        }
+        Ok(())
+    }
+
+    fn and_expr(&mut self) -> Result<(), SyntaxError> {
+        self.equality_expr()?;
+        while self.eat_name("and") {
+            self.equality_expr()?;
+        }
+        Ok(())
+    }
+
+    fn equality_expr(&mut self) -> Result<(), SyntaxError> {
+        self.relational_expr()?;
+        while self.eat(&Token::Eq) || self.eat(&Token::NotEq) {
+            self.relational_expr()?;
+        }
+        Ok(())
+    }
+
+    fn relational_expr(&mut self) -> Result<(), SyntaxError> {
+        self.additive_expr()?;
+        while self.eat(&Token::Lt) || self.eat(&Token::LtEq)
+            || self.eat(&Token::Gt) || self.eat(&Token::GtEq) {
+            self.additive_expr()?;
+        }
+        Ok(())
+    }
+
+    fn additive_expr(&mut self) -> Result<(), SyntaxError> {
+        self.multiplicative_expr()?;
+        while self.eat(&Token::Plus) || self.eat(&Token::Minus) {
+            self.multiplicative_expr()?;
+        }
+        Ok(())
+    }
+
+    fn multiplicative_expr(&mut self) -> Result<(), SyntaxError> {
+        self.unary_expr()?;
+        while self.eat(&Token::Star) || self.eat_name("div") || self.eat_name("mod") {
+            self.unary_expr()?;
+        }
+        Ok(())
+    }
+
+    fn unary_expr(&mut self) -> Result<(), SyntaxError> {
+        while self.eat(&Token::Minus) {}
+        self.path_expr()?;
+        while self.eat(&Token::Pipe) {
+            self.path_expr()?;
+        }
+        Ok(())
+    }
+
+    fn path_expr(&mut self) -> Result<(), SyntaxError> {
+        let filter = match *self.peek() {
+            Token::Variable | Token::LParen | Token::Literal | Token::Number => true,
+            Token::Name(ref name) =>
+                *self.peek_at(1) == Token::LParen && !NODE_TYPES.contains(&name.as_str()),
+            _ => false,
+        };
+        if filter {
+            self.primary_expr()?;
+            while *self.peek() == Token::LBracket {
+                self.predicate()?;
+            }
+            if self.eat(&Token::Slash) || self.eat(&Token::DoubleSlash) {
+                self.relative_location_path()?;
+            }
+            return Ok(());
+        }
+        if self.eat(&Token::Slash) {
+            // A lone `/` selects the root node.
+            if self.starts_step() {
+                self.relative_location_path()?;
+            }
+            Ok(())
+        } else {
+            self.eat(&Token::DoubleSlash);
+            self.relative_location_path()
+        }
+    }
+
+    fn starts_step(&self) -> bool {
+        matches!(*self.peek(),
+                 Token::Dot | Token::DotDot | Token::At | Token::Star | Token::Name(_))
+    }
+
+    fn primary_expr(&mut self) -> Result<(), SyntaxError> {
+        match *self.peek() {
+            Token::Variable | Token::Literal | Token::Number => {
+                self.advance();
+                Ok(())
+            }
+            Token::LParen => {
+                self.advance();
+                self.expr()?;
+                self.expect(Token::RParen)
+            }
+            _ => self.function_call(),
+        }
+    }
+
+    fn function_call(&mut self) -> Result<(), SyntaxError> {
+        let start = self.pos;
+        let name = match *self.peek() {
+            Token::Name(ref name) => name.clone(),
+            _ => return Err(self.unexpected("a function name")),
+        };
+        let &(_, min, max) = FUNCTIONS.iter().find(|f| f.0 == name)
+            .ok_or_else(|| self.error(format!("unknown function `{}`", name)))?;
+        self.advance();
+        self.expect(Token::LParen)?;
+        let mut args = 0;
+        if !self.eat(&Token::RParen) {
+            loop {
+                self.expr()?;
+                args += 1;
+                if !self.eat(&Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(Token::RParen)?;
+        }
+        if args < min || args > max {
+            let expected = if min == max {
+                format!("{}", min)
+            } else if max == usize::MAX {
+                format!("at least {}", min)
+            } else {
+                format!("{} to {}", min, max)
+            };
+            return Err(SyntaxError {
+                position: self.tokens[start].1,
+                message: format!("`{}` takes {} arguments, found {}", name, expected, args),
+            });
+        }
+        Ok(())
+    }
+
+    fn relative_location_path(&mut self) -> Result<(), SyntaxError> {
+        self.step()?;
+        while self.eat(&Token::Slash) || self.eat(&Token::DoubleSlash) {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<(), SyntaxError> {
+        if self.eat(&Token::Dot) || self.eat(&Token::DotDot) {
+            return Ok(());
+        }
+        if !self.eat(&Token::At) {
+            if let Token::Name(ref name) = *self.peek() {
+                if *self.peek_at(1) == Token::ColonColon {
+                    if !AXES.contains(&name.as_str()) {
+                        return Err(self.error(format!("unknown axis `{}`", name)));
+                    }
+                    self.advance();
+                    self.advance();
+                }
+            }
+        }
+        self.node_test()?;
+        while *self.peek() == Token::LBracket {
+            self.predicate()?;
+        }
+        Ok(())
+    }
+
+    fn node_test(&mut self) -> Result<(), SyntaxError> {
+        let name = match *self.peek() {
+            Token::Star => {
+                self.advance();
+                return Ok(());
+            }
+            Token::Name(ref name) => name.clone(),
+            _ => return Err(self.unexpected("a node test")),
+        };
+        self.advance();
+        if *self.peek() == Token::LParen {
+            if !NODE_TYPES.contains(&name.as_str()) {
+                return Err(self.error(format!("unknown node type `{}`", name)));
+            }
+            self.advance();
+            if name == "processing-instruction" {
+                self.eat(&Token::Literal);
+            }
+            self.expect(Token::RParen)?;
+        }
+        Ok(())
+    }
+
+    fn predicate(&mut self) -> Result<(), SyntaxError> {
+        self.expect(Token::LBracket)?;
+        self.expr()?;
+        self.expect(Token::RBracket)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate;
+
+    fn error(expr: &str) -> (usize, String) {
+        let err = validate(expr).unwrap_err();
+        (err.position, err.message)
+    }
+
+    #[test]
+    fn accepts_xpath_1() {
+        for expr in &[
+            "//div",
+            "/",
+            ".",
+            "//a[@href and not(@target)]",
+            "//ul/li[position() mod 2 = 1][last()]",
+            "(//p)[2]/following-sibling::*[1]",
+            "//*[text()[contains(., 'a \"b\"')]]",
+            "//svg:g | //html:div/@class",
+            "count(//li) * -2 div 3 >= 1.5",
+            "ancestor-or-self::node()/processing-instruction('x')",
+            "id('main')//a[$var != .5]",
+            "//div[@id='a']/..",
+        ] {
+            assert!(validate(expr).is_ok(), "{} should be valid", expr);
+        }
+    }
+
+    #[test]
+    fn reports_the_bad_token() {
+        assert_eq!(error("//div[@id='a'"), (13, "expected `]`, found end of expression".to_owned()));
+        assert_eq!(error("//div]"), (5, "expected an operator, found `]`".to_owned()));
+        assert_eq!(error("//a[contain(., 'x')]"), (4, "unknown function `contain`".to_owned()));
+        assert_eq!(error("child::[1]"), (7, "expected a node test, found `[`".to_owned()));
+        assert_eq!(error("//a[text() = 'x]"), (13, "unterminated string".to_owned()));
+        assert_eq!(error("sibling::p"), (0, "unknown axis `sibling`".to_owned()));
+        assert_eq!(error("//a[starts-with(@href)]").1, "`starts-with` takes 2 arguments, found 1");
+        assert_eq!(error("//p/texts()").1, "unknown node type `texts`");
+        assert_eq!(error("").1, "expected a node test, found end of expression");
+        assert_eq!(error("//a#b").0, 3);
+    }
+
+    /// The fake browser's evaluator must accept a subset of what `xpath!`
+    /// accepts, with the same argument counts.
+    #[test]
+    fn agrees_with_the_fake_browser() {
+        use scraper::Html;
+        use webdriver_client::fake::xpath::{self, FUNCTIONS as FAKE_FUNCTIONS};
+
+        for function in FAKE_FUNCTIONS {
+            assert!(super::FUNCTIONS.contains(function), "{:?} differs from xpath!", function);
+        }
+
+        let html = Html::parse_document("<p id='a'>one two</p><p id='b'>three</p>");
+        let root = html.tree.root().id();
+        for expr in &[
+            "//p[contains(., 'two')]",
+            "//p[starts-with(@id, 'a')]",
+            "//p[concat(@id, 'x', 'y') = 'axy']",
+            "//p[string-length(normalize-space()) > 3][last()]",
+            "//p[substring(translate(@id, 'a', 'b'), 1, 1) = 'b']",
+            "//p[not(boolean(number(@id)))]",
+        ] {
+            assert!(validate(expr).is_ok(), "xpath! rejects {}", expr);
+            assert!(xpath::select(&html, root, expr).is_ok(), "the fake browser rejects {}", expr);
+        }
+        for expr in &[
+            "//p[ends-with(@id, 'a')]",
+            "//p[matches(@id, 'a')]",
+            "//p[contains(@id)]",
+            "//p[concat(@id)]",
+            "//p[substring(@id)]",
+        ] {
+            assert!(validate(expr).is_err(), "xpath! accepts {}", expr);
+            assert!(xpath::select(&html, root, expr).is_err(), "the fake browser accepts {}", expr);
+        }
+    }
+}